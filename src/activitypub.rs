@@ -0,0 +1,620 @@
+use crate::{
+    auth, escape_html,
+    feed::{self, VirtualFeed},
+    render::Channel,
+    store::Store,
+    Article, Feed,
+};
+use anyhow::*;
+use chrono::{DateTime, Utc};
+use fnv::FnvHasher;
+use reqwest::{
+    header::{HeaderMap, ACCEPT, CONTENT_TYPE},
+    Client, Url,
+};
+use ring::{
+    digest::{digest, SHA256},
+    rand::SystemRandom,
+    signature::{
+        KeyPair, RsaKeyPair, UnparsedPublicKey, RSA_PKCS1_2048_8192_SHA256, RSA_PKCS1_SHA256,
+    },
+};
+use serde_json::{json, Value};
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hasher,
+    net::IpAddr,
+    sync::OnceLock,
+};
+use tokio::net::lookup_host;
+use tracing::{instrument, trace, warn};
+
+pub const ACTIVITY_JSON: &str = "application/activity+json";
+const ACTIVITY_STREAMS: &str = "https://www.w3.org/ns/activitystreams";
+const PUBLIC: &str = "https://www.w3.org/ns/activitystreams#Public";
+const OUTBOX_SIZE: usize = 20;
+const MAX_CLOCK_SKEW: i64 = 5 * 60;
+// Without all of these a signature could be replayed to another inbox, or with another body.
+const SIGNED_HEADERS: [&str; 4] = ["(request-target)", "host", "date", "digest"];
+// The DER AlgorithmIdentifier for rsaEncryption (1.2.840.113549.1.1.1) with NULL parameters.
+const RSA_ENCRYPTION: &[u8] = &[
+    0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01, 0x05, 0x00,
+];
+
+#[derive(Debug)]
+pub struct ActivityPub {
+    pub base: String,
+    pub host: String,
+    key: RsaKeyPair,
+    public_key_pem: String,
+}
+
+static ACTIVITYPUB: OnceLock<ActivityPub> = OnceLock::new();
+
+pub fn configure(activitypub: ActivityPub) {
+    ACTIVITYPUB.set(activitypub).ok();
+}
+
+pub fn get() -> Option<&'static ActivityPub> {
+    ACTIVITYPUB.get()
+}
+
+/// The actor name for a feed, restricted to what Mastodon accepts in a handle.
+pub fn username(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Anyone can read an actor's outbox and follow it to have articles delivered, neither of which
+/// can ask for credentials, so protected feeds aren't published as actors.
+fn published(name: &str, virtual_feeds: &[VirtualFeed]) -> bool {
+    auth::get().is_none_or(|auth| !auth.protects(Some(name), virtual_feeds))
+}
+
+/// The feed published as the actor with this username.
+pub fn actor_feed<'a>(
+    feeds: &'a HashMap<String, Feed>,
+    username: &str,
+    virtual_feeds: &[VirtualFeed],
+) -> Option<(&'a str, &'a Feed)> {
+    feeds
+        .iter()
+        .find(|(name, _)| self::username(name) == username)
+        .filter(|(name, _)| published(name, virtual_feeds))
+        .map(|(name, feed)| (name.as_str(), feed))
+}
+
+fn pem(label: &str, der: &[u8]) -> String {
+    let body = base64::encode(der)
+        .as_bytes()
+        .chunks(64)
+        .map(|line| std::str::from_utf8(line).unwrap())
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!("-----BEGIN {0}-----\n{1}\n-----END {0}-----\n", label, body)
+}
+
+fn unpem(pem: &str) -> Result<(String, Vec<u8>)> {
+    let label = pem
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("-----BEGIN "))
+        .and_then(|line| line.strip_suffix("-----"))
+        .context("Expected a PEM block")?;
+    let body = pem
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with("-----"))
+        .collect::<String>();
+    Ok((label.to_owned(), base64::decode(body)?))
+}
+
+fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut der = vec![tag];
+    if content.len() < 0x80 {
+        der.push(content.len() as u8);
+    } else {
+        let length = content.len().to_be_bytes();
+        let length = &length[length.iter().take_while(|&&byte| byte == 0).count()..];
+        der.push(0x80 | length.len() as u8);
+        der.extend(length);
+    }
+    der.extend(content);
+    der
+}
+
+fn read_der(input: &[u8]) -> Result<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = input.split_first().context("Truncated DER")?;
+    let (&first, rest) = rest.split_first().context("Truncated DER")?;
+    let (length, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let bytes = (first & 0x7f) as usize;
+        ensure!(bytes <= 4 && rest.len() >= bytes, "Invalid DER length");
+        let length = rest[..bytes]
+            .iter()
+            .fold(0, |length, &byte| length << 8 | byte as usize);
+        (length, &rest[bytes..])
+    };
+    ensure!(rest.len() >= length, "Truncated DER");
+    Ok((tag, &rest[..length], &rest[length..]))
+}
+
+/// Wraps a PKCS#1 RSA public key in the SubjectPublicKeyInfo that `BEGIN PUBLIC KEY` expects.
+fn spki(pkcs1: &[u8]) -> Vec<u8> {
+    let mut bits = vec![0];
+    bits.extend(pkcs1);
+    let mut content = RSA_ENCRYPTION.to_vec();
+    content.extend(der(0x03, &bits));
+    der(0x30, &content)
+}
+
+fn pkcs1(public_key_pem: &str) -> Result<Vec<u8>> {
+    let (label, der) = unpem(public_key_pem)?;
+    if label == "RSA PUBLIC KEY" {
+        return Ok(der);
+    }
+    let (tag, content, _) = read_der(&der)?;
+    ensure!(tag == 0x30, "Expected a SubjectPublicKeyInfo");
+    let (_, algorithm, rest) = read_der(content)?;
+    ensure!(
+        algorithm == &RSA_ENCRYPTION[2..],
+        "Only RSA keys are supported"
+    );
+    let (tag, bits, _) = read_der(rest)?;
+    ensure!(
+        tag == 0x03 && bits.first() == Some(&0),
+        "Invalid public key"
+    );
+    Ok(bits[1..].to_vec())
+}
+
+fn hash(id: &str) -> u64 {
+    let mut hasher = FnvHasher::default();
+    hasher.write(id.as_bytes());
+    hasher.finish()
+}
+
+fn authority(url: &Url) -> Result<String> {
+    let host = url.host_str().context("URL has no host")?;
+    Ok(match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_owned(),
+    })
+}
+
+/// A follower's inbox, which has to be on the follower's own server so a follow can't point
+/// deliveries somewhere else.
+fn follower_inbox(actor: &str, inbox: &str) -> Result<Url> {
+    let inbox = inbox.parse::<Url>()?;
+    ensure!(inbox.scheme() == "https", "Inbox {} isn't HTTPS", inbox);
+    ensure!(
+        inbox.origin() == Url::parse(actor)?.origin(),
+        "Inbox {} is on another server",
+        inbox
+    );
+    Ok(inbox)
+}
+
+/// Whether an address is only reachable from inside this network, which deliveries never go to.
+pub fn internal(address: IpAddr) -> bool {
+    match address {
+        IpAddr::V4(address) => {
+            address.is_loopback()
+                || address.is_private()
+                || address.is_link_local()
+                || address.is_unspecified()
+                || address.is_broadcast()
+        }
+        IpAddr::V6(address) => match address.to_ipv4_mapped() {
+            Some(address) => internal(IpAddr::V4(address)),
+            None => {
+                address.is_loopback()
+                    || address.is_unspecified()
+                    || address.is_unique_local()
+                    || address.is_unicast_link_local()
+            }
+        },
+    }
+}
+
+fn signature_parameters(signature: &str) -> HashMap<&str, &str> {
+    signature
+        .split(',')
+        .filter_map(|parameter| parameter.split_once('='))
+        .map(|(name, value)| (name.trim(), value.trim().trim_matches('"')))
+        .collect()
+}
+
+async fn fetch(client: &Client, url: &str) -> Result<Value> {
+    let text = client
+        .get(url)
+        .header(ACCEPT, ACTIVITY_JSON)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    serde_json::from_str(&text).with_context(|| format!("Invalid ActivityPub document at {}", url))
+}
+
+impl ActivityPub {
+    pub fn new(public_url: &str, private_key_pem: &str) -> Result<ActivityPub> {
+        let (label, der) = unpem(private_key_pem)?;
+        let key = match label.as_str() {
+            "PRIVATE KEY" => RsaKeyPair::from_pkcs8(&der),
+            "RSA PRIVATE KEY" => RsaKeyPair::from_der(&der),
+            _ => bail!("Unsupported private key type {:?}", label),
+        }
+        .map_err(|error| anyhow!("Invalid RSA private key: {}", error))?;
+        let base = public_url.trim_end_matches('/').to_owned();
+        let host = Url::parse(&base)?
+            .host_str()
+            .context("Public URL has no host")?
+            .to_owned();
+        Ok(ActivityPub {
+            public_key_pem: pem("PUBLIC KEY", &spki(key.public_key().as_ref())),
+            key,
+            host,
+            base,
+        })
+    }
+
+    pub fn actor_id(&self, username: &str) -> String {
+        format!("{}/ap/{}", self.base, username)
+    }
+
+    /// The username a WebFinger `acct:` or actor URL resource refers to.
+    pub fn resource(&self, resource: &str) -> Option<String> {
+        if let Some(username) = resource.strip_prefix(&format!("{}/ap/", self.base)) {
+            return Some(username.to_owned());
+        }
+        let (username, host) = resource.strip_prefix("acct:")?.rsplit_once('@')?;
+        host.eq_ignore_ascii_case(&self.host)
+            .then(|| username.trim_start_matches('@').to_lowercase())
+    }
+
+    pub fn webfinger(&self, username: &str) -> Value {
+        let actor = self.actor_id(username);
+        json!({
+            "subject": format!("acct:{}@{}", username, self.host),
+            "aliases": [actor],
+            "links": [{ "rel": "self", "type": ACTIVITY_JSON, "href": actor }],
+        })
+    }
+
+    pub fn actor(&self, username: &str, channel: &Channel) -> Value {
+        let id = self.actor_id(username);
+        json!({
+            "@context": [ACTIVITY_STREAMS, "https://w3id.org/security/v1"],
+            "id": id,
+            "type": "Service",
+            "preferredUsername": username,
+            "name": channel.title,
+            "summary": channel.description.as_deref().map(escape_html),
+            "url": channel.link,
+            "inbox": format!("{}/inbox", id),
+            "outbox": format!("{}/outbox", id),
+            "followers": format!("{}/followers", id),
+            "manuallyApprovesFollowers": false,
+            "discoverable": true,
+            "publicKey": {
+                "id": format!("{}#main-key", id),
+                "owner": id,
+                "publicKeyPem": self.public_key_pem,
+            },
+        })
+    }
+
+    fn note(&self, username: &str, article: &Article) -> Value {
+        let actor = self.actor_id(username);
+        let mut content = format!(
+            r#"<p><a href="{}">{}</a></p>"#,
+            escape_html(article.link.as_str()),
+            escape_html(&article.headline)
+        );
+        let summary = article.summary();
+        if !summary.is_empty() {
+            content.push_str(&format!("<p>{}</p>", escape_html(&summary)));
+        }
+        json!({
+            "id": format!("{}/notes/{:016x}", actor, hash(article.id())),
+            "type": "Note",
+            "attributedTo": actor,
+            "published": article.date.to_rfc3339(),
+            "updated": article.updated.map(|updated| updated.to_rfc3339()),
+            "url": article.link.as_str(),
+            "to": [PUBLIC],
+            "cc": [format!("{}/followers", actor)],
+            "content": content,
+            "attachment": article.image.iter().map(|image| json!({
+                "type": "Document",
+                "mediaType": image.mime_type,
+                "url": image.url.as_str(),
+            })).collect::<Vec<_>>(),
+        })
+    }
+
+    pub fn create(&self, username: &str, article: &Article) -> Value {
+        let note = self.note(username, article);
+        json!({
+            "@context": ACTIVITY_STREAMS,
+            "id": format!("{}/activity", note["id"].as_str().unwrap()),
+            "type": "Create",
+            "actor": self.actor_id(username),
+            "published": note["published"],
+            "to": note["to"],
+            "cc": note["cc"],
+            "object": note,
+        })
+    }
+
+    pub fn outbox(&self, username: &str, articles: &[Article]) -> Value {
+        json!({
+            "@context": ACTIVITY_STREAMS,
+            "id": format!("{}/outbox", self.actor_id(username)),
+            "type": "OrderedCollection",
+            "totalItems": articles.len(),
+            "orderedItems": articles
+                .iter()
+                .take(OUTBOX_SIZE)
+                .map(|article| self.create(username, article))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    pub fn followers(&self, username: &str, count: usize) -> Value {
+        json!({
+            "@context": ACTIVITY_STREAMS,
+            "id": format!("{}/followers", self.actor_id(username)),
+            "type": "OrderedCollection",
+            "totalItems": count,
+        })
+    }
+
+    #[instrument(skip(self, client, activity), fields(inbox = inbox.as_str()))]
+    pub async fn deliver(
+        &self,
+        client: &Client,
+        username: &str,
+        inbox: &Url,
+        activity: &Value,
+    ) -> Result<()> {
+        let host = inbox.host_str().context("URL has no host")?;
+        let port = inbox.port_or_known_default().unwrap_or(443);
+        let addresses = lookup_host((host.trim_matches(['[', ']']), port)).await?;
+        for address in addresses {
+            ensure!(
+                !internal(address.ip()),
+                "Inbox {} is on an internal address",
+                inbox
+            );
+        }
+        let body = activity.to_string();
+        let date = Utc::now().format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+        let digest = format!(
+            "SHA-256={}",
+            base64::encode(digest(&SHA256, body.as_bytes()))
+        );
+        let target = match inbox.query() {
+            Some(query) => format!("post {}?{}", inbox.path(), query),
+            None => format!("post {}", inbox.path()),
+        };
+        let signed = format!(
+            "(request-target): {}\nhost: {}\ndate: {}\ndigest: {}",
+            target,
+            authority(inbox)?,
+            date,
+            digest
+        );
+        let mut signature = vec![0; self.key.public_modulus_len()];
+        self.key
+            .sign(
+                &RSA_PKCS1_SHA256,
+                &SystemRandom::new(),
+                signed.as_bytes(),
+                &mut signature,
+            )
+            .map_err(|_| anyhow!("Failed to sign request"))?;
+        client
+            .post(inbox.clone())
+            .header(CONTENT_TYPE, ACTIVITY_JSON)
+            .header("date", date)
+            .header("digest", digest)
+            .header(
+                "signature",
+                format!(
+                    r#"keyId="{}#main-key",algorithm="rsa-sha256",headers="(request-target) host date digest",signature="{}""#,
+                    self.actor_id(username),
+                    base64::encode(signature)
+                ),
+            )
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?;
+        trace!("Delivered activity");
+        Ok(())
+    }
+
+    /// Checks a request's HTTP signature, returning the ID and document of the actor who signed it.
+    async fn verify(
+        &self,
+        client: &Client,
+        target: &str,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<(String, Value)> {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .with_context(|| format!("Missing {} header", name))
+        };
+        let parameters = signature_parameters(header("signature")?);
+        let key_id = parameters.get("keyId").context("Signature has no keyId")?;
+        let names = parameters.get("headers").copied().unwrap_or("date");
+        let signature = base64::decode(
+            parameters
+                .get("signature")
+                .context("Signature has no signature")?,
+        )?;
+        let names = names.split_whitespace().collect::<Vec<_>>();
+        if let Some(missing) = SIGNED_HEADERS.iter().find(|name| !names.contains(name)) {
+            bail!("Signature does not cover {}", missing);
+        }
+        let expected = format!("SHA-256={}", base64::encode(digest(&SHA256, body)));
+        ensure!(header("digest")? == expected, "Digest does not match body");
+        let date = DateTime::parse_from_rfc2822(header("date")?.replace("GMT", "+0000").as_str())?;
+        ensure!(
+            (Utc::now().timestamp() - date.timestamp()).abs() < MAX_CLOCK_SKEW,
+            "Signature date is too far from the current time"
+        );
+        let signed = names
+            .iter()
+            .map(|name| match *name {
+                "(request-target)" => Ok(format!("(request-target): {}", target)),
+                name => Ok(format!("{}: {}", name, header(name)?)),
+            })
+            .collect::<Result<Vec<_>>>()?
+            .join("\n");
+        let key_url = key_id.split('#').next().unwrap_or(key_id);
+        let key = fetch(client, key_url).await?;
+        let actor = key["publicKey"]["owner"]
+            .as_str()
+            .or_else(|| key["owner"].as_str())
+            .or_else(|| key["id"].as_str())
+            .context("Public key has no owner")?
+            .to_owned();
+        // Anyone can publish a key claiming any owner, so the owner has to be on the key's
+        // server and list the key as its own.
+        ensure!(
+            Url::parse(&actor)?.origin() == Url::parse(key_id)?.origin(),
+            "Public key is owned by an actor on another server"
+        );
+        let document = if key["id"].as_str() == Some(actor.as_str()) {
+            key
+        } else {
+            fetch(client, &actor).await?
+        };
+        ensure!(
+            document["id"].as_str() == Some(actor.as_str()),
+            "Signing actor has another id"
+        );
+        let public_key = &document["publicKey"];
+        ensure!(
+            public_key["id"].as_str() == Some(*key_id),
+            "Signing actor doesn't own the key"
+        );
+        let pem = public_key["publicKeyPem"]
+            .as_str()
+            .context("Signing actor has no public key")?;
+        UnparsedPublicKey::new(&RSA_PKCS1_2048_8192_SHA256, pkcs1(pem)?)
+            .verify(signed.as_bytes(), &signature)
+            .map_err(|_| anyhow!("Invalid signature"))?;
+        Ok((actor, document))
+    }
+
+    /// Handles an activity POSTed to a feed's inbox: follows are stored and accepted, and
+    /// undone follows removed. Anything else is ignored.
+    #[instrument(skip(self, client, store, headers, body))]
+    pub async fn receive(
+        &self,
+        client: &Client,
+        store: &Store,
+        feed: &str,
+        target: &str,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<()> {
+        let (signer, document) = self.verify(client, target, headers, body).await?;
+        let activity: Value = serde_json::from_slice(body).context("Invalid activity")?;
+        let actor = activity["actor"]
+            .as_str()
+            .context("Activity has no actor")?;
+        ensure!(actor == signer, "Activity was signed by another actor");
+        let username = username(feed);
+        match activity["type"].as_str() {
+            Some("Follow") => {
+                ensure!(
+                    activity["object"].as_str() == Some(self.actor_id(&username).as_str()),
+                    "Follow is for another actor"
+                );
+                let inbox = follower_inbox(
+                    actor,
+                    document["inbox"]
+                        .as_str()
+                        .context("Follower has no inbox")?,
+                )?;
+                let shared = document["endpoints"]["sharedInbox"]
+                    .as_str()
+                    .and_then(|shared| follower_inbox(actor, shared).ok())
+                    .unwrap_or_else(|| inbox.clone());
+                store.add_follower(feed, actor, &shared)?;
+                let accept = json!({
+                    "@context": ACTIVITY_STREAMS,
+                    "id": format!(
+                        "{}#accepts/{:016x}",
+                        self.actor_id(&username),
+                        hash(activity["id"].as_str().unwrap_or(actor))
+                    ),
+                    "type": "Accept",
+                    "actor": self.actor_id(&username),
+                    "object": activity,
+                });
+                self.deliver(client, &username, &inbox, &accept).await?;
+                trace!(actor, "Accepted follower");
+            }
+            Some("Undo") if activity["object"]["type"] == "Follow" => {
+                store.remove_follower(feed, actor)?;
+                trace!(actor, "Removed follower");
+            }
+            kind => trace!(?kind, "Ignoring activity"),
+        }
+        Ok(())
+    }
+}
+
+/// Delivers a Create activity for each new article to everyone following the feed or the
+/// combined feed.
+#[instrument(skip(client, store, articles), fields(articles = articles.len()))]
+pub async fn publish(client: &Client, store: Option<&Store>, name: &str, articles: &[Article]) {
+    let (activitypub, store) = match (get(), store) {
+        (Some(activitypub), Some(store)) => (activitypub, store),
+        _ => return,
+    };
+    // Only sources and the combined feed are delivered, so there are no virtual feeds to check.
+    for feed in [name, feed::COMBINED]
+        .iter()
+        .filter(|&&feed| published(feed, &[]))
+    {
+        let inboxes = match store.followers(feed) {
+            Ok(followers) => followers
+                .into_iter()
+                .map(|(_, inbox)| inbox)
+                .collect::<HashSet<_>>(),
+            Err(error) => {
+                warn!(?error, feed, "Failed to load followers");
+                continue;
+            }
+        };
+        let username = username(feed);
+        for article in articles.iter().rev() {
+            let activity = activitypub.create(&username, article);
+            for inbox in &inboxes {
+                if let Err(error) = activitypub
+                    .deliver(client, &username, inbox, &activity)
+                    .await
+                {
+                    warn!(?error, inbox = inbox.as_str(), "Failed to deliver article");
+                }
+            }
+        }
+    }
+}
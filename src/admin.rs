@@ -0,0 +1,158 @@
+use crate::load;
+use anyhow::*;
+use news_rss::feed;
+use ring::constant_time::verify_slices_are_equal;
+use std::{fs, sync::OnceLock};
+use tokio::sync::{Mutex, Notify};
+use toml::{Table, Value};
+use tracing::{info, instrument};
+
+/// Edits the `[[scraper]]` tables of the config file, then asks for a reload. The file is
+/// rewritten from the parsed TOML, so its comments and formatting are lost on the first edit.
+#[derive(Debug)]
+pub struct Admin {
+    pub token: &'static str,
+    pub config: &'static str,
+    pub reload: &'static Notify,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    Done,
+    NotFound,
+    Conflict,
+}
+
+static ADMIN: OnceLock<Admin> = OnceLock::new();
+
+pub fn configure(admin: Admin) {
+    ADMIN.set(admin).ok();
+}
+
+pub fn get() -> Option<&'static Admin> {
+    ADMIN.get()
+}
+
+/// Feed status, article previews and a scraper editor, all loaded through the admin API.
+pub const PAGE: &str = include_str!("admin.html");
+
+// Held from reading the config to replacing it, so concurrent edits can't drop each other.
+static EDITING: Mutex<()> = Mutex::const_new(());
+
+fn name(scraper: &Table) -> Result<&str> {
+    scraper
+        .get("name")
+        .and_then(Value::as_str)
+        .context("Expected a scraper name")
+}
+
+/// The other tables whose names share a namespace with scrapers.
+fn others(config: &Table) -> impl Iterator<Item = &Table> {
+    ["feed", "api", "plugin", "virtual_feed"]
+        .iter()
+        .filter_map(move |kind| config.get(*kind).and_then(Value::as_array))
+        .flatten()
+        .filter_map(Value::as_table)
+}
+
+fn split(mut config: Table) -> Result<(Table, Vec<Table>)> {
+    let scrapers = match config.remove("scraper") {
+        Some(Value::Array(scrapers)) => scrapers
+            .into_iter()
+            .map(|scraper| match scraper {
+                Value::Table(scraper) => Ok(scraper),
+                _ => bail!("Expected [[scraper]] tables"),
+            })
+            .collect::<Result<_>>()?,
+        Some(_) => bail!("Expected [[scraper]] tables"),
+        None => Vec::new(),
+    };
+    Ok((config, scrapers))
+}
+
+impl Admin {
+    pub fn authorized(&self, token: Option<&str>) -> bool {
+        !self.token.is_empty()
+            && token.is_some_and(|token| {
+                verify_slices_are_equal(token.as_bytes(), self.token.as_bytes()).is_ok()
+            })
+    }
+
+    fn read(&self) -> Result<(Table, Vec<Table>)> {
+        let config = fs::read_to_string(self.config)
+            .with_context(|| format!("Failed to read config {:?}", self.config))?;
+        split(toml::from_str(&config).context("Failed to parse config")?)
+    }
+
+    pub async fn scrapers(&self) -> Result<Vec<Table>> {
+        let _editing = EDITING.lock().await;
+        Ok(self.read()?.1)
+    }
+
+    /// Adds a scraper when `name` is `None`, otherwise replaces the scraper called `name`, or
+    /// removes it when `scraper` is `None`. A replacement without a name keeps the old one. The
+    /// new config is only written once it loads.
+    #[instrument(skip(self, scraper))]
+    pub async fn edit(&self, name: Option<&str>, mut scraper: Option<Table>) -> Result<Edit> {
+        let _editing = EDITING.lock().await;
+        let (mut config, mut scrapers) = self.read()?;
+        let index = match name {
+            Some(name) => match scrapers.iter().position(|other| {
+                self::name(other).is_ok_and(|other| other.eq_ignore_ascii_case(name))
+            }) {
+                Some(index) => Some(index),
+                None => return Ok(Edit::NotFound),
+            },
+            None => None,
+        };
+        if let (Some(scraper), Some(index)) = (&mut scraper, index) {
+            let name = scrapers[index]["name"].clone();
+            scraper.entry("name").or_insert(name);
+        }
+        if let Some(scraper) = &scraper {
+            let added = self::name(scraper)?;
+            let taken = added.eq_ignore_ascii_case(feed::COMBINED)
+                || scrapers
+                    .iter()
+                    .enumerate()
+                    .filter(|(other, _)| Some(*other) != index)
+                    .map(|(_, table)| table)
+                    .chain(others(&config))
+                    .any(|table| {
+                        self::name(table).is_ok_and(|other| other.eq_ignore_ascii_case(added))
+                    });
+            if taken {
+                return Ok(Edit::Conflict);
+            }
+        }
+        match (index, scraper) {
+            (None, Some(scraper)) => scrapers.push(scraper),
+            (Some(index), Some(scraper)) => scrapers[index] = scraper,
+            (Some(index), None) => {
+                scrapers.remove(index);
+            }
+            (None, None) => bail!("Expected a scraper to add"),
+        }
+        config.insert(
+            "scraper".to_owned(),
+            Value::Array(scrapers.into_iter().map(Value::Table).collect()),
+        );
+        self.replace(&toml::to_string(&config)?)?;
+        info!("Edited scrapers");
+        self.reload.notify_one();
+        Ok(Edit::Done)
+    }
+
+    // The candidate is loaded like any config before it is renamed over the real one, so a
+    // reload never reads a broken or partial file.
+    fn replace(&self, config: &str) -> Result<()> {
+        let temporary = format!("{}.tmp", self.config);
+        fs::write(&temporary, config).with_context(|| format!("Failed to write {}", temporary))?;
+        if let Err(error) = load(&temporary) {
+            fs::remove_file(&temporary).ok();
+            return Err(error);
+        }
+        fs::rename(&temporary, self.config)
+            .with_context(|| format!("Failed to replace config {:?}", self.config))
+    }
+}
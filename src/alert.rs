@@ -0,0 +1,108 @@
+use anyhow::*;
+use chrono::{DateTime, Utc};
+use reqwest::{header::CONTENT_TYPE, Client, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{str::FromStr, sync::OnceLock};
+use tracing::{instrument, trace, warn};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookFormat {
+    #[default]
+    Generic,
+    Slack,
+    Discord,
+}
+
+impl FromStr for WebhookFormat {
+    type Err = Error;
+
+    fn from_str(format: &str) -> Result<Self> {
+        match format {
+            "generic" => Ok(WebhookFormat::Generic),
+            "slack" => Ok(WebhookFormat::Slack),
+            "discord" => Ok(WebhookFormat::Discord),
+            _ => bail!("Unknown webhook format {:?}", format),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    pub url: Url,
+    pub format: WebhookFormat,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum Event {
+    Failing { failures: u32, error: String },
+    Empty,
+    Recovered,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert<'a> {
+    pub feed: &'a str,
+    #[serde(flatten)]
+    pub event: Event,
+    pub at: DateTime<Utc>,
+}
+
+static WEBHOOK: OnceLock<Webhook> = OnceLock::new();
+
+pub fn configure(webhook: Webhook) {
+    WEBHOOK.set(webhook).ok();
+}
+
+impl Alert<'_> {
+    pub fn new(feed: &str, event: Event) -> Alert<'_> {
+        Alert {
+            feed,
+            event,
+            at: Utc::now(),
+        }
+    }
+
+    fn message(&self) -> String {
+        match &self.event {
+            Event::Failing { failures, error } => format!(
+                "{} has failed {} consecutive scrapes: {}",
+                self.feed, failures, error
+            ),
+            Event::Empty => format!(
+                "{} returned no articles; its selectors may be out of date",
+                self.feed
+            ),
+            Event::Recovered => format!("{} is scraping successfully again", self.feed),
+        }
+    }
+}
+
+#[instrument(skip(client))]
+pub async fn send(client: &Client, alert: Alert<'_>) {
+    let webhook = match WEBHOOK.get() {
+        Some(webhook) => webhook,
+        None => return,
+    };
+    let body = match webhook.format {
+        WebhookFormat::Generic => json!({ "message": alert.message(), "alert": alert }),
+        WebhookFormat::Slack => json!({ "text": alert.message() }),
+        WebhookFormat::Discord => json!({ "content": alert.message() }),
+    };
+    let sent = async {
+        client
+            .post(webhook.url.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.to_string())
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    };
+    match sent.await {
+        Ok(()) => trace!("Sent alert"),
+        Err::<_, Error>(error) => warn!(?error, "Failed to send alert"),
+    }
+}
@@ -0,0 +1,210 @@
+use crate::{
+    canonical, jsonpath::JsonPath, metrics, render::Channel, retry, store::Store, Adaptive,
+    Article, DateParser, Image, Retention, RetryPolicy, Scraped, Source, Stagger,
+};
+use anyhow::*;
+use async_trait::async_trait;
+use chrono::{DateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use reqwest::{header::HeaderMap, Client, Url};
+use serde_json::Value;
+use std::{borrow::Cow, time::Duration};
+use tracing::{instrument, trace, warn};
+
+#[derive(Debug)]
+pub struct JsonApi {
+    pub name: Cow<'static, str>,
+    pub description: Option<Cow<'static, str>>,
+    pub language: Option<Cow<'static, str>>,
+    pub link: Option<Cow<'static, str>>,
+    pub url: Url,
+    pub headers: HeaderMap,
+    pub items_path: JsonPath,
+    pub headline_path: JsonPath,
+    pub link_path: JsonPath,
+    pub date_path: Option<JsonPath>,
+    pub parse_date: DateParser,
+    pub timezone: Tz,
+    pub image_path: Option<JsonPath>,
+    pub body_path: Option<JsonPath>,
+    pub author_path: Option<JsonPath>,
+    pub categories_path: Option<JsonPath>,
+    pub interval: Duration,
+    pub retry: RetryPolicy,
+    pub retention: Retention,
+    pub stagger: Stagger,
+    pub adaptive: Option<Adaptive>,
+    pub max_items: Option<usize>,
+}
+
+fn timestamp(timestamp: i64, timezone: Tz) -> Option<DateTime<Tz>> {
+    // Anything past the year 5138 in seconds is far more likely to be milliseconds.
+    let millis = if timestamp.abs() < 100_000_000_000 {
+        timestamp.checked_mul(1000)?
+    } else {
+        timestamp
+    };
+    Utc.timestamp_millis_opt(millis)
+        .single()
+        .map(|date| date.with_timezone(&timezone))
+}
+
+impl JsonApi {
+    fn date(&self, item: &Value) -> Result<Option<DateTime<Tz>>> {
+        let path = match &self.date_path {
+            Some(path) => path,
+            None => return Ok(Some(Utc::now().with_timezone(&self.timezone))),
+        };
+        let date = match path.first(item) {
+            Some(Value::String(date)) => date.trim().to_owned(),
+            Some(Value::Number(number)) => number.to_string(),
+            _ => String::new(),
+        };
+        if let Some(date) = date.parse().ok().and_then(|n| timestamp(n, self.timezone)) {
+            return Ok(Some(date));
+        }
+        self.parse_date.parse(date, self.timezone)
+    }
+
+    fn article(&self, item: &Value) -> Result<Option<Article>> {
+        let headline = self
+            .headline_path
+            .string(item)
+            .with_context(|| format!("No headline at {}", self.headline_path))?;
+        let link = self
+            .link_path
+            .string(item)
+            .with_context(|| format!("No link at {}", self.link_path))?;
+        let link = self
+            .url
+            .join(&link)
+            .map(canonical::strip_tracking)
+            .with_context(|| format!("Invalid link {:?}", link))?;
+        let date = match self.date(item)? {
+            Some(date) => date,
+            None => {
+                trace!(link = link.as_str(), "Skipping article without a date");
+                return Ok(None);
+            }
+        };
+        let image = self
+            .image_path
+            .as_ref()
+            .and_then(|path| path.string(item))
+            .and_then(|image| self.url.join(&image).ok())
+            .map(|url| Image {
+                url,
+                mime_type: None,
+                length: None,
+            });
+        Ok(Some(Article {
+            headline,
+            body: self
+                .body_path
+                .as_ref()
+                .and_then(|path| path.string(item))
+                .unwrap_or_default(),
+            description: None,
+            language: None,
+            image,
+            images: Vec::new(),
+            guid: None,
+            alternates: Vec::new(),
+            updated: None,
+            author: self.author_path.as_ref().and_then(|path| path.string(item)),
+            categories: self
+                .categories_path
+                .as_ref()
+                .map(|path| path.strings(item))
+                .unwrap_or_default(),
+            date,
+            link,
+        }))
+    }
+
+    #[instrument(skip(self, client), fields(self.name))]
+    pub async fn get_articles(&self, client: &Client) -> Result<Option<Scraped>> {
+        let _timer = metrics::SCRAPE_DURATION
+            .with_label_values(&[&self.name])
+            .start_timer();
+        let (text, validators) =
+            match retry::fetch_if_modified(client, self.url.clone(), self.retry, &self.headers)
+                .await?
+            {
+                Some(text) => text,
+                None => {
+                    metrics::scraped(&self.name);
+                    return Ok(None);
+                }
+            };
+        let json: Value = serde_json::from_str(&text).context("Failed to parse JSON response")?;
+        // Items that fail to map would fail again, so only an unparseable response is refetched.
+        validators.save().await;
+        let items = match self.items_path.select(&json).as_slice() {
+            [Value::Array(items)] => items.iter().collect(),
+            items => items.to_vec(),
+        };
+        let mut articles = Vec::new();
+        let mut failed = 0;
+        for item in items {
+            match self.article(item) {
+                Ok(Some(article)) => articles.push(article),
+                Ok(None) => {}
+                Err(error) => {
+                    warn!(?error, "Failed to map item");
+                    failed += 1;
+                }
+            }
+        }
+        metrics::ARTICLES_FETCHED
+            .with_label_values(&[&self.name])
+            .inc_by(articles.len() as u64);
+        metrics::ARTICLE_FAILURES
+            .with_label_values(&[&self.name])
+            .inc_by(failed as u64);
+        metrics::scraped(&self.name);
+        Ok(Some(Scraped {
+            articles,
+            failed,
+            icon: None,
+        }))
+    }
+}
+
+#[async_trait(?Send)]
+impl Source for JsonApi {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    fn retention(&self) -> Retention {
+        self.retention
+    }
+
+    fn stagger(&self) -> Stagger {
+        self.stagger
+    }
+
+    fn adaptive(&self) -> Option<Adaptive> {
+        self.adaptive
+    }
+
+    fn channel(&self) -> Channel {
+        Channel {
+            description: self.description.as_deref().map(str::to_owned),
+            language: self.language.as_deref().map(str::to_owned),
+            link: self.link.as_deref().map(str::to_owned),
+            ttl: Some(self.interval),
+            max_items: self.max_items,
+            ..Channel::new(self.name())
+        }
+    }
+
+    async fn fetch(&self, client: &Client, _store: Option<&Store>) -> Result<Option<Scraped>> {
+        self.get_articles(client).await
+    }
+}
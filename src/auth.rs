@@ -0,0 +1,95 @@
+use crate::feed::{self, VirtualFeed};
+use ring::constant_time::verify_slices_are_equal;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone)]
+pub struct User {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Auth {
+    /// Accepted as `Authorization: Bearer`, or as `?token=` for readers that can't set headers.
+    pub tokens: Vec<String>,
+    /// Accepted as HTTP Basic credentials.
+    pub users: Vec<User>,
+    /// The feeds that need credentials, or every feed when empty.
+    pub feeds: Vec<String>,
+}
+
+static AUTH: OnceLock<Auth> = OnceLock::new();
+
+pub fn configure(auth: Auth) {
+    AUTH.set(auth).ok();
+}
+
+pub fn get() -> Option<&'static Auth> {
+    AUTH.get()
+}
+
+fn equal(a: &str, b: &str) -> bool {
+    verify_slices_are_equal(a.as_bytes(), b.as_bytes()).is_ok()
+}
+
+impl Auth {
+    fn token(&self, token: &str) -> bool {
+        self.tokens.iter().any(|accepted| equal(accepted, token))
+    }
+
+    /// Whether a feed needs credentials. The combined feed and searches across every feed
+    /// (`None`) include articles from protected feeds, so they are protected whenever any is, as are
+    /// the virtual feeds drawing from one. Those are passed in rather than kept, as they change
+    /// when the config is reloaded.
+    pub fn protects(&self, feed: Option<&str>, virtual_feeds: &[VirtualFeed]) -> bool {
+        let listed = |feed: &str| {
+            self.feeds
+                .iter()
+                .any(|protected| protected.eq_ignore_ascii_case(feed))
+        };
+        match feed {
+            _ if self.feeds.is_empty() => true,
+            Some(feed) if !feed.eq_ignore_ascii_case(feed::COMBINED) => {
+                listed(feed)
+                    || virtual_feeds
+                        .iter()
+                        .filter(|virtual_feed| virtual_feed.name.eq_ignore_ascii_case(feed))
+                        .any(|virtual_feed| {
+                            self.feeds
+                                .iter()
+                                .any(|protected| virtual_feed.includes(protected))
+                        })
+            }
+            _ => true,
+        }
+    }
+
+    /// Checks an `Authorization` header, or failing that a `token` query parameter.
+    pub fn authorized(&self, authorization: Option<&str>, token: Option<&str>) -> bool {
+        let (scheme, credentials) = match authorization.and_then(|header| header.split_once(' ')) {
+            Some((scheme, credentials)) => (scheme, credentials.trim()),
+            None => return token.is_some_and(|token| self.token(token)),
+        };
+        if scheme.eq_ignore_ascii_case("bearer") {
+            return self.token(credentials);
+        }
+        if !scheme.eq_ignore_ascii_case("basic") {
+            return false;
+        }
+        let decoded = match base64::decode(credentials)
+            .ok()
+            .and_then(|decoded| String::from_utf8(decoded).ok())
+        {
+            Some(decoded) => decoded,
+            None => return false,
+        };
+        let (username, password) = match decoded.split_once(':') {
+            Some(credentials) => credentials,
+            None => return false,
+        };
+        // Every user is checked, so the timing doesn't reveal which usernames exist.
+        self.users.iter().fold(false, |matched, user| {
+            equal(&user.username, username) & equal(&user.password, password) | matched
+        })
+    }
+}
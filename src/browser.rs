@@ -0,0 +1,36 @@
+use crate::politeness;
+use anyhow::*;
+use reqwest::{Client, Url};
+use std::{env, time::Duration};
+use tokio::{process::Command, time::timeout};
+use tracing::{instrument, trace};
+
+const RENDER_TIMEOUT: Duration = Duration::from_secs(60);
+
+fn chromium() -> String {
+    env::var("NEWS_RSS_CHROMIUM").unwrap_or_else(|_| "chromium".to_owned())
+}
+
+#[instrument(skip(client), fields(url = url.as_str()))]
+pub(crate) async fn render(client: &Client, url: &Url) -> Result<String> {
+    politeness::wait(client, url).await?;
+    let chromium = chromium();
+    trace!(chromium = chromium.as_str(), "Rendering page");
+    let output = Command::new(&chromium)
+        .args(["--headless", "--disable-gpu", "--dump-dom"])
+        .arg(url.as_str())
+        .kill_on_drop(true)
+        .output();
+    let output = timeout(RENDER_TIMEOUT, output)
+        .await
+        .context("Timed out rendering page")?
+        .with_context(|| format!("Failed to run {:?}", chromium))?;
+    ensure!(
+        output.status.success(),
+        "{} exited with {}: {}",
+        chromium,
+        output.status,
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -0,0 +1,420 @@
+use crate::{
+    rewrite::{Field, Rewrite},
+    script::Script,
+    translate::Translation, Adaptive, DateParser, Extractor, GuidStrategy, Pagination, Renderer, Retention, RetryPolicy, Scraper, Selectors, Stagger,
+    DEFAULT_CONCURRENCY,
+};
+use anyhow::*;
+use chrono_tz::Tz;
+use nipper::Matcher;
+use regex::Regex;
+use reqwest::{
+    header::{HeaderName, HeaderValue, COOKIE},
+    Url,
+};
+use std::{borrow::Cow, time::Duration};
+
+#[derive(Debug, Default)]
+pub struct ScraperBuilder {
+    pub(crate) name: Option<Cow<'static, str>>,
+    pub(crate) description: Option<Cow<'static, str>>,
+    pub(crate) language: Option<Cow<'static, str>>,
+    pub(crate) base_url: Option<Cow<'static, str>>,
+    pub(crate) news_url: Option<Cow<'static, str>>,
+    pub(crate) article_selector: Option<Cow<'static, str>>,
+    pub(crate) headline_selector: Option<Selectors>,
+    pub(crate) image_selector: Option<Selectors>,
+    pub(crate) collect_images: Option<bool>,
+    pub(crate) author_selector: Option<Selectors>,
+    pub(crate) categories_selector: Option<Selectors>,
+    pub(crate) date_selector: Option<Selectors>,
+    pub(crate) date_attribute: Option<Cow<'static, str>>,
+    pub(crate) parse_date: Option<DateParser>,
+    pub(crate) timezone: Option<Tz>,
+    pub(crate) link_selector: Option<Selectors>,
+    pub(crate) body_selector: Option<Selectors>,
+    pub(crate) extractor: Option<Extractor>,
+    pub(crate) remove_selectors: Vec<Cow<'static, str>>,
+    pub(crate) rewrites: Vec<(Field, Cow<'static, str>, Cow<'static, str>)>,
+    pub(crate) script: Option<Script>,
+    pub(crate) article_next_page_selector: Option<Selectors>,
+    pub(crate) prefer_amp: Option<bool>,
+    pub(crate) renderer: Option<Renderer>,
+    pub(crate) interval: Option<Duration>,
+    pub(crate) concurrency: Option<usize>,
+    pub(crate) retry: Option<RetryPolicy>,
+    pub(crate) retention: Option<Retention>,
+    pub(crate) stagger: Option<Stagger>,
+    pub(crate) adaptive: Option<Adaptive>,
+    pub(crate) headers: Vec<(Cow<'static, str>, Cow<'static, str>)>,
+    pub(crate) cookies: Vec<(Cow<'static, str>, Cow<'static, str>)>,
+    pub(crate) pagination: Option<Pagination>,
+    pub(crate) guid: Option<GuidStrategy>,
+    pub(crate) track_updates: Option<bool>,
+    pub(crate) summary_only: Option<bool>,
+    pub(crate) summary_sentences: Option<usize>,
+    pub(crate) detect_language: Option<bool>,
+    pub(crate) translation: Option<Translation>,
+    pub(crate) bump_pub_date: Option<bool>,
+    pub(crate) max_items: Option<usize>,
+}
+
+fn selectors(name: &str, selectors: Option<Selectors>) -> Result<Selectors> {
+    let selectors = selectors.with_context(|| format!("Scraper requires {}", name))?;
+    selectors.validate(name)?;
+    Ok(selectors)
+}
+
+fn selector(name: &str, selector: Option<Cow<'static, str>>) -> Result<Cow<'static, str>> {
+    let selector = selector.with_context(|| format!("Scraper requires {}", name))?;
+    Matcher::new(&selector).map_err(|e| anyhow!("Invalid {} {:?}: {:?}", name, selector, e))?;
+    Ok(selector)
+}
+
+impl ScraperBuilder {
+    pub fn name(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<Cow<'static, str>>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn language(mut self, language: impl Into<Cow<'static, str>>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    pub fn base_url(mut self, base_url: impl Into<Cow<'static, str>>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    pub fn news_url(mut self, news_url: impl Into<Cow<'static, str>>) -> Self {
+        self.news_url = Some(news_url.into());
+        self
+    }
+
+    pub fn article_selector(mut self, selector: impl Into<Cow<'static, str>>) -> Self {
+        self.article_selector = Some(selector.into());
+        self
+    }
+
+    pub fn headline_selector(mut self, selector: impl Into<Selectors>) -> Self {
+        self.headline_selector = Some(selector.into());
+        self
+    }
+
+    pub fn image_selector(mut self, selector: impl Into<Selectors>) -> Self {
+        self.image_selector = Some(selector.into());
+        self
+    }
+
+    pub fn collect_images(mut self, collect_images: bool) -> Self {
+        self.collect_images = Some(collect_images);
+        self
+    }
+
+    pub fn author_selector(mut self, selector: impl Into<Selectors>) -> Self {
+        self.author_selector = Some(selector.into());
+        self
+    }
+
+    pub fn categories_selector(mut self, selector: impl Into<Selectors>) -> Self {
+        self.categories_selector = Some(selector.into());
+        self
+    }
+
+    pub fn date_selector(mut self, selector: impl Into<Selectors>) -> Self {
+        self.date_selector = Some(selector.into());
+        self
+    }
+
+    pub fn date_attribute(mut self, attribute: impl Into<Cow<'static, str>>) -> Self {
+        self.date_attribute = Some(attribute.into());
+        self
+    }
+
+    pub fn parse_date(mut self, parse_date: DateParser) -> Self {
+        self.parse_date = Some(parse_date);
+        self
+    }
+
+    /// The timezone dates without an offset are in, which defaults to UTC.
+    pub fn timezone(mut self, timezone: Tz) -> Self {
+        self.timezone = Some(timezone);
+        self
+    }
+
+    pub fn link_selector(mut self, selector: impl Into<Selectors>) -> Self {
+        self.link_selector = Some(selector.into());
+        self
+    }
+
+    pub fn body_selector(mut self, selector: impl Into<Selectors>) -> Self {
+        self.body_selector = Some(selector.into());
+        self
+    }
+
+    pub fn extractor(mut self, extractor: Extractor) -> Self {
+        self.extractor = Some(extractor);
+        self
+    }
+
+    pub fn remove_selector(mut self, selector: impl Into<Cow<'static, str>>) -> Self {
+        self.remove_selectors.push(selector.into());
+        self
+    }
+
+    /// Replaces matches of a regex in one field of every article, in the order rewrites are added.
+    pub fn rewrite(
+        mut self,
+        field: Field,
+        pattern: impl Into<Cow<'static, str>>,
+        replacement: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        self.rewrites.push((field, pattern.into(), replacement.into()));
+        self
+    }
+
+    pub fn script(mut self, script: Script) -> Self {
+        self.script = Some(script);
+        self
+    }
+
+    pub fn article_next_page_selector(mut self, selector: impl Into<Selectors>) -> Self {
+        self.article_next_page_selector = Some(selector.into());
+        self
+    }
+
+    pub fn prefer_amp(mut self, prefer_amp: bool) -> Self {
+        self.prefer_amp = Some(prefer_amp);
+        self
+    }
+
+    pub fn renderer(mut self, renderer: Renderer) -> Self {
+        self.renderer = Some(renderer);
+        self
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = Some(concurrency);
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    pub fn retention(mut self, retention: Retention) -> Self {
+        self.retention = Some(retention);
+        self
+    }
+
+    pub fn stagger(mut self, stagger: Stagger) -> Self {
+        self.stagger = Some(stagger);
+        self
+    }
+
+    pub fn adaptive(mut self, adaptive: Adaptive) -> Self {
+        self.adaptive = Some(adaptive);
+        self
+    }
+
+    pub fn header(
+        mut self,
+        name: impl Into<Cow<'static, str>>,
+        value: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn cookie(
+        mut self,
+        name: impl Into<Cow<'static, str>>,
+        value: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        self.cookies.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn pagination(mut self, pagination: Pagination) -> Self {
+        self.pagination = Some(pagination);
+        self
+    }
+
+    pub fn guid(mut self, guid: GuidStrategy) -> Self {
+        self.guid = Some(guid);
+        self
+    }
+
+    pub fn track_updates(mut self, track_updates: bool) -> Self {
+        self.track_updates = Some(track_updates);
+        self
+    }
+
+    pub fn summary_only(mut self, summary_only: bool) -> Self {
+        self.summary_only = Some(summary_only);
+        self
+    }
+
+    pub fn summary_sentences(mut self, summary_sentences: usize) -> Self {
+        self.summary_sentences = Some(summary_sentences);
+        self
+    }
+
+    pub fn detect_language(mut self, detect_language: bool) -> Self {
+        self.detect_language = Some(detect_language);
+        self
+    }
+
+    pub fn translation(mut self, translation: Translation) -> Self {
+        self.translation = Some(translation);
+        self
+    }
+
+    pub fn bump_pub_date(mut self, bump_pub_date: bool) -> Self {
+        self.bump_pub_date = Some(bump_pub_date);
+        self
+    }
+
+    pub fn max_items(mut self, max_items: usize) -> Self {
+        self.max_items = Some(max_items);
+        self
+    }
+
+    pub fn build(self) -> Result<Scraper> {
+        let name = self.name.context("Scraper requires a name")?;
+        let base_url = self.base_url.context("Scraper requires a base URL")?;
+        let news_url = self.news_url.context("Scraper requires a news URL")?;
+        Url::parse(&base_url)
+            .and_then(|base| base.join(&news_url))
+            .with_context(|| format!("Invalid URL for scraper {}", name))?;
+        let mut headers = self.headers;
+        if !self.cookies.is_empty() {
+            let cookies = self
+                .cookies
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect::<Vec<_>>()
+                .join("; ");
+            headers.push((COOKIE.as_str().into(), cookies.into()));
+        }
+        for (header, value) in &headers {
+            HeaderName::from_bytes(header.as_bytes()).with_context(|| {
+                format!("Invalid header name {:?} for scraper {}", header, name)
+            })?;
+            HeaderValue::from_str(value).with_context(|| {
+                format!("Invalid value for header {} of scraper {}", header, name)
+            })?;
+        }
+        match &self.pagination {
+            Some(Pagination::Next { selector, .. }) => selector.validate("next page selector")?,
+            Some(Pagination::Template { template, .. }) => ensure!(
+                template.contains("{page}"),
+                "Page URL template for scraper {} requires a {{page}} placeholder",
+                name
+            ),
+            None => {}
+        }
+        if let Some(GuidStrategy::Selector { selector, .. }) = &self.guid {
+            selector.validate("GUID selector")?;
+        }
+        let renderer = self.renderer.unwrap_or_default();
+        let extractor = self.extractor.unwrap_or_default();
+        ensure!(
+            cfg!(feature = "browser") || renderer != Renderer::Browser,
+            "Scraper {} uses the browser renderer, which requires the browser feature",
+            name
+        );
+        Ok(Scraper {
+            article_selector: selector("article selector", self.article_selector)?,
+            headline_selector: selectors("headline selector", self.headline_selector)?,
+            image_selector: self
+                .image_selector
+                .map(|image| selectors("image selector", Some(image)))
+                .transpose()?,
+            collect_images: self.collect_images.unwrap_or(false),
+            author_selector: self
+                .author_selector
+                .map(|author| selectors("author selector", Some(author)))
+                .transpose()?,
+            categories_selector: self
+                .categories_selector
+                .map(|categories| selectors("categories selector", Some(categories)))
+                .transpose()?,
+            date_selector: selectors("date selector", self.date_selector)?,
+            date_attribute: self.date_attribute,
+            parse_date: self.parse_date.context("Scraper requires a date parser")?,
+            timezone: self.timezone.unwrap_or(Tz::UTC),
+            link_selector: selectors("link selector", self.link_selector)?,
+            body_selector: match (self.body_selector, extractor) {
+                (None, Extractor::Readability) => None,
+                (body, _) => Some(selectors("body selector", body)?),
+            },
+            extractor,
+            remove_selectors: self
+                .remove_selectors
+                .into_iter()
+                .map(|remove| selector("remove selector", Some(remove)))
+                .collect::<Result<Vec<_>>>()?
+                .into(),
+            rewrites: self
+                .rewrites
+                .into_iter()
+                .map(|(field, pattern, replacement)| {
+                    Ok(Rewrite {
+                        field,
+                        pattern: Regex::new(&pattern)
+                            .with_context(|| format!("Invalid rewrite pattern {:?}", pattern))?,
+                        replacement,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?
+                .into(),
+            script: self.script,
+            article_next_page_selector: self
+                .article_next_page_selector
+                .map(|next| selectors("article next page selector", Some(next)))
+                .transpose()?,
+            prefer_amp: self.prefer_amp.unwrap_or(false),
+            renderer,
+            interval: self.interval.unwrap_or(Duration::from_secs(60 * 60)),
+            concurrency: self.concurrency.unwrap_or(DEFAULT_CONCURRENCY).max(1),
+            retry: self.retry.unwrap_or_default(),
+            retention: self.retention.unwrap_or_default(),
+            stagger: self.stagger.unwrap_or_default(),
+            adaptive: self.adaptive,
+            headers: headers.into(),
+            pagination: self.pagination,
+            guid: self.guid.unwrap_or_default(),
+            track_updates: self.track_updates.unwrap_or(false),
+            summary_only: self.summary_only.unwrap_or(false),
+            summary_sentences: self.summary_sentences.filter(|count| *count > 0),
+            detect_language: self.detect_language.unwrap_or(true),
+            translation: self.translation,
+            bump_pub_date: self.bump_pub_date.unwrap_or(false),
+            max_items: self.max_items,
+            name,
+            description: self.description,
+            language: self.language,
+            base_url,
+            news_url,
+        })
+    }
+}
+
+impl Scraper {
+    pub fn builder() -> ScraperBuilder {
+        ScraperBuilder::default()
+    }
+}
@@ -0,0 +1,178 @@
+use anyhow::*;
+use chrono::Utc;
+use fnv::FnvHasher;
+use reqwest::{
+    header::{HeaderMap, HeaderValue, CACHE_CONTROL, ETAG, EXPIRES, LAST_MODIFIED},
+    Url,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    hash::Hasher,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::{Duration, SystemTime},
+};
+use tracing::{instrument, trace, warn};
+
+/// Entries not revalidated for this long are removed when the cache opens.
+const UNUSED: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// An on-disk cache of article pages, one JSON file per URL.
+#[derive(Debug)]
+pub struct HttpCache {
+    dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Entry {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// Unix seconds until which the page can be used without revalidating it.
+    fresh_until: i64,
+    pub body: String,
+}
+
+static CACHE: OnceLock<HttpCache> = OnceLock::new();
+
+pub fn configure(cache: HttpCache) {
+    CACHE.set(cache).ok();
+}
+
+pub(crate) fn get() -> Option<&'static HttpCache> {
+    CACHE.get()
+}
+
+/// How long a response stays fresh, or `None` when it mustn't be stored. Responses that say
+/// nothing are stored, but revalidated every time.
+pub fn lifetime(headers: &HeaderMap) -> Option<Duration> {
+    let directives = headers
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|directive| directive.trim().to_ascii_lowercase())
+        .collect::<Vec<_>>();
+    if directives.iter().any(|directive| directive == "no-store") {
+        return None;
+    }
+    if directives.iter().any(|directive| directive == "no-cache") {
+        return Some(Duration::ZERO);
+    }
+    let max_age = directives.iter().find_map(|directive| {
+        directive
+            .strip_prefix("max-age=")
+            .and_then(|seconds| seconds.trim_matches('"').parse().ok())
+    });
+    if let Some(max_age) = max_age {
+        return Some(Duration::from_secs(max_age));
+    }
+    let expires = headers
+        .get(EXPIRES)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| chrono::DateTime::parse_from_rfc2822(value).ok());
+    Some(
+        expires
+            .and_then(|expires| (expires.with_timezone(&Utc) - Utc::now()).to_std().ok())
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+fn header(headers: &HeaderMap, name: reqwest::header::HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned)
+}
+
+impl Entry {
+    pub fn fresh(&self) -> bool {
+        Utc::now().timestamp() < self.fresh_until
+    }
+
+    pub fn etag(&self) -> Option<HeaderValue> {
+        self.etag.as_deref().and_then(|etag| etag.parse().ok())
+    }
+
+    pub fn last_modified(&self) -> Option<HeaderValue> {
+        self.last_modified
+            .as_deref()
+            .and_then(|last_modified| last_modified.parse().ok())
+    }
+}
+
+impl HttpCache {
+    #[instrument(skip(dir), fields(dir = ?dir.as_ref()))]
+    pub fn open(dir: impl AsRef<Path>) -> Result<HttpCache> {
+        let dir = dir.as_ref().to_owned();
+        fs::create_dir_all(&dir).with_context(|| format!("Failed to create cache {:?}", dir))?;
+        let now = SystemTime::now();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let unused = fs::metadata(&path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .is_some_and(|age| age > UNUSED);
+            if unused {
+                trace!(?path, "Removing unused cache entry");
+                fs::remove_file(&path).ok();
+            }
+        }
+        Ok(HttpCache { dir })
+    }
+
+    fn path(&self, url: &Url) -> PathBuf {
+        let mut hasher = FnvHasher::default();
+        hasher.write(url.as_str().as_bytes());
+        self.dir.join(format!("{:016x}.json", hasher.finish()))
+    }
+
+    pub(crate) fn get(&self, url: &Url) -> Option<Entry> {
+        let entry = fs::read(self.path(url)).ok()?;
+        serde_json::from_slice::<Entry>(&entry)
+            .ok()
+            // Different URLs can share a file name, and only one is kept at a time.
+            .filter(|entry| entry.url == url.as_str())
+    }
+
+    /// Stores a fetched page, or drops any stored copy when the response can't be stored.
+    pub(crate) fn put(&self, url: &Url, headers: &HeaderMap, body: String) {
+        let lifetime = match lifetime(headers) {
+            Some(lifetime) => lifetime,
+            None => {
+                fs::remove_file(self.path(url)).ok();
+                return;
+            }
+        };
+        self.write(url, Entry {
+            url: url.as_str().to_owned(),
+            etag: header(headers, ETAG),
+            last_modified: header(headers, LAST_MODIFIED),
+            fresh_until: Utc::now().timestamp() + lifetime.as_secs() as i64,
+            body,
+        });
+    }
+
+    /// Extends a stored page after the server confirmed it with 304 Not Modified.
+    pub(crate) fn revalidated(&self, url: &Url, entry: &Entry, headers: &HeaderMap) {
+        let lifetime = lifetime(headers).unwrap_or(Duration::ZERO);
+        self.write(url, Entry {
+            etag: header(headers, ETAG).or_else(|| entry.etag.clone()),
+            last_modified: header(headers, LAST_MODIFIED).or_else(|| entry.last_modified.clone()),
+            fresh_until: Utc::now().timestamp() + lifetime.as_secs() as i64,
+            ..entry.clone()
+        });
+    }
+
+    fn write(&self, url: &Url, entry: Entry) {
+        let path = self.path(url);
+        let written = serde_json::to_vec(&entry)
+            .map_err(Error::from)
+            .and_then(|entry| Ok(fs::write(&path, entry)?));
+        if let Err(error) = written {
+            warn!(?error, ?path, "Failed to cache page");
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use nipper::Document;
+use reqwest::Url;
+
+const TRACKING_PARAMETERS: &[&str] = &[
+    "fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid", "yclid", "twclid", "igshid",
+    "mc_cid", "mc_eid", "mkt_tok", "_ga", "_gl", "ocid", "ref_src",
+];
+
+fn tracking(name: &str) -> bool {
+    name.starts_with("utm_") || TRACKING_PARAMETERS.contains(&name)
+}
+
+pub(crate) fn strip_tracking(mut url: Url) -> Url {
+    let pairs = url.query_pairs().into_owned().collect::<Vec<_>>();
+    let kept = pairs
+        .iter()
+        .filter(|(name, _)| !tracking(name))
+        .collect::<Vec<_>>();
+    if kept.len() == pairs.len() {
+        return url;
+    }
+    if kept.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(kept);
+    }
+    url
+}
+
+pub(crate) fn canonical(document: &Document, page_url: &Url) -> Option<Url> {
+    let href = document.select(r#"link[rel="canonical"]"#).attr("href")?;
+    let canonical = page_url.join(href.trim()).ok()?;
+    // A canonical pointing at the site root is a common misconfiguration, not a real alias.
+    let plausible = matches!(canonical.scheme(), "http" | "https")
+        && (canonical.path() != "/" || page_url.path() == "/");
+    plausible.then(|| strip_tracking(canonical))
+}
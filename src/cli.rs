@@ -0,0 +1,372 @@
+use anyhow::*;
+use std::{collections::HashMap, env, fmt::Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Serve,
+    ScrapeOnce,
+    ListScrapers,
+    ValidateConfig,
+    Export,
+    DebugSelectors,
+}
+
+use Command::*;
+
+const COMMANDS: [(Command, &str, &str, &str); 6] = [
+    (
+        Serve,
+        "serve",
+        "[ADDRESS]",
+        "Scrape continuously and serve the feeds over HTTP (the default)",
+    ),
+    (
+        ScrapeOnce,
+        "scrape-once",
+        "[SCRAPER]",
+        "Scrape each source once and print the articles as JSON",
+    ),
+    (
+        ListScrapers,
+        "list-scrapers",
+        "",
+        "List the configured sources",
+    ),
+    (
+        ValidateConfig,
+        "validate-config",
+        "",
+        "Check that the config file loads",
+    ),
+    (
+        Export,
+        "export",
+        "",
+        "Scrape each source once, then write or upload the feeds",
+    ),
+    (
+        DebugSelectors,
+        "debug-selectors",
+        "SCRAPER LOCATION",
+        "Show what each of a scraper's selectors matches on a page",
+    ),
+];
+
+const ALL: &[Command] = &[
+    Serve,
+    ScrapeOnce,
+    ListScrapers,
+    ValidateConfig,
+    Export,
+    DebugSelectors,
+];
+const FETCHING: &[Command] = &[Serve, ScrapeOnce, Export, DebugSelectors];
+const EXPORTING: &[Command] = &[Serve, Export];
+
+struct Flag {
+    name: &'static str,
+    value: &'static str,
+    help: &'static str,
+    commands: &'static [Command],
+}
+
+const FLAGS: &[Flag] = &[
+    Flag {
+        name: "config",
+        value: "PATH",
+        help: "TOML file of scrapers and settings",
+        commands: ALL,
+    },
+    Flag {
+        name: "listen",
+        value: "ADDRESS",
+        help: "HOST:PORT, PORT or unix:PATH to serve on, by default 0.0.0.0:2048",
+        commands: &[Serve],
+    },
+    Flag {
+        name: "store",
+        value: "PATH",
+        help: "SQLite database that keeps articles and the scrape schedule between runs",
+        commands: EXPORTING,
+    },
+    Flag {
+        name: "public-url",
+        value: "URL",
+        help: "URL the feeds are served from, for self links",
+        commands: EXPORTING,
+    },
+    Flag {
+        name: "output-dir",
+        value: "PATH",
+        help: "Write the feeds to a directory instead of serving them",
+        commands: EXPORTING,
+    },
+    Flag {
+        name: "refresh-token",
+        value: "TOKEN",
+        help: "Bearer token that allows POST /refresh/:feed",
+        commands: &[Serve],
+    },
+    Flag {
+        name: "admin-token",
+        value: "TOKEN",
+        help: "Bearer token for /api/scrapers and the /admin page",
+        commands: &[Serve],
+    },
+    Flag {
+        name: "user-agent",
+        value: "VALUE",
+        help: "User-Agent header for requests",
+        commands: FETCHING,
+    },
+    Flag {
+        name: "proxy",
+        value: "URL",
+        help: "Proxy for requests",
+        commands: FETCHING,
+    },
+    Flag {
+        name: "timeout",
+        value: "SECONDS",
+        help: "Total timeout for each request",
+        commands: FETCHING,
+    },
+    Flag {
+        name: "connect-timeout",
+        value: "SECONDS",
+        help: "Connection timeout for each request",
+        commands: FETCHING,
+    },
+    Flag {
+        name: "max-redirects",
+        value: "COUNT",
+        help: "Redirects to follow per request",
+        commands: FETCHING,
+    },
+    Flag {
+        name: "cache-dir",
+        value: "PATH",
+        help: "Directory that caches article pages between scrapes",
+        commands: FETCHING,
+    },
+    Flag {
+        name: "image-dir",
+        value: "PATH",
+        help: "Directory that keeps article images to serve from /img (requires --public-url)",
+        commands: &[Serve],
+    },
+    Flag {
+        name: "thumbnail-width",
+        value: "PIXELS",
+        help: "Width of thumbnails made of cached images",
+        commands: &[Serve],
+    },
+    Flag {
+        name: "webhook",
+        value: "URL",
+        help: "Webhook to alert when a feed fails or empties",
+        commands: &[Serve],
+    },
+    Flag {
+        name: "webhook-format",
+        value: "FORMAT",
+        help: "Alert payload format: generic, slack or discord",
+        commands: &[Serve],
+    },
+    Flag {
+        name: "push-webhook",
+        value: "URL",
+        help: "Webhook to POST each new article to",
+        commands: &[Serve],
+    },
+    Flag {
+        name: "hub",
+        value: "URL",
+        help: "WebSub hub to advertise and ping",
+        commands: &[Serve],
+    },
+    Flag {
+        name: "activitypub-key",
+        value: "PATH",
+        help: "PEM private key that enables ActivityPub actors",
+        commands: &[Serve],
+    },
+];
+
+#[derive(Debug)]
+pub struct Cli {
+    pub command: Command,
+    pub positional: Vec<String>,
+    values: HashMap<&'static str, String>,
+}
+
+fn variable(flag: &str) -> String {
+    format!("NEWS_RSS_{}", flag.to_uppercase().replace('-', "_"))
+}
+
+fn flags(command: Command) -> impl Iterator<Item = &'static Flag> {
+    FLAGS
+        .iter()
+        .filter(move |flag| flag.commands.contains(&command))
+}
+
+fn name(command: Command) -> &'static str {
+    COMMANDS
+        .iter()
+        .find(|(other, ..)| *other == command)
+        .map(|(_, name, ..)| *name)
+        .unwrap()
+}
+
+pub fn help(command: Option<Command>) -> String {
+    let mut help = String::new();
+    match command {
+        None => {
+            help.push_str("Scrapes news sites into RSS, Atom and JSON feeds.\n\n");
+            help.push_str("Usage: news-rss [COMMAND] [OPTIONS]\n\nCommands:\n");
+            for (_, name, _, summary) in COMMANDS {
+                writeln!(help, "  {:<18}{}", name, summary).unwrap();
+            }
+            help.push_str("\nRun `news-rss COMMAND --help` for the options of each command.\n");
+        }
+        Some(command) => {
+            let (_, name, arguments, summary) = COMMANDS
+                .iter()
+                .find(|(other, ..)| *other == command)
+                .unwrap();
+            writeln!(help, "{}.\n", summary).unwrap();
+            writeln!(help, "Usage: news-rss {} [OPTIONS] {}\n", name, arguments).unwrap();
+            help.push_str("Options:\n");
+            for flag in flags(command) {
+                writeln!(
+                    help,
+                    "  {:<32}{} [env: {}]",
+                    format!("--{} {}", flag.name, flag.value),
+                    flag.help,
+                    variable(flag.name)
+                )
+                .unwrap();
+            }
+            writeln!(help, "  {:<32}Print this help", "-h, --help").unwrap();
+        }
+    }
+    help
+}
+
+/// Where the command is: the first argument that is neither an option nor an option's value, as
+/// long as it names a command. Before subcommands, `scrape`, `--once` and `debug-selectors` could
+/// follow the options.
+fn command_position(args: &[String]) -> Option<usize> {
+    let mut index = 0;
+    while let Some(arg) = args.get(index) {
+        match arg.strip_prefix("--") {
+            Some("once") => return Some(index),
+            Some("help") => index += 1,
+            Some(option) if option.contains('=') => index += 1,
+            // Every option takes a value.
+            Some(_) => index += 2,
+            None if arg == "-h" => index += 1,
+            None => {
+                let command =
+                    arg == "scrape" || COMMANDS.iter().any(|(_, name, ..)| name == arg);
+                return command.then_some(index);
+            }
+        }
+    }
+    None
+}
+
+impl Cli {
+    /// Parses arguments, printing help and exiting when asked. Flags that are not given fall
+    /// back to their `NEWS_RSS_*` environment variables.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Cli> {
+        let mut args = args.into_iter().collect::<Vec<_>>();
+        if args
+            .first()
+            .is_some_and(|arg| arg == "-h" || arg == "--help")
+        {
+            print!("{}", help(None));
+            std::process::exit(0);
+        }
+        let position = command_position(&args);
+        let command = match position {
+            Some(position) => match args.remove(position).as_str() {
+                "scrape" | "--once" => ScrapeOnce,
+                command => COMMANDS
+                    .iter()
+                    .find(|(_, name, ..)| *name == command)
+                    .map(|(command, ..)| *command)
+                    .unwrap(),
+            },
+            None => Serve,
+        };
+        let mut args = args.into_iter();
+        let mut cli = Cli {
+            command,
+            positional: Vec::new(),
+            values: HashMap::new(),
+        };
+        while let Some(arg) = args.next() {
+            if arg == "-h" || arg == "--help" {
+                print!("{}", help(Some(command)));
+                std::process::exit(0);
+            }
+            let option = match arg.strip_prefix("--") {
+                Some(option) => option,
+                None => {
+                    cli.positional.push(arg);
+                    continue;
+                }
+            };
+            let (option, value) = match option.split_once('=') {
+                Some((option, value)) => (option, Some(value.to_owned())),
+                None => (option, None),
+            };
+            let flag = flags(command)
+                .find(|flag| flag.name == option)
+                .with_context(|| {
+                    format!(
+                        "Unknown option --{} for {}; run `news-rss {} --help`",
+                        option,
+                        name(command),
+                        name(command)
+                    )
+                })?;
+            let value = match value {
+                Some(value) => value,
+                None => args
+                    .next()
+                    .with_context(|| format!("Expected {} after --{}", flag.value, flag.name))?,
+            };
+            cli.values.insert(flag.name, value);
+        }
+        for flag in flags(command) {
+            if !cli.values.contains_key(flag.name) {
+                if let Result::Ok(value) = env::var(variable(flag.name)) {
+                    cli.values.insert(flag.name, value);
+                }
+            }
+        }
+        Ok(cli)
+    }
+
+    pub fn get(&self, flag: &str) -> Option<String> {
+        self.values.get(flag).cloned()
+    }
+
+    pub fn parse_value<T>(&self, flag: &str) -> Result<Option<T>>
+    where
+        T: std::str::FromStr,
+        T::Err: Into<Error>,
+    {
+        self.values
+            .get(flag)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(Into::into)
+                    .with_context(|| format!("Invalid value {:?} for --{}", value, flag))
+            })
+            .transpose()
+    }
+}
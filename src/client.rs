@@ -0,0 +1,66 @@
+use crate::images::ThumbnailFormat;
+use anyhow::*;
+use reqwest::{redirect, Client, Proxy};
+use std::{path::PathBuf, time::Duration};
+use tracing::instrument;
+
+pub const USER_AGENT: &str = concat!(
+    "news-rss/",
+    env!("CARGO_PKG_VERSION"),
+    " (+https://github.com/bluepython508/news-rss)"
+);
+
+#[derive(Debug, Clone, Default)]
+pub struct ClientConfig {
+    pub connect_timeout: Option<Duration>,
+    pub timeout: Option<Duration>,
+    pub user_agent: Option<String>,
+    pub proxy: Option<String>,
+    pub max_redirects: Option<usize>,
+    /// Directory for the on-disk cache of article pages.
+    pub cache_dir: Option<PathBuf>,
+    /// Directory for copies of article images, served in place of the originals.
+    pub image_dir: Option<PathBuf>,
+    /// Width to scale thumbnails of cached images to; none are made without one.
+    pub thumbnail_width: Option<u32>,
+    pub thumbnail_format: Option<ThumbnailFormat>,
+}
+
+impl ClientConfig {
+    pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+    pub const DEFAULT_MAX_REDIRECTS: usize = 10;
+
+    pub fn or(self, other: ClientConfig) -> ClientConfig {
+        ClientConfig {
+            connect_timeout: self.connect_timeout.or(other.connect_timeout),
+            timeout: self.timeout.or(other.timeout),
+            user_agent: self.user_agent.or(other.user_agent),
+            proxy: self.proxy.or(other.proxy),
+            max_redirects: self.max_redirects.or(other.max_redirects),
+            cache_dir: self.cache_dir.or(other.cache_dir),
+            image_dir: self.image_dir.or(other.image_dir),
+            thumbnail_width: self.thumbnail_width.or(other.thumbnail_width),
+            thumbnail_format: self.thumbnail_format.or(other.thumbnail_format),
+        }
+    }
+
+    #[instrument]
+    pub fn build(&self) -> Result<Client> {
+        let mut builder = Client::builder()
+            .connect_timeout(
+                self.connect_timeout
+                    .unwrap_or(Self::DEFAULT_CONNECT_TIMEOUT),
+            )
+            .timeout(self.timeout.unwrap_or(Self::DEFAULT_TIMEOUT))
+            .user_agent(self.user_agent.as_deref().unwrap_or(USER_AGENT))
+            .redirect(redirect::Policy::limited(
+                self.max_redirects.unwrap_or(Self::DEFAULT_MAX_REDIRECTS),
+            ));
+        if let Some(proxy) = &self.proxy {
+            builder = builder
+                .proxy(Proxy::all(proxy).with_context(|| format!("Invalid proxy {:?}", proxy))?);
+        }
+        builder.build().context("Failed to build HTTP client")
+    }
+}
@@ -0,0 +1,158 @@
+use crate::{Alternate, Article};
+use std::{collections::HashSet, sync::OnceLock};
+
+/// Articles further apart than this aren't taken to be the same story.
+const WINDOW: i64 = 2 * 24 * 60 * 60;
+/// Headlines sharing fewer words than the similarity still match if their text names this many
+/// of the same things.
+const SHARED_ENTITIES: usize = 2;
+const STOPWORDS: &[&str] = &[
+    "about", "after", "again", "against", "amid", "and", "are", "been", "but", "can", "could",
+    "for", "from", "has", "have", "how", "into", "its", "more", "new", "not", "now", "off", "one",
+    "out", "over", "said", "says", "than", "that", "the", "their", "them", "there", "they", "this",
+    "two", "was", "were", "what", "when", "who", "why", "will", "with", "would", "you",
+];
+
+/// Grouping of articles about the same story from different sources in the combined feed.
+#[derive(Debug, Clone, Copy)]
+pub struct Clustering {
+    /// Replaces each story's articles with the first to be published, linking the rest from it.
+    /// Otherwise every article links the others.
+    pub collapse: bool,
+    /// The share of their headlines' words two articles need in common, from 0 to 1.
+    pub similarity: f64,
+}
+
+impl Clustering {
+    pub const DEFAULT_SIMILARITY: f64 = 0.5;
+}
+
+struct Features<'a> {
+    source: &'a str,
+    words: HashSet<String>,
+    entities: HashSet<String>,
+    date: i64,
+}
+
+static CLUSTERING: OnceLock<Clustering> = OnceLock::new();
+
+pub fn configure(clustering: Clustering) {
+    CLUSTERING.set(clustering).ok();
+}
+
+pub fn get() -> Option<&'static Clustering> {
+    CLUSTERING.get()
+}
+
+fn tokens(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| token.chars().count() >= 2)
+}
+
+fn stopword(token: &str) -> bool {
+    STOPWORDS.contains(&token)
+}
+
+fn features<'a>(source: &'a str, article: &Article) -> Features<'a> {
+    let words = tokens(&article.headline)
+        .map(str::to_lowercase)
+        .filter(|word| word.chars().count() >= 3 && !stopword(word))
+        .collect();
+    // Capitalised words are mostly names, though also the start of sentences.
+    let summary = article.summary();
+    let entities = tokens(&article.headline)
+        .chain(tokens(&summary))
+        .filter(|token| token.starts_with(char::is_uppercase))
+        .map(str::to_lowercase)
+        .filter(|entity| !stopword(entity))
+        .collect();
+    Features {
+        source,
+        words,
+        entities,
+        date: article.date.timestamp(),
+    }
+}
+
+impl Clustering {
+    fn similar(&self, a: &Features, b: &Features) -> bool {
+        if a.source == b.source || (a.date - b.date).abs() > WINDOW {
+            return false;
+        }
+        let union = a.words.union(&b.words).count();
+        if union == 0 {
+            return false;
+        }
+        let shared = a.words.intersection(&b.words).count() as f64 / union as f64;
+        shared >= self.similarity
+            || (shared >= self.similarity / 2.0
+                && a.entities.intersection(&b.entities).count() >= SHARED_ENTITIES)
+    }
+
+    /// Groups of indices into `articles` that cover the same story, each with articles from more
+    /// than one source. Matches chain, so a group's articles needn't all match each other.
+    pub fn clusters(&self, articles: &[(&str, &Article)]) -> Vec<Vec<usize>> {
+        let features = articles
+            .iter()
+            .map(|(source, article)| features(source, article))
+            .collect::<Vec<_>>();
+        let mut parents = (0..articles.len()).collect::<Vec<_>>();
+        fn root(parents: &mut [usize], mut index: usize) -> usize {
+            while parents[index] != index {
+                parents[index] = parents[parents[index]];
+                index = parents[index];
+            }
+            index
+        }
+        for a in 0..features.len() {
+            for b in a + 1..features.len() {
+                if self.similar(&features[a], &features[b]) {
+                    let (a, b) = (root(&mut parents, a), root(&mut parents, b));
+                    parents[b] = a;
+                }
+            }
+        }
+        let mut clusters = vec![Vec::new(); articles.len()];
+        for index in 0..articles.len() {
+            clusters[root(&mut parents, index)].push(index);
+        }
+        clusters.retain(|cluster| cluster.len() > 1);
+        for cluster in &mut clusters {
+            cluster.sort_by_key(|&index| (articles[index].1.date, index));
+        }
+        clusters
+    }
+
+    /// The indices of the articles to keep, each with the other articles on its story. Collapsing
+    /// keeps only the first of each story.
+    pub(crate) fn apply(&self, articles: &[(&str, &Article)]) -> Vec<(usize, Vec<Alternate>)> {
+        let mut alternates = vec![Some(Vec::new()); articles.len()];
+        for cluster in self.clusters(articles) {
+            for (position, &index) in cluster.iter().enumerate() {
+                if self.collapse && position > 0 {
+                    alternates[index] = None;
+                    continue;
+                }
+                alternates[index] = Some(
+                    cluster
+                        .iter()
+                        .filter(|&&other| other != index)
+                        .map(|&other| {
+                            let (source, article) = articles[other];
+                            Alternate {
+                                source: source.to_owned(),
+                                headline: article.headline.clone(),
+                                link: article.link.clone(),
+                            }
+                        })
+                        .collect(),
+                );
+            }
+        }
+        alternates
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, alternates)| Some((index, alternates?)))
+            .collect()
+    }
+}
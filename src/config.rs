@@ -0,0 +1,1220 @@
+use crate::{
+    activitypub::ActivityPub,
+    alert::{Webhook, WebhookFormat},
+    auth::{Auth, User},
+    cluster::Clustering,
+    digest::{Digest, Schedule},
+    export::Export,
+    images::ThumbnailFormat,
+    push::Push,
+    rewrite::Field,
+    s3::S3,
+    script::Script,
+    smtp::{Security, Smtp},
+    telegram::Telegram,
+    throttle::Throttle,
+    tls::Tls,
+    translate::{Backend, TranslatedFields, Translation, Translator},
+    websub::Hub,
+    feed::{self, VirtualFeed},
+    Adaptive, ClientConfig, DateFallback, DateParser, Extractor, GuidStrategy, JsonApi, JsonPath,
+    Pagination, Passthrough, Plugin, Renderer, Retention, RetryPolicy, Scraper, ScraperBuilder, Selectors,
+    Stagger, DEFAULT_CONCURRENCY, DEFAULT_MAX_PAGES,
+};
+use anyhow::*;
+use chrono::{NaiveTime, Weekday};
+use chrono_tz::Tz;
+use ipnet::IpNet;
+use regex::Regex;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, HashSet},
+    env, fs,
+    net::IpAddr,
+    path::Path,
+    time::Duration,
+};
+use tracing::instrument;
+
+#[derive(Debug, Deserialize)]
+struct Config {
+    #[serde(rename = "scraper", default)]
+    scrapers: Vec<ScraperConfig>,
+    #[serde(rename = "feed", default)]
+    feeds: Vec<FeedConfig>,
+    #[serde(rename = "api", default)]
+    apis: Vec<ApiConfig>,
+    #[serde(rename = "plugin", default)]
+    plugins: Vec<PluginConfig>,
+    #[serde(rename = "virtual_feed", default)]
+    virtual_feeds: Vec<VirtualFeedConfig>,
+    #[serde(default)]
+    client: HttpConfig,
+    alerts: Option<AlertConfig>,
+    websub: Option<WebSubConfig>,
+    push: Option<PushConfig>,
+    digest: Option<DigestConfig>,
+    telegram: Option<TelegramConfig>,
+    translate: Option<TranslateConfig>,
+    clustering: Option<ClusteringConfig>,
+    activitypub: Option<ActivityPubConfig>,
+    export: Option<ExportConfig>,
+    tls: Option<TlsConfig>,
+    rate_limit: Option<RateLimitConfig>,
+    auth: Option<AuthConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AlertConfig {
+    webhook: String,
+    #[serde(default)]
+    format: WebhookFormat,
+}
+
+#[derive(Debug, Deserialize)]
+struct DigestConfig {
+    to: Vec<String>,
+    #[serde(default = "digest_subject")]
+    subject: String,
+    #[serde(default)]
+    schedule: Schedule,
+    at: Option<String>,
+    weekday: Option<String>,
+    #[serde(default)]
+    feeds: Vec<String>,
+    smtp: SmtpConfig,
+}
+
+fn digest_subject() -> String {
+    "news-rss digest".to_owned()
+}
+
+#[derive(Debug, Deserialize)]
+struct SmtpConfig {
+    host: String,
+    port: Option<u16>,
+    #[serde(default)]
+    security: Security,
+    username: Option<String>,
+    password: Option<String>,
+    from: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportConfig {
+    dir: Option<String>,
+    index: Option<bool>,
+    s3: Option<S3Config>,
+}
+
+#[derive(Debug, Deserialize)]
+struct S3Config {
+    bucket: String,
+    #[serde(default)]
+    prefix: String,
+    region: Option<String>,
+    endpoint: Option<String>,
+    access_key_id: Option<String>,
+    secret_access_key: Option<String>,
+    session_token: Option<String>,
+}
+
+fn credential(value: Option<String>, variable: &str) -> Result<String> {
+    value
+        .or_else(|| env::var(variable).ok())
+        .with_context(|| format!("Expected S3 credentials in the config or {}", variable))
+}
+
+#[derive(Debug, Deserialize)]
+struct RateLimitConfig {
+    requests_per_minute: u32,
+    #[serde(default)]
+    trusted_proxies: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AuthConfig {
+    #[serde(default)]
+    tokens: Vec<String>,
+    #[serde(default)]
+    users: Vec<UserConfig>,
+    #[serde(default)]
+    feeds: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UserConfig {
+    username: String,
+    password: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TlsConfig {
+    certificate: String,
+    private_key: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ActivityPubConfig {
+    private_key: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TelegramConfig {
+    token: String,
+    chat_id: String,
+    api_url: Option<String>,
+    interval: Option<u64>,
+    #[serde(default)]
+    feeds: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TranslateConfig {
+    backend: Backend,
+    url: Option<String>,
+    api_key: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClusteringConfig {
+    #[serde(default)]
+    collapse: bool,
+    similarity: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PushConfig {
+    webhook: String,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WebSubConfig {
+    hub: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RewriteConfig {
+    field: Field,
+    pattern: String,
+    #[serde(default)]
+    replacement: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct FeedConfig {
+    name: String,
+    description: Option<String>,
+    language: Option<String>,
+    link: Option<String>,
+    feed_url: String,
+    body_selector: OneOrMany,
+    timezone: Option<String>,
+    refresh_interval: Option<u64>,
+    concurrency: Option<usize>,
+    retry_attempts: Option<u32>,
+    retry_base_delay_ms: Option<u64>,
+    max_age: Option<u64>,
+    max_articles: Option<usize>,
+    start_offset: Option<u64>,
+    jitter: Option<f64>,
+    min_interval: Option<u64>,
+    max_interval: Option<u64>,
+    max_items: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiConfig {
+    name: String,
+    description: Option<String>,
+    language: Option<String>,
+    link: Option<String>,
+    url: String,
+    items_path: String,
+    headline_path: String,
+    link_path: String,
+    date_path: Option<String>,
+    date_format: Option<OneOrMany>,
+    timezone: Option<String>,
+    date_fallback: Option<DateFallback>,
+    image_path: Option<String>,
+    body_path: Option<String>,
+    author_path: Option<String>,
+    categories_path: Option<String>,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    refresh_interval: Option<u64>,
+    retry_attempts: Option<u32>,
+    retry_base_delay_ms: Option<u64>,
+    max_age: Option<u64>,
+    max_articles: Option<usize>,
+    start_offset: Option<u64>,
+    jitter: Option<f64>,
+    min_interval: Option<u64>,
+    max_interval: Option<u64>,
+    max_items: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PluginConfig {
+    name: String,
+    description: Option<String>,
+    language: Option<String>,
+    link: Option<String>,
+    url: String,
+    module: String,
+    timezone: Option<String>,
+    fuel: Option<u64>,
+    refresh_interval: Option<u64>,
+    concurrency: Option<usize>,
+    retry_attempts: Option<u32>,
+    retry_base_delay_ms: Option<u64>,
+    max_age: Option<u64>,
+    max_articles: Option<usize>,
+    start_offset: Option<u64>,
+    jitter: Option<f64>,
+    min_interval: Option<u64>,
+    max_interval: Option<u64>,
+    max_items: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VirtualFeedConfig {
+    name: String,
+    description: Option<String>,
+    #[serde(default)]
+    sources: Vec<String>,
+    #[serde(default)]
+    keywords: Vec<String>,
+    #[serde(default)]
+    patterns: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct HttpConfig {
+    connect_timeout: Option<u64>,
+    timeout: Option<u64>,
+    user_agent: Option<String>,
+    proxy: Option<String>,
+    max_redirects: Option<usize>,
+    cache_dir: Option<String>,
+    image_dir: Option<String>,
+    thumbnail_width: Option<u32>,
+    thumbnail_format: Option<ThumbnailFormat>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScraperConfig {
+    name: String,
+    description: Option<String>,
+    language: Option<String>,
+    base_url: String,
+    news_url: String,
+    article_selector: String,
+    headline_selector: OneOrMany,
+    image_selector: Option<OneOrMany>,
+    collect_images: Option<bool>,
+    author_selector: Option<OneOrMany>,
+    categories_selector: Option<OneOrMany>,
+    date_selector: OneOrMany,
+    date_attribute: Option<String>,
+    date_format: Option<OneOrMany>,
+    timezone: Option<String>,
+    date_fallback: Option<DateFallback>,
+    link_selector: OneOrMany,
+    body_selector: Option<OneOrMany>,
+    extractor: Option<Extractor>,
+    remove_selectors: Option<OneOrMany>,
+    #[serde(rename = "rewrite", default)]
+    rewrites: Vec<RewriteConfig>,
+    article_next_page_selector: Option<OneOrMany>,
+    prefer_amp: Option<bool>,
+    renderer: Option<Renderer>,
+    refresh_interval: Option<u64>,
+    concurrency: Option<usize>,
+    retry_attempts: Option<u32>,
+    retry_base_delay_ms: Option<u64>,
+    max_age: Option<u64>,
+    max_articles: Option<usize>,
+    start_offset: Option<u64>,
+    jitter: Option<f64>,
+    min_interval: Option<u64>,
+    max_interval: Option<u64>,
+    max_items: Option<usize>,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    #[serde(default)]
+    cookies: BTreeMap<String, String>,
+    next_page_selector: Option<OneOrMany>,
+    page_url_template: Option<String>,
+    max_pages: Option<usize>,
+    script: Option<String>,
+    guid: Option<Guid>,
+    guid_selector: Option<OneOrMany>,
+    guid_attribute: Option<String>,
+    track_updates: Option<bool>,
+    summary_only: Option<bool>,
+    summary_sentences: Option<usize>,
+    detect_language: Option<bool>,
+    translate_to: Option<String>,
+    translate_fields: Option<TranslatedFields>,
+    bump_pub_date: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Guid {
+    Link,
+    Hash,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl From<OneOrMany> for Vec<String> {
+    fn from(values: OneOrMany) -> Self {
+        match values {
+            OneOrMany::One(value) => vec![value],
+            OneOrMany::Many(values) => values,
+        }
+    }
+}
+
+impl From<OneOrMany> for Selectors {
+    fn from(selectors: OneOrMany) -> Self {
+        match selectors {
+            OneOrMany::One(selector) => selector.into(),
+            OneOrMany::Many(selectors) => selectors.into(),
+        }
+    }
+}
+
+fn retry(attempts: Option<u32>, base_delay_ms: Option<u64>) -> RetryPolicy {
+    RetryPolicy {
+        attempts: attempts.unwrap_or(RetryPolicy::DEFAULT.attempts),
+        base_delay: base_delay_ms.map_or(RetryPolicy::DEFAULT.base_delay, Duration::from_millis),
+        ..RetryPolicy::DEFAULT
+    }
+}
+
+fn retention(max_age: Option<u64>, max_articles: Option<usize>) -> Retention {
+    Retention {
+        max_age: max_age.map_or(Retention::DEFAULT.max_age, Duration::from_secs),
+        max_articles: max_articles.unwrap_or(Retention::DEFAULT.max_articles),
+    }
+}
+
+fn stagger(name: &str, start_offset: Option<u64>, jitter: Option<f64>) -> Result<Stagger> {
+    let jitter = jitter.unwrap_or(Stagger::DEFAULT_JITTER);
+    ensure!(
+        (0.0..=1.0).contains(&jitter),
+        "Expected jitter between 0 and 1 for {}",
+        name
+    );
+    Ok(Stagger {
+        offset: start_offset.map(Duration::from_secs),
+        jitter,
+    })
+}
+
+/// Intervals only adapt when given a bound; the refresh interval stands in for the other.
+fn adaptive(
+    name: &str,
+    interval: Option<u64>,
+    min_interval: Option<u64>,
+    max_interval: Option<u64>,
+) -> Result<Option<Adaptive>> {
+    if min_interval.is_none() && max_interval.is_none() {
+        return Ok(None);
+    }
+    let interval = interval.unwrap_or(60 * 60);
+    let (min_interval, max_interval) = (
+        min_interval.unwrap_or(interval),
+        max_interval.unwrap_or(interval),
+    );
+    ensure!(
+        0 < min_interval && min_interval <= max_interval,
+        "Expected min_interval to be positive and at most max_interval for {}",
+        name
+    );
+    Ok(Some(Adaptive {
+        min_interval: Duration::from_secs(min_interval),
+        max_interval: Duration::from_secs(max_interval),
+    }))
+}
+
+impl ScraperConfig {
+    fn into_scraper(self) -> Result<Scraper> {
+        let timezone = self
+            .timezone
+            .as_deref()
+            .map_or(Ok(Tz::UTC), str::parse::<Tz>)
+            .map_err(|e| anyhow!(e))
+            .with_context(|| format!("Invalid timezone for scraper {}", self.name))?;
+        let max_pages = self.max_pages.unwrap_or(DEFAULT_MAX_PAGES);
+        let pagination = match (self.next_page_selector, self.page_url_template) {
+            (Some(_), Some(_)) => bail!(
+                "Scraper {} cannot set both next_page_selector and page_url_template",
+                self.name
+            ),
+            (Some(selector), None) => Some(Pagination::Next {
+                selector: selector.into(),
+                max_pages,
+            }),
+            (None, Some(template)) => Some(Pagination::Template {
+                template: template.into(),
+                max_pages,
+            }),
+            (None, None) => None,
+        };
+        let name = &self.name;
+        let script = self
+            .script
+            .as_ref()
+            .map(|path| {
+                fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read script {}", path))
+                    .and_then(|source| Script::new(&source))
+                    .with_context(|| format!("Invalid script for scraper {}", name))
+            })
+            .transpose()?;
+        let guid = match (self.guid, self.guid_selector) {
+            (Some(_), Some(_)) => bail!(
+                "Scraper {} cannot set both guid and guid_selector",
+                self.name
+            ),
+            (Some(Guid::Link), None) => Some(GuidStrategy::Link),
+            (Some(Guid::Hash), None) => Some(GuidStrategy::Hash),
+            (None, Some(selector)) => Some(GuidStrategy::Selector {
+                selector: selector.into(),
+                attribute: self.guid_attribute.map(Into::into),
+            }),
+            (None, None) => None,
+        };
+        ensure!(
+            self.translate_to.is_some() || self.translate_fields.is_none(),
+            "Scraper {} sets translate_fields without translate_to",
+            self.name
+        );
+        let fields = self.translate_fields.unwrap_or_default();
+        let translation = self.translate_to.map(|target| Translation {
+            target: target.into(),
+            fields,
+        });
+        let stagger = stagger(&self.name, self.start_offset, self.jitter)?;
+        let adaptive = adaptive(
+            &self.name,
+            self.refresh_interval,
+            self.min_interval,
+            self.max_interval,
+        )?;
+        ScraperBuilder {
+            name: Some(self.name.into()),
+            description: self.description.map(Into::into),
+            language: self.language.map(Into::into),
+            base_url: Some(self.base_url.into()),
+            news_url: Some(self.news_url.into()),
+            article_selector: Some(self.article_selector.into()),
+            headline_selector: Some(self.headline_selector.into()),
+            image_selector: self.image_selector.map(Into::into),
+            collect_images: self.collect_images,
+            author_selector: self.author_selector.map(Into::into),
+            categories_selector: self.categories_selector.map(Into::into),
+            date_selector: Some(self.date_selector.into()),
+            date_attribute: self.date_attribute.map(Into::into),
+            parse_date: Some(DateParser::Format {
+                formats: self
+                    .date_format
+                    .map(Vec::<String>::from)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(Into::into)
+                    .collect(),
+                fallback: self.date_fallback.unwrap_or_default(),
+            }),
+            timezone: Some(timezone),
+            link_selector: Some(self.link_selector.into()),
+            body_selector: self.body_selector.map(Into::into),
+            extractor: self.extractor,
+            remove_selectors: self
+                .remove_selectors
+                .map(Vec::<String>::from)
+                .unwrap_or_default()
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            rewrites: self
+                .rewrites
+                .into_iter()
+                .map(|rewrite| (rewrite.field, rewrite.pattern.into(), rewrite.replacement.into()))
+                .collect(),
+            script,
+            article_next_page_selector: self.article_next_page_selector.map(Into::into),
+            prefer_amp: self.prefer_amp,
+            renderer: self.renderer,
+            interval: self.refresh_interval.map(Duration::from_secs),
+            concurrency: self.concurrency,
+            retry: Some(retry(self.retry_attempts, self.retry_base_delay_ms)),
+            retention: Some(retention(self.max_age, self.max_articles)),
+            stagger: Some(stagger),
+            adaptive,
+            headers: self
+                .headers
+                .into_iter()
+                .map(|(name, value)| (name.into(), value.into()))
+                .collect(),
+            cookies: self
+                .cookies
+                .into_iter()
+                .map(|(name, value)| (name.into(), value.into()))
+                .collect(),
+            pagination,
+            guid,
+            track_updates: self.track_updates,
+            summary_only: self.summary_only,
+            summary_sentences: self.summary_sentences,
+            detect_language: self.detect_language,
+            translation,
+            max_items: self.max_items,
+            bump_pub_date: self.bump_pub_date,
+        }
+        .build()
+    }
+}
+
+fn read(path: &Path) -> Result<Config> {
+    let config =
+        fs::read_to_string(path).with_context(|| format!("Failed to read config {:?}", path))?;
+    let config: Config = toml::from_str(&config).context("Failed to parse config")?;
+    config.check_names()?;
+    Ok(config)
+}
+
+impl Scraper {
+    #[instrument(skip(path), fields(path = ?path.as_ref()))]
+    pub fn from_config(path: impl AsRef<Path>) -> Result<Vec<Scraper>> {
+        let config = read(path.as_ref())?;
+        if config.translate.is_none() {
+            if let Some(scraper) = config.scrapers.iter().find(|scraper| scraper.translate_to.is_some()) {
+                bail!("Scraper {} sets translate_to without a [translate] backend", scraper.name);
+            }
+        }
+        config
+            .scrapers
+            .into_iter()
+            .map(ScraperConfig::into_scraper)
+            .collect()
+    }
+
+    /// A scraper from the fields of a single `[[scraper]]` table.
+    pub fn from_table(table: toml::Table) -> Result<Scraper> {
+        toml::Value::Table(table)
+            .try_into::<ScraperConfig>()
+            .context("Failed to parse scraper")?
+            .into_scraper()
+    }
+}
+
+impl FeedConfig {
+    fn into_passthrough(self) -> Result<Passthrough> {
+        let feed_url = self
+            .feed_url
+            .parse()
+            .with_context(|| format!("Invalid feed URL for {}", self.name))?;
+        let timezone = self
+            .timezone
+            .as_deref()
+            .map_or(Ok(Tz::UTC), str::parse::<Tz>)
+            .map_err(|e| anyhow!(e))
+            .with_context(|| format!("Invalid timezone for feed {}", self.name))?;
+        let body_selector = Selectors::from(self.body_selector);
+        body_selector.validate("body selector")?;
+        let stagger = stagger(&self.name, self.start_offset, self.jitter)?;
+        let adaptive = adaptive(
+            &self.name,
+            self.refresh_interval,
+            self.min_interval,
+            self.max_interval,
+        )?;
+        Ok(Passthrough {
+            name: self.name.into(),
+            description: self.description.map(Into::into),
+            language: self.language.map(Into::into),
+            link: self.link.map(Into::into),
+            feed_url,
+            body_selector,
+            timezone,
+            interval: Duration::from_secs(self.refresh_interval.unwrap_or(60 * 60)),
+            concurrency: self.concurrency.unwrap_or(DEFAULT_CONCURRENCY).max(1),
+            retry: retry(self.retry_attempts, self.retry_base_delay_ms),
+            retention: retention(self.max_age, self.max_articles),
+            stagger,
+            adaptive,
+            max_items: self.max_items,
+        })
+    }
+}
+
+impl Passthrough {
+    #[instrument(skip(path), fields(path = ?path.as_ref()))]
+    pub fn from_config(path: impl AsRef<Path>) -> Result<Vec<Passthrough>> {
+        read(path.as_ref())?
+            .feeds
+            .into_iter()
+            .map(FeedConfig::into_passthrough)
+            .collect()
+    }
+}
+
+fn path(name: &str, path: String) -> Result<JsonPath> {
+    path.parse().with_context(|| format!("Invalid {}", name))
+}
+
+impl ApiConfig {
+    fn into_json_api(self) -> Result<JsonApi> {
+        let url = self
+            .url
+            .parse()
+            .with_context(|| format!("Invalid URL for API {}", self.name))?;
+        let timezone = self
+            .timezone
+            .as_deref()
+            .map_or(Ok(Tz::UTC), str::parse::<Tz>)
+            .map_err(|e| anyhow!(e))
+            .with_context(|| format!("Invalid timezone for API {}", self.name))?;
+        let mut headers = HeaderMap::new();
+        for (header, value) in &self.headers {
+            headers.append(
+                HeaderName::from_bytes(header.as_bytes()).with_context(|| {
+                    format!("Invalid header name {:?} for API {}", header, self.name)
+                })?,
+                HeaderValue::from_str(value).with_context(|| {
+                    format!("Invalid value for header {} of API {}", header, self.name)
+                })?,
+            );
+        }
+        Ok(JsonApi {
+            url,
+            headers,
+            items_path: path("items_path", self.items_path)?,
+            headline_path: path("headline_path", self.headline_path)?,
+            link_path: path("link_path", self.link_path)?,
+            date_path: self
+                .date_path
+                .map(|date| path("date_path", date))
+                .transpose()?,
+            parse_date: DateParser::Format {
+                formats: self
+                    .date_format
+                    .map(Vec::<String>::from)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(Into::into)
+                    .collect(),
+                fallback: self.date_fallback.unwrap_or_default(),
+            },
+            timezone,
+            image_path: self
+                .image_path
+                .map(|image| path("image_path", image))
+                .transpose()?,
+            body_path: self
+                .body_path
+                .map(|body| path("body_path", body))
+                .transpose()?,
+            author_path: self
+                .author_path
+                .map(|author| path("author_path", author))
+                .transpose()?,
+            categories_path: self
+                .categories_path
+                .map(|categories| path("categories_path", categories))
+                .transpose()?,
+            interval: Duration::from_secs(self.refresh_interval.unwrap_or(60 * 60)),
+            retry: retry(self.retry_attempts, self.retry_base_delay_ms),
+            retention: retention(self.max_age, self.max_articles),
+            stagger: stagger(&self.name, self.start_offset, self.jitter)?,
+            adaptive: adaptive(
+                &self.name,
+                self.refresh_interval,
+                self.min_interval,
+                self.max_interval,
+            )?,
+            max_items: self.max_items,
+            name: self.name.into(),
+            description: self.description.map(Into::into),
+            language: self.language.map(Into::into),
+            link: self.link.map(Into::into),
+        })
+    }
+}
+
+impl JsonApi {
+    #[instrument(skip(path), fields(path = ?path.as_ref()))]
+    pub fn from_config(path: impl AsRef<Path>) -> Result<Vec<JsonApi>> {
+        read(path.as_ref())?
+            .apis
+            .into_iter()
+            .map(ApiConfig::into_json_api)
+            .collect()
+    }
+}
+
+impl PluginConfig {
+    fn into_plugin(self) -> Result<Plugin> {
+        let url = self
+            .url
+            .parse()
+            .with_context(|| format!("Invalid URL for plugin {}", self.name))?;
+        let timezone = self
+            .timezone
+            .as_deref()
+            .map_or(Ok(Tz::UTC), str::parse::<Tz>)
+            .map_err(|e| anyhow!(e))
+            .with_context(|| format!("Invalid timezone for plugin {}", self.name))?;
+        let wasm = fs::read(&self.module)
+            .with_context(|| format!("Failed to read module {}", self.module))?;
+        let plugin = Plugin::new(self.name.clone(), url, &wasm)
+            .with_context(|| format!("Failed to load plugin {}", self.name))?;
+        let stagger = stagger(&self.name, self.start_offset, self.jitter)?;
+        let adaptive = adaptive(
+            &self.name,
+            self.refresh_interval,
+            self.min_interval,
+            self.max_interval,
+        )?;
+        Ok(Plugin {
+            description: self.description.map(Into::into),
+            language: self.language.map(Into::into),
+            link: self.link.map(Into::into),
+            timezone,
+            interval: Duration::from_secs(self.refresh_interval.unwrap_or(60 * 60)),
+            concurrency: self.concurrency.unwrap_or(DEFAULT_CONCURRENCY).max(1),
+            retry: retry(self.retry_attempts, self.retry_base_delay_ms),
+            retention: retention(self.max_age, self.max_articles),
+            stagger,
+            adaptive,
+            max_items: self.max_items,
+            fuel: self.fuel.unwrap_or(plugin.fuel),
+            ..plugin
+        })
+    }
+}
+
+impl Config {
+    fn source_names(&self) -> impl Iterator<Item = &str> {
+        self.scrapers
+            .iter()
+            .map(|scraper| scraper.name.as_str())
+            .chain(self.feeds.iter().map(|feed| feed.name.as_str()))
+            .chain(self.apis.iter().map(|api| api.name.as_str()))
+            .chain(self.plugins.iter().map(|plugin| plugin.name.as_str()))
+    }
+
+    /// Feeds are found by name ignoring case, so no two sources can share one, nor take the
+    /// combined feed's.
+    fn check_names(&self) -> Result<()> {
+        let mut names = HashSet::new();
+        for name in self.source_names() {
+            ensure!(
+                !name.eq_ignore_ascii_case(feed::COMBINED),
+                "The name {} is reserved for the combined feed",
+                name
+            );
+            ensure!(
+                names.insert(name.to_ascii_lowercase()),
+                "More than one source is named {}",
+                name
+            );
+        }
+        Ok(())
+    }
+}
+
+impl VirtualFeedConfig {
+    fn into_virtual_feed(self, sources: &[String]) -> Result<VirtualFeed> {
+        ensure!(
+            !self.keywords.is_empty() || !self.patterns.is_empty(),
+            "Expected keywords or patterns for virtual feed {}",
+            self.name
+        );
+        ensure!(
+            !self.name.eq_ignore_ascii_case(feed::COMBINED)
+                && !sources.iter().any(|source| source.eq_ignore_ascii_case(&self.name)),
+            "Virtual feed {} has the same name as another feed",
+            self.name
+        );
+        if let Some(unknown) = self
+            .sources
+            .iter()
+            .find(|name| !sources.iter().any(|source| source.eq_ignore_ascii_case(name)))
+        {
+            bail!("Virtual feed {} draws from unknown feed {}", self.name, unknown);
+        }
+        let patterns = self
+            .patterns
+            .iter()
+            .map(|pattern| Regex::new(pattern))
+            .collect::<Result<_, _>>()
+            .with_context(|| format!("Invalid pattern for virtual feed {}", self.name))?;
+        Ok(VirtualFeed {
+            name: self.name,
+            description: self.description,
+            sources: self.sources,
+            keywords: self.keywords,
+            patterns,
+        })
+    }
+}
+
+impl Plugin {
+    #[instrument(skip(path), fields(path = ?path.as_ref()))]
+    pub fn from_config(path: impl AsRef<Path>) -> Result<Vec<Plugin>> {
+        read(path.as_ref())?
+            .plugins
+            .into_iter()
+            .map(PluginConfig::into_plugin)
+            .collect()
+    }
+}
+
+impl VirtualFeed {
+    #[instrument(skip(path), fields(path = ?path.as_ref()))]
+    pub fn from_config(path: impl AsRef<Path>) -> Result<Vec<VirtualFeed>> {
+        let config = read(path.as_ref())?;
+        let sources = config.source_names().map(str::to_owned).collect::<Vec<_>>();
+        let mut virtual_feeds = Vec::<VirtualFeed>::new();
+        for virtual_feed in config.virtual_feeds {
+            ensure!(
+                !virtual_feeds
+                    .iter()
+                    .any(|other| other.name.eq_ignore_ascii_case(&virtual_feed.name)),
+                "Duplicate virtual feed {}",
+                virtual_feed.name
+            );
+            virtual_feeds.push(virtual_feed.into_virtual_feed(&sources)?);
+        }
+        Ok(virtual_feeds)
+    }
+}
+
+impl ClientConfig {
+    #[instrument(skip(path), fields(path = ?path.as_ref()))]
+    pub fn from_config(path: impl AsRef<Path>) -> Result<ClientConfig> {
+        let client = read(path.as_ref())?.client;
+        Ok(ClientConfig {
+            connect_timeout: client.connect_timeout.map(Duration::from_secs),
+            timeout: client.timeout.map(Duration::from_secs),
+            user_agent: client.user_agent,
+            proxy: client.proxy,
+            max_redirects: client.max_redirects,
+            cache_dir: client.cache_dir.map(Into::into),
+            image_dir: client.image_dir.map(Into::into),
+            thumbnail_width: client.thumbnail_width,
+            thumbnail_format: client.thumbnail_format,
+        })
+    }
+}
+
+impl Webhook {
+    #[instrument(skip(path), fields(path = ?path.as_ref()))]
+    pub fn from_config(path: impl AsRef<Path>) -> Result<Option<Webhook>> {
+        read(path.as_ref())?
+            .alerts
+            .map(|alerts| {
+                Ok(Webhook {
+                    url: alerts
+                        .webhook
+                        .parse()
+                        .context("Invalid alert webhook URL")?,
+                    format: alerts.format,
+                })
+            })
+            .transpose()
+    }
+}
+
+impl Push {
+    #[instrument(skip(path), fields(path = ?path.as_ref()))]
+    pub fn from_config(path: impl AsRef<Path>) -> Result<Option<Push>> {
+        read(path.as_ref())?
+            .push
+            .map(|push| {
+                let mut headers = HeaderMap::new();
+                for (header, value) in &push.headers {
+                    headers.append(
+                        HeaderName::from_bytes(header.as_bytes()).with_context(|| {
+                            format!("Invalid header name {:?} for push webhook", header)
+                        })?,
+                        HeaderValue::from_str(value).with_context(|| {
+                            format!("Invalid value for header {} of push webhook", header)
+                        })?,
+                    );
+                }
+                Ok(Push {
+                    url: push.webhook.parse().context("Invalid push webhook URL")?,
+                    headers,
+                })
+            })
+            .transpose()
+    }
+}
+
+impl Digest {
+    #[instrument(skip(path), fields(path = ?path.as_ref()))]
+    pub fn from_config(path: impl AsRef<Path>) -> Result<Option<Digest>> {
+        read(path.as_ref())?
+            .digest
+            .map(|digest| {
+                ensure!(
+                    !digest.to.is_empty(),
+                    "Digest requires at least one recipient"
+                );
+                let smtp = digest.smtp;
+                Ok(Digest {
+                    at: digest
+                        .at
+                        .as_deref()
+                        .map_or(Ok(NaiveTime::MIN), |at| {
+                            NaiveTime::parse_from_str(at, "%H:%M")
+                        })
+                        .context("Invalid digest time, expected HH:MM")?,
+                    weekday: digest
+                        .weekday
+                        .as_deref()
+                        .map_or(Ok(Weekday::Mon), str::parse)
+                        .map_err(|_| anyhow!("Invalid digest weekday"))?,
+                    smtp: Smtp {
+                        port: smtp.port.unwrap_or_else(|| smtp.security.default_port()),
+                        host: smtp.host,
+                        security: smtp.security,
+                        username: smtp.username,
+                        password: smtp.password,
+                        from: smtp.from,
+                    },
+                    to: digest.to,
+                    subject: digest.subject,
+                    schedule: digest.schedule,
+                    feeds: digest.feeds,
+                })
+            })
+            .transpose()
+    }
+}
+
+impl Telegram {
+    #[instrument(skip(path), fields(path = ?path.as_ref()))]
+    pub fn from_config(path: impl AsRef<Path>) -> Result<Option<Telegram>> {
+        read(path.as_ref())?
+            .telegram
+            .map(|telegram| {
+                Ok(Telegram {
+                    token: telegram.token,
+                    chat_id: telegram.chat_id,
+                    api_url: telegram
+                        .api_url
+                        .as_deref()
+                        .unwrap_or("https://api.telegram.org/")
+                        .parse()
+                        .context("Invalid Telegram API URL")?,
+                    interval: Duration::from_secs(telegram.interval.unwrap_or(3)),
+                    feeds: telegram.feeds,
+                })
+            })
+            .transpose()
+    }
+}
+
+impl Translator {
+    #[instrument(skip(path), fields(path = ?path.as_ref()))]
+    pub fn from_config(path: impl AsRef<Path>) -> Result<Option<Translator>> {
+        read(path.as_ref())?
+            .translate
+            .map(|translate| {
+                Ok(Translator {
+                    url: translate
+                        .url
+                        .as_deref()
+                        .unwrap_or_else(|| translate.backend.default_url(translate.api_key.as_deref()))
+                        .parse()
+                        .context("Invalid translation API URL")?,
+                    backend: translate.backend,
+                    api_key: translate.api_key,
+                })
+            })
+            .transpose()
+    }
+}
+
+impl Clustering {
+    #[instrument(skip(path), fields(path = ?path.as_ref()))]
+    pub fn from_config(path: impl AsRef<Path>) -> Result<Option<Clustering>> {
+        read(path.as_ref())?
+            .clustering
+            .map(|clustering| {
+                let similarity = clustering.similarity.unwrap_or(Clustering::DEFAULT_SIMILARITY);
+                ensure!(
+                    similarity > 0.0 && similarity <= 1.0,
+                    "Clustering similarity must be between 0 and 1"
+                );
+                Ok(Clustering {
+                    collapse: clustering.collapse,
+                    similarity,
+                })
+            })
+            .transpose()
+    }
+}
+
+impl Hub {
+    #[instrument(skip(path), fields(path = ?path.as_ref()))]
+    pub fn from_config(path: impl AsRef<Path>) -> Result<Option<Hub>> {
+        read(path.as_ref())?
+            .websub
+            .map(|websub| {
+                Ok(Hub {
+                    url: websub.hub.parse().context("Invalid WebSub hub URL")?,
+                })
+            })
+            .transpose()
+    }
+}
+
+impl ActivityPub {
+    /// Reads the PEM private key that signs deliveries from the path in `[activitypub]`.
+    #[instrument(skip(path), fields(path = ?path.as_ref()))]
+    pub fn from_config(path: impl AsRef<Path>, public_url: &str) -> Result<Option<ActivityPub>> {
+        read(path.as_ref())?
+            .activitypub
+            .map(|activitypub| {
+                let key = fs::read_to_string(&activitypub.private_key).with_context(|| {
+                    format!("Failed to read private key {}", activitypub.private_key)
+                })?;
+                ActivityPub::new(public_url, &key)
+            })
+            .transpose()
+    }
+}
+
+impl Export {
+    #[instrument(skip(path), fields(path = ?path.as_ref()))]
+    pub fn from_config(path: impl AsRef<Path>) -> Result<Option<Export>> {
+        read(path.as_ref())?
+            .export
+            .map(|export| {
+                let s3 = export
+                    .s3
+                    .map(|s3| -> Result<S3> {
+                        Ok(S3 {
+                            bucket: s3.bucket,
+                            prefix: s3.prefix,
+                            region: s3.region.unwrap_or_else(|| "us-east-1".to_owned()),
+                            endpoint: s3
+                                .endpoint
+                                .map(|endpoint| endpoint.parse())
+                                .transpose()
+                                .context("Invalid S3 endpoint")?,
+                            access_key_id: credential(s3.access_key_id, "AWS_ACCESS_KEY_ID")?,
+                            secret_access_key: credential(
+                                s3.secret_access_key,
+                                "AWS_SECRET_ACCESS_KEY",
+                            )?,
+                            session_token: s3
+                                .session_token
+                                .or_else(|| env::var("AWS_SESSION_TOKEN").ok()),
+                        })
+                    })
+                    .transpose()?;
+                ensure!(
+                    export.dir.is_some() || s3.is_some(),
+                    "Expected an export dir or [export.s3] bucket"
+                );
+                Ok(Export {
+                    dir: export.dir.map(Into::into),
+                    s3,
+                    index: export.index.unwrap_or(true),
+                    public_url: None,
+                })
+            })
+            .transpose()
+    }
+}
+
+impl Tls {
+    #[instrument(skip(path), fields(path = ?path.as_ref()))]
+    pub fn from_config(path: impl AsRef<Path>) -> Result<Option<Tls>> {
+        Ok(read(path.as_ref())?.tls.map(|tls| Tls {
+            certificate: tls.certificate.into(),
+            private_key: tls.private_key.into(),
+        }))
+    }
+}
+
+impl Auth {
+    #[instrument(skip(path), fields(path = ?path.as_ref()))]
+    pub fn from_config(path: impl AsRef<Path>) -> Result<Option<Auth>> {
+        read(path.as_ref())?.auth.map(|auth| {
+            ensure!(
+                !auth.tokens.is_empty() || !auth.users.is_empty(),
+                "Expected auth tokens or users"
+            );
+            ensure!(
+                auth.tokens.iter().all(|token| !token.is_empty()),
+                "Expected non-empty auth tokens"
+            );
+            ensure!(
+                auth.users.iter().all(|user| !user.username.contains(':')),
+                "Expected auth usernames without a colon"
+            );
+            Ok(Auth {
+                tokens: auth.tokens,
+                users: auth
+                    .users
+                    .into_iter()
+                    .map(|user| User {
+                        username: user.username,
+                        password: user.password,
+                    })
+                    .collect(),
+                feeds: auth.feeds,
+            })
+        })
+        .transpose()
+    }
+}
+
+impl Throttle {
+    #[instrument(skip(path), fields(path = ?path.as_ref()))]
+    pub fn from_config(path: impl AsRef<Path>) -> Result<Option<Throttle>> {
+        read(path.as_ref())?
+            .rate_limit
+            .map(|limit| {
+                ensure!(
+                    limit.requests_per_minute > 0,
+                    "Expected a positive requests_per_minute"
+                );
+                let trusted_proxies = limit
+                    .trusted_proxies
+                    .iter()
+                    .map(|proxy| {
+                        proxy
+                            .parse()
+                            .or_else(|_| proxy.parse::<IpAddr>().map(IpNet::from))
+                            .with_context(|| format!("Invalid trusted proxy {:?}", proxy))
+                    })
+                    .collect::<Result<_>>()?;
+                Ok(Throttle {
+                    requests_per_minute: limit.requests_per_minute,
+                    trusted_proxies,
+                })
+            })
+            .transpose()
+    }
+}
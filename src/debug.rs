@@ -0,0 +1,135 @@
+use crate::{retry, Scraper, Selectors};
+use anyhow::*;
+use nipper::{Document, Selection};
+use reqwest::{Client, Url};
+use serde::Serialize;
+use std::fs;
+use tracing::instrument;
+
+const MAX_MATCHES: usize = 5;
+
+#[derive(Debug, Serialize)]
+pub struct Element {
+    pub text: String,
+    pub href: Option<String>,
+    pub src: Option<String>,
+    pub attribute: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SelectorReport {
+    pub field: &'static str,
+    pub selector: String,
+    pub count: usize,
+    pub used: bool,
+    pub elements: Vec<Element>,
+}
+
+fn element(selection: &Selection, attribute: Option<&str>) -> Element {
+    let attr = |name: &str| selection.attr(name).map(|value| value.to_string());
+    Element {
+        text: selection
+            .text()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" "),
+        href: attr("href"),
+        src: attr("src"),
+        attribute: attribute.and_then(attr),
+    }
+}
+
+fn report<'a>(
+    field: &'static str,
+    selectors: &Selectors,
+    attribute: Option<&str>,
+    select: impl Fn(&str) -> Vec<Selection<'a>>,
+) -> Vec<SelectorReport> {
+    let mut used = false;
+    selectors
+        .iter()
+        .map(|selector| {
+            let selections = select(selector);
+            let elements = selections
+                .iter()
+                .flat_map(Selection::iter)
+                .collect::<Vec<_>>();
+            let report = SelectorReport {
+                field,
+                selector: selector.to_owned(),
+                count: elements.len(),
+                used: !used && !elements.is_empty(),
+                elements: elements
+                    .iter()
+                    .take(MAX_MATCHES)
+                    .map(|selection| element(selection, attribute))
+                    .collect(),
+            };
+            used |= report.used;
+            report
+        })
+        .collect()
+}
+
+impl Scraper {
+    #[instrument(skip(self, client), fields(self.name))]
+    pub async fn debug_selectors(
+        &self,
+        client: &Client,
+        location: &str,
+    ) -> Result<Vec<SelectorReport>> {
+        let url = Url::parse(location)
+            .ok()
+            .filter(|url| url.scheme() == "http" || url.scheme() == "https");
+        let html = match url {
+            Some(url) => retry::fetch(client, url, self.retry, &self.headers()).await?,
+            None => fs::read_to_string(location)
+                .with_context(|| format!("Failed to read {:?}", location))?,
+        };
+        let document = Document::from(&html);
+        let articles = document.select(&self.article_selector);
+        let article_selectors = Selectors::Owned(vec![self.article_selector.to_string()]);
+        let in_document = |selector: &str| vec![document.select(selector)];
+        let in_articles = |selector: &str| {
+            articles
+                .iter()
+                .map(|article| article.select(selector))
+                .collect()
+        };
+        let mut reports = report("article", &article_selectors, None, in_document);
+        reports.extend(report(
+            "headline",
+            &self.headline_selector,
+            None,
+            in_articles,
+        ));
+        reports.extend(report("link", &self.link_selector, None, in_articles));
+        let date_attribute = self.date_attribute.as_deref();
+        reports.extend(report(
+            "date",
+            &self.date_selector,
+            date_attribute,
+            in_document,
+        ));
+        if let Some(body) = &self.body_selector {
+            reports.extend(report("body", body, None, in_document));
+        }
+        let optional = [
+            ("image", &self.image_selector),
+            ("author", &self.author_selector),
+            ("categories", &self.categories_selector),
+        ];
+        for (field, selectors) in optional {
+            if let Some(selectors) = selectors {
+                reports.extend(report(field, selectors, None, in_document));
+            }
+        }
+        Ok(reports)
+    }
+
+    /// Reports on the listing page the scraper starts from.
+    pub async fn debug_listing(&self, client: &Client) -> Result<Vec<SelectorReport>> {
+        let news_url = self.url(&self.news_url)?;
+        self.debug_selectors(client, news_url.as_str()).await
+    }
+}
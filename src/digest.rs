@@ -0,0 +1,71 @@
+use crate::{
+    render,
+    smtp::{Message, Smtp},
+    Article,
+};
+use chrono::{DateTime, Datelike, Duration, NaiveTime, Utc, Weekday};
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Schedule {
+    #[default]
+    Daily,
+    Weekly,
+}
+
+#[derive(Debug, Clone)]
+pub struct Digest {
+    pub smtp: Smtp,
+    pub to: Vec<String>,
+    pub subject: String,
+    pub schedule: Schedule,
+    /// Time of day in UTC.
+    pub at: NaiveTime,
+    pub weekday: Weekday,
+    /// Feeds to include; every feed when empty.
+    pub feeds: Vec<String>,
+}
+
+impl Digest {
+    pub fn period(&self) -> Duration {
+        match self.schedule {
+            Schedule::Daily => Duration::days(1),
+            Schedule::Weekly => Duration::weeks(1),
+        }
+    }
+
+    /// The first scheduled send strictly after `after`.
+    pub fn next(&self, after: DateTime<Utc>) -> DateTime<Utc> {
+        let mut next = after.date_naive().and_time(self.at).and_utc();
+        while next <= after || self.schedule == Schedule::Weekly && next.weekday() != self.weekday {
+            next += Duration::days(1);
+        }
+        next
+    }
+
+    pub fn includes(&self, feed: &str) -> bool {
+        self.feeds.is_empty()
+            || self
+                .feeds
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(feed))
+    }
+
+    /// Renders the articles from each feed, or nothing when there are none to send.
+    pub fn message(&self, sections: &[(&str, Vec<Article>)]) -> Option<Message> {
+        if sections.iter().all(|(_, articles)| articles.is_empty()) {
+            return None;
+        }
+        let sections = sections
+            .iter()
+            .filter(|(_, articles)| !articles.is_empty())
+            .cloned()
+            .collect::<Vec<_>>();
+        Some(Message {
+            to: self.to.clone(),
+            subject: self.subject.clone(),
+            html: render::digest(&self.subject, &sections),
+        })
+    }
+}
@@ -0,0 +1,53 @@
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+use regex::Regex;
+use std::sync::LazyLock;
+use tracing::trace;
+
+const PRESCAN_LENGTH: usize = 1024;
+
+static META_CHARSET: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?i)<meta[^>]+charset\s*=\s*["']?\s*([a-z0-9_:.-]+)"#).unwrap());
+
+fn header_encoding(content_type: &str) -> Option<&'static Encoding> {
+    content_type
+        .split(';')
+        .filter_map(|param| param.split_once('='))
+        .find(|(key, _)| key.trim().eq_ignore_ascii_case("charset"))
+        .and_then(|(_, charset)| Encoding::for_label(charset.trim().trim_matches('"').as_bytes()))
+}
+
+fn meta_encoding(bytes: &[u8]) -> Option<&'static Encoding> {
+    let prefix = String::from_utf8_lossy(&bytes[..bytes.len().min(PRESCAN_LENGTH)]);
+    META_CHARSET
+        .captures(&prefix)
+        .and_then(|captures| Encoding::for_label(captures[1].as_bytes()))
+        .map(Encoding::output_encoding)
+}
+
+pub(crate) fn decode(bytes: &[u8], content_type: Option<&str>) -> String {
+    let declared = content_type
+        .and_then(header_encoding)
+        .or_else(|| meta_encoding(bytes));
+    let encoding = match Encoding::for_bom(bytes) {
+        Some((encoding, _)) => encoding,
+        None if declared.is_none_or(Encoding::is_ascii_compatible)
+            && std::str::from_utf8(bytes).is_ok() =>
+        {
+            UTF_8
+        }
+        None => [declared, meta_encoding(bytes)]
+            .iter()
+            .flatten()
+            .copied()
+            .find(|encoding| *encoding != UTF_8)
+            .unwrap_or(WINDOWS_1252),
+    };
+    if let Some(declared) = declared.filter(|declared| *declared != encoding && !bytes.is_ascii()) {
+        trace!(
+            declared = declared.name(),
+            detected = encoding.name(),
+            "Ignoring declared charset"
+        );
+    }
+    encoding.decode(bytes).0.into_owned()
+}
@@ -0,0 +1,127 @@
+use crate::{
+    feed::{self, Feed},
+    render::{self, Format},
+    s3::S3,
+};
+use anyhow::*;
+use bytes::Bytes;
+use futures::future::join_all;
+use reqwest::Client;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+use tracing::{instrument, trace, warn};
+
+#[derive(Debug, Clone)]
+pub struct Export {
+    pub dir: Option<PathBuf>,
+    pub s3: Option<S3>,
+    /// Also write an index.html listing every feed, and an OPML file when the public URL is known.
+    pub index: bool,
+    pub public_url: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct File {
+    pub name: String,
+    pub content_type: &'static str,
+    pub contents: Bytes,
+}
+
+static EXPORT: OnceLock<Export> = OnceLock::new();
+
+pub fn configure(export: Export) {
+    EXPORT.set(export).ok();
+}
+
+pub fn get() -> Option<&'static Export> {
+    EXPORT.get()
+}
+
+// Written beside the target and renamed over it, so a web server never sees a partial file.
+fn replace(path: &Path, contents: &[u8]) -> Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    fs::write(&temporary, contents)
+        .and_then(|()| fs::rename(&temporary, path))
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+impl Export {
+    /// Every format of the named feeds and the stylesheet they link to, then the index.
+    pub fn files(&self, feeds: &HashMap<String, Feed>, names: &[&str]) -> Vec<File> {
+        let mut files = names
+            .iter()
+            .filter_map(|name| feeds.get(*name).map(|feed| (name, feed)))
+            .flat_map(|(name, feed)| {
+                Format::ALL.iter().map(move |format| File {
+                    name: format!("{}.{}", name.to_lowercase(), format.extension()),
+                    content_type: format.content_type(),
+                    contents: feed.rendered.get(*format),
+                })
+            })
+            .collect::<Vec<_>>();
+        files.push(File {
+            name: render::STYLESHEET_PATH.trim_start_matches('/').to_owned(),
+            content_type: "text/xsl",
+            contents: render::STYLESHEET.into(),
+        });
+        if self.index {
+            let mut names = feeds
+                .keys()
+                .map(String::as_str)
+                .filter(|name| *name != feed::COMBINED)
+                .collect::<Vec<_>>();
+            names.sort_unstable();
+            names.push(feed::COMBINED);
+            let index = names
+                .iter()
+                .map(|name| (*name, feeds.get(*name)))
+                .collect::<Vec<_>>();
+            files.push(File {
+                name: "index.html".to_owned(),
+                content_type: "text/html; charset=utf-8",
+                contents: render::index(&index).into(),
+            });
+            if let Some(public_url) = &self.public_url {
+                files.push(File {
+                    name: "opml".to_owned(),
+                    content_type: "text/x-opml",
+                    contents: render::opml(public_url, &names).into(),
+                });
+            }
+        }
+        files
+    }
+
+    /// Writes files to the output directory and uploads them to the bucket, whichever are
+    /// configured.
+    #[instrument(skip(self, client, files), fields(files = files.len()))]
+    pub async fn publish(&self, client: &Client, files: &[File]) -> Result<()> {
+        if let Some(dir) = &self.dir {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+            for file in files {
+                replace(&dir.join(&file.name), &file.contents)?;
+            }
+            trace!(?dir, "Wrote feeds");
+        }
+        if let Some(s3) = &self.s3 {
+            let failed = join_all(files.iter().map(|file| async move {
+                s3.put(client, &file.name, file.content_type, file.contents.clone())
+                    .await
+                    .map_err(|error| warn!(?error, file = file.name.as_str(), "Failed to upload"))
+            }))
+            .await
+            .into_iter()
+            .filter(Result::is_err)
+            .count();
+            ensure!(failed == 0, "Failed to upload {} files", failed);
+            trace!(bucket = s3.bucket.as_str(), "Uploaded feeds");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,278 @@
+use crate::{
+    cluster,
+    render::{Channel, Rendered},
+    Article, Image,
+};
+use chrono::{DateTime, Utc};
+use fnv::FnvHasher;
+use nipper::Document;
+use regex::Regex;
+use std::{
+    cmp::Reverse,
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    hash::{Hash, Hasher},
+    time::Duration,
+};
+use tracing::trace;
+
+pub const COMBINED: &str = "All";
+
+#[derive(Debug, Clone, Copy)]
+pub struct Retention {
+    pub max_age: Duration,
+    pub max_articles: usize,
+}
+
+impl Retention {
+    pub const DEFAULT: Retention = Retention {
+        max_age: Duration::from_secs(7 * 24 * 60 * 60),
+        max_articles: 100,
+    };
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Retention::DEFAULT
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Feed {
+    pub name: String,
+    pub channel: Channel,
+    pub articles: Vec<Article>,
+    pub rendered: Rendered,
+    pub hash: u64,
+    pub updated: DateTime<Utc>,
+}
+
+/// A hash of everything extracted for an article, which is stored alongside it, so it uses a
+/// hasher that is stable across builds. The `updated` timestamp is derived from this, so it isn't
+/// included, and images only count by URL, as probes can see a different type or length.
+pub fn content_hash(article: &Article) -> u64 {
+    let mut hasher = FnvHasher::default();
+    article.headline.hash(&mut hasher);
+    article.link.as_str().hash(&mut hasher);
+    article.body.hash(&mut hasher);
+    article.description.hash(&mut hasher);
+    article.language.hash(&mut hasher);
+    article
+        .image
+        .as_ref()
+        .map(|image| image.url.as_str())
+        .hash(&mut hasher);
+    article
+        .images
+        .iter()
+        .map(|image| image.url.as_str())
+        .collect::<Vec<_>>()
+        .hash(&mut hasher);
+    article.author.hash(&mut hasher);
+    article.categories.hash(&mut hasher);
+    article.date.timestamp().hash(&mut hasher);
+    article.guid.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn hash(channel: &Channel, articles: &[Article]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    channel.icon.as_ref().map(|icon| icon.url.as_str()).hash(&mut hasher);
+    for article in articles {
+        content_hash(article).hash(&mut hasher);
+        article.alternates.hash(&mut hasher);
+        article
+            .updated
+            .map(|updated| updated.timestamp())
+            .hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+pub fn sort(articles: &mut [Article]) {
+    articles.sort_by(|a, b| b.date.cmp(&a.date).then_with(|| a.id().cmp(b.id())));
+}
+
+impl Feed {
+    pub fn new(channel: Channel, mut articles: Vec<Article>) -> Feed {
+        sort(&mut articles);
+        Feed {
+            name: channel.title.clone(),
+            hash: hash(&channel, &articles),
+            rendered: Rendered::new(&channel, &articles),
+            channel,
+            articles,
+            updated: Utc::now(),
+        }
+    }
+
+    /// Merges a scrape into the feed, returning whether anything changed.
+    pub fn update(&mut self, mut articles: Vec<Article>, retention: Retention) -> bool {
+        let now = Utc::now();
+        let previous = self
+            .articles
+            .iter()
+            .map(|article| (article.id(), article))
+            .collect::<HashMap<_, _>>();
+        for article in &mut articles {
+            let previous = match previous.get(article.id()) {
+                Some(previous) => previous,
+                None => continue,
+            };
+            if content_hash(previous) != content_hash(article) {
+                trace!(
+                    article = article.id(),
+                    "Article changed since the last scrape"
+                );
+                article.updated = Some(now.with_timezone(&article.date.timezone()));
+            } else {
+                article.updated = article.updated.or(previous.updated);
+            }
+        }
+        let cutoff = chrono::Duration::from_std(retention.max_age)
+            .ok()
+            .and_then(|max_age| Utc::now().checked_sub_signed(max_age));
+        let current = articles
+            .iter()
+            .map(|article| article.id().to_owned())
+            .collect::<HashSet<_>>();
+        let mut retained = self
+            .articles
+            .drain(..)
+            .filter(|article| !current.contains(article.id()))
+            .filter(|article| cutoff.is_none_or(|cutoff| article.date >= cutoff))
+            .collect::<Vec<_>>();
+        retained.sort_by_key(|article| Reverse(article.date));
+        let limit = retention.max_articles.max(articles.len());
+        articles.extend(retained);
+        articles.truncate(limit);
+        self.replace(articles)
+    }
+
+    /// Sets the channel's icon, which sources find rather than it being configured, returning
+    /// whether it changed.
+    pub fn set_icon(&mut self, icon: Option<Image>) -> bool {
+        if self.channel.icon.as_ref().map(|icon| &icon.url) == icon.as_ref().map(|icon| &icon.url) {
+            return false;
+        }
+        self.channel.icon = icon;
+        self.hash = hash(&self.channel, &self.articles);
+        self.updated = Utc::now();
+        self.rendered = Rendered::new(&self.channel, &self.articles);
+        true
+    }
+
+    pub fn section(&self, section: &str) -> Vec<Article> {
+        self.articles
+            .iter()
+            .filter(|article| {
+                article
+                    .categories
+                    .iter()
+                    .any(|category| slug(category).eq_ignore_ascii_case(section))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Replaces the feed's articles, only re-rendering and bumping `updated` when they changed.
+    pub fn replace(&mut self, mut articles: Vec<Article>) -> bool {
+        sort(&mut articles);
+        let hash = hash(&self.channel, &articles);
+        let changed = hash != self.hash;
+        if changed {
+            self.hash = hash;
+            self.updated = Utc::now();
+            self.rendered = Rendered::new(&self.channel, &articles);
+        }
+        self.articles = articles;
+        changed
+    }
+}
+
+fn slug(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+pub fn combine<'a>(feeds: impl IntoIterator<Item = &'a Feed>) -> Vec<Article> {
+    let sourced = feeds
+        .into_iter()
+        .filter(|feed| feed.name != COMBINED)
+        .flat_map(|feed| feed.articles.iter().map(move |article| (feed.name.as_str(), article)))
+        .collect::<Vec<_>>();
+    merge(&sourced)
+}
+
+/// Articles from several sources as one feed, each headline naming its source.
+fn merge(sourced: &[(&str, &Article)]) -> Vec<Article> {
+    let alternates = match cluster::get() {
+        Some(clustering) => clustering.apply(sourced),
+        None => (0..sourced.len()).map(|index| (index, Vec::new())).collect(),
+    };
+    let mut articles = alternates
+        .into_iter()
+        .map(|(index, alternates)| {
+            let (source, article) = sourced[index];
+            Article {
+                headline: format!("[{}] {}", source, article.headline),
+                alternates,
+                ..article.clone()
+            }
+        })
+        .collect::<Vec<_>>();
+    sort(&mut articles);
+    let mut seen = HashSet::new();
+    articles.retain(|article| seen.insert(article.id().to_owned()));
+    articles
+}
+
+/// A feed made of the articles from other feeds that mention any of its keywords or match any of
+/// its patterns, recomputed whenever those feeds change.
+#[derive(Debug, Clone)]
+pub struct VirtualFeed {
+    pub name: String,
+    pub description: Option<String>,
+    /// The feeds it draws from, or every source when empty.
+    pub sources: Vec<String>,
+    /// Matched case-insensitively anywhere in the headline or text.
+    pub keywords: Vec<String>,
+    pub patterns: Vec<Regex>,
+}
+
+impl VirtualFeed {
+    pub fn includes(&self, source: &str) -> bool {
+        self.sources.is_empty()
+            || self
+                .sources
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(source))
+    }
+
+    pub fn matches(&self, article: &Article) -> bool {
+        let text = format!(
+            "{}\n{}",
+            article.headline,
+            Document::from(article.body.as_str()).select("body").text()
+        );
+        let lowercase = text.to_lowercase();
+        self.keywords
+            .iter()
+            .any(|keyword| lowercase.contains(&keyword.to_lowercase()))
+            || self.patterns.iter().any(|pattern| pattern.is_match(&text))
+    }
+
+    /// The matching articles from the feeds it draws from. The combined feed and other virtual
+    /// feeds should be left out, as their articles are already in their sources.
+    pub fn select<'a>(&self, feeds: impl IntoIterator<Item = &'a Feed>) -> Vec<Article> {
+        let sourced = feeds
+            .into_iter()
+            .filter(|feed| feed.name != COMBINED && self.includes(&feed.name))
+            .flat_map(|feed| feed.articles.iter().map(move |article| (feed.name.as_str(), article)))
+            .filter(|(_, article)| self.matches(article))
+            .collect::<Vec<_>>();
+        merge(&sourced)
+    }
+}
@@ -0,0 +1,59 @@
+<?xml version="1.0" encoding="utf-8"?>
+<xsl:stylesheet version="1.0"
+  xmlns:xsl="http://www.w3.org/1999/XSL/Transform"
+  xmlns:atom="http://www.w3.org/2005/Atom">
+  <xsl:output method="html" encoding="utf-8" doctype-system="about:legacy-compat"/>
+
+  <xsl:template match="/">
+    <xsl:variable name="title" select="rss/channel/title | atom:feed/atom:title"/>
+    <html>
+      <head>
+        <meta charset="utf-8"/>
+        <meta name="viewport" content="width=device-width, initial-scale=1"/>
+        <title><xsl:value-of select="$title"/></title>
+        <style>
+          body { font-family: sans-serif; max-width: 48em; margin: 2em auto; padding: 0 1em; line-height: 1.5; color: #222; }
+          .subscribe { background: #fff6d5; border: 1px solid #e6d37a; padding: 0.5em 1em; }
+          .subscribe code { word-break: break-all; }
+          article { border-bottom: 1px solid #ddd; padding: 0.5em 0 1em; }
+          article h2 { font-size: 1.1em; margin-bottom: 0.2em; }
+          time { color: #666; font-size: 0.9em; }
+        </style>
+      </head>
+      <body>
+        <div class="subscribe">
+          <p>
+            This is a web feed. To subscribe, copy
+            <xsl:choose>
+              <xsl:when test="rss/channel/atom:link[@rel='self'] | atom:feed/atom:link[@rel='self']">
+                <code><xsl:value-of select="(rss/channel/atom:link[@rel='self'] | atom:feed/atom:link[@rel='self'])/@href"/></code>
+              </xsl:when>
+              <xsl:otherwise>the address of this page</xsl:otherwise>
+            </xsl:choose>
+            into your feed reader.
+          </p>
+        </div>
+        <h1><xsl:value-of select="$title"/></h1>
+        <xsl:apply-templates select="rss/channel/item | atom:feed/atom:entry"/>
+      </body>
+    </html>
+  </xsl:template>
+
+  <xsl:template match="item">
+    <article>
+      <h2><a href="{link}"><xsl:value-of select="title"/></a></h2>
+      <time><xsl:value-of select="pubDate"/></time>
+      <p><xsl:value-of select="description"/></p>
+    </article>
+  </xsl:template>
+
+  <xsl:template match="atom:entry">
+    <article>
+      <h2>
+        <a href="{atom:link[not(@rel) or @rel='alternate']/@href}"><xsl:value-of select="atom:title"/></a>
+      </h2>
+      <time><xsl:value-of select="atom:published | atom:updated[not(../atom:published)]"/></time>
+      <p><xsl:value-of select="atom:summary"/></p>
+    </article>
+  </xsl:template>
+</xsl:stylesheet>
@@ -0,0 +1,24 @@
+use crate::{encoding, Pages, Scraped, Scraper};
+use anyhow::*;
+use reqwest::Url;
+use std::{fs, path::Path};
+use tracing::instrument;
+
+pub(crate) fn read(dir: &Path, url: &Url) -> Result<String> {
+    let mut path = dir.join(url.path().trim_start_matches('/'));
+    if url.path().ends_with('/') {
+        path.push("index.html");
+    }
+    let bytes =
+        fs::read(&path).with_context(|| format!("Missing fixture {:?} for {}", path, url))?;
+    Ok(encoding::decode(&bytes, None))
+}
+
+impl Scraper {
+    #[instrument(skip(self, dir), fields(self.name, dir = ?dir.as_ref()))]
+    pub async fn scrape_fixtures(&self, dir: impl AsRef<Path>) -> Result<Scraped> {
+        self.scrape(Pages::Fixtures(dir.as_ref()), None)
+            .await?
+            .context("Expected fixtures to produce a listing page")
+    }
+}
@@ -0,0 +1,265 @@
+use anyhow::*;
+use fnv::FnvHasher;
+use image::{
+    codecs::{jpeg::JpegEncoder, webp::WebPEncoder},
+    DynamicImage,
+};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    hash::Hasher,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::{Duration, SystemTime},
+};
+use tracing::{instrument, trace, warn};
+
+/// Images not seen in a scrape for this long are removed when the cache opens.
+const UNUSED: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+/// Larger images are linked from their origin rather than copied.
+pub(crate) const MAX_SIZE: usize = 10 * 1024 * 1024;
+const JPEG_QUALITY: u8 = 80;
+
+/// Copies of article images, served from `/img/{key}` in place of the originals. Each image is
+/// stored as `{key}`, next to a `{key}.json` naming where it came from, and any thumbnail of it
+/// as `{key}.thumbnail`.
+#[derive(Debug)]
+pub struct ImageCache {
+    dir: PathBuf,
+    thumbnails: Option<Thumbnails>,
+}
+
+/// WebP thumbnails are lossless, so JPEG is usually smaller for photographs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbnailFormat {
+    #[default]
+    Jpeg,
+    Webp,
+}
+
+/// Thumbnails are scaled down to `width`, keeping their aspect ratio; narrower images are only
+/// re-encoded.
+#[derive(Debug, Clone, Copy)]
+pub struct Thumbnails {
+    pub width: u32,
+    pub format: ThumbnailFormat,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Thumbnail {
+    pub mime_type: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Meta {
+    url: String,
+    mime_type: String,
+    #[serde(default)]
+    thumbnail: Option<Thumbnail>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Cached {
+    pub mime_type: String,
+    pub body: Vec<u8>,
+}
+
+static IMAGES: OnceLock<ImageCache> = OnceLock::new();
+
+pub fn configure(images: ImageCache) {
+    IMAGES.set(images).ok();
+}
+
+pub fn get() -> Option<&'static ImageCache> {
+    IMAGES.get()
+}
+
+/// The key an image is served under.
+pub fn key(url: &Url) -> String {
+    let mut hasher = FnvHasher::default();
+    hasher.write(url.as_str().as_bytes());
+    format!("{:016x}", hasher.finish())
+}
+
+fn valid(key: &str) -> bool {
+    key.len() == 16 && key.bytes().all(|byte| byte.is_ascii_hexdigit())
+}
+
+impl ThumbnailFormat {
+    fn mime_type(self) -> &'static str {
+        match self {
+            ThumbnailFormat::Jpeg => "image/jpeg",
+            ThumbnailFormat::Webp => "image/webp",
+        }
+    }
+}
+
+impl Thumbnails {
+    fn make(&self, body: &[u8]) -> Result<(Thumbnail, Vec<u8>)> {
+        let image = image::load_from_memory(body)?;
+        let image = if image.width() > self.width {
+            image.thumbnail(self.width, u32::MAX)
+        } else {
+            image
+        };
+        let mut encoded = Vec::new();
+        match self.format {
+            ThumbnailFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8())
+                .write_with_encoder(JpegEncoder::new_with_quality(&mut encoded, JPEG_QUALITY))?,
+            ThumbnailFormat::Webp => DynamicImage::ImageRgba8(image.to_rgba8())
+                .write_with_encoder(WebPEncoder::new_lossless(&mut encoded))?,
+        }
+        let thumbnail = Thumbnail {
+            mime_type: self.format.mime_type().to_owned(),
+            width: image.width(),
+            height: image.height(),
+        };
+        Ok((thumbnail, encoded))
+    }
+}
+
+impl ImageCache {
+    #[instrument(skip(dir), fields(dir = ?dir.as_ref()))]
+    pub fn open(dir: impl AsRef<Path>) -> Result<ImageCache> {
+        let dir = dir.as_ref().to_owned();
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create image cache {:?}", dir))?;
+        let images = ImageCache {
+            dir,
+            thumbnails: None,
+        };
+        let now = SystemTime::now();
+        for entry in fs::read_dir(&images.dir)? {
+            let path = entry?.path();
+            let key = match path.file_name().and_then(|name| name.to_str()) {
+                Some(name) if valid(name) => name.to_owned(),
+                _ => continue,
+            };
+            // Only the metadata is rewritten when an image is seen again, so its age counts.
+            let unused = fs::metadata(images.meta(&key))
+                .and_then(|metadata| metadata.modified())
+                .map(|modified| now.duration_since(modified).is_ok_and(|age| age > UNUSED))
+                .unwrap_or(true);
+            if unused {
+                trace!(?path, "Removing unused image");
+                fs::remove_file(&path).ok();
+                fs::remove_file(images.meta(&key)).ok();
+                fs::remove_file(images.thumbnail_path(&key)).ok();
+            }
+        }
+        Ok(images)
+    }
+
+    pub fn with_thumbnails(self, thumbnails: Thumbnails) -> ImageCache {
+        ImageCache {
+            thumbnails: Some(thumbnails),
+            ..self
+        }
+    }
+
+    fn meta(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    fn thumbnail_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.thumbnail", key))
+    }
+
+    fn read_meta(&self, url: &Url) -> Option<Meta> {
+        let meta = fs::read(self.meta(&key(url))).ok()?;
+        serde_json::from_slice::<Meta>(&meta)
+            .ok()
+            // Different URLs can share a key, and only one is kept at a time.
+            .filter(|meta| meta.url == url.as_str())
+    }
+
+    /// Whether a copy of the image can be served.
+    pub fn contains(&self, url: &Url) -> bool {
+        self.read_meta(url).is_some()
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        valid(key) && self.dir.join(key).is_file()
+    }
+
+    /// The thumbnail made of the image, if any.
+    pub fn thumbnail(&self, url: &Url) -> Option<Thumbnail> {
+        self.read_meta(url)?.thumbnail
+    }
+
+    pub fn load(&self, key: &str) -> Option<Cached> {
+        if !valid(key) {
+            return None;
+        }
+        let meta = serde_json::from_slice::<Meta>(&fs::read(self.meta(key)).ok()?).ok()?;
+        Some(Cached {
+            mime_type: meta.mime_type,
+            body: fs::read(self.dir.join(key)).ok()?,
+        })
+    }
+
+    pub fn load_thumbnail(&self, key: &str) -> Option<Cached> {
+        if !valid(key) {
+            return None;
+        }
+        let meta = serde_json::from_slice::<Meta>(&fs::read(self.meta(key)).ok()?).ok()?;
+        Some(Cached {
+            mime_type: meta.thumbnail?.mime_type,
+            body: fs::read(self.thumbnail_path(key)).ok()?,
+        })
+    }
+
+    /// The type and size of a stored image, marking it as still in use.
+    pub(crate) fn seen(&self, url: &Url) -> Option<(String, u64)> {
+        let meta = self.read_meta(url)?;
+        let length = fs::metadata(self.dir.join(key(url))).ok()?.len();
+        self.write_meta(url, &meta);
+        Some((meta.mime_type, length))
+    }
+
+    /// Stores an image, and a thumbnail of it when they are configured. Decoding the image makes
+    /// this slow, so it is best kept off async tasks.
+    pub fn put(&self, url: &Url, mime_type: &str, body: &[u8]) {
+        let key = key(url);
+        if let Err(error) = fs::write(self.dir.join(&key), body) {
+            warn!(?error, %url, "Failed to cache image");
+            return;
+        }
+        let thumbnail = self
+            .thumbnails
+            .filter(|_| mime_type != "image/svg+xml")
+            .and_then(|thumbnails| match thumbnails.make(body) {
+                Result::Ok(thumbnail) => Some(thumbnail),
+                Err(error) => {
+                    trace!(?error, %url, "Not making a thumbnail");
+                    None
+                }
+            })
+            .and_then(|(thumbnail, encoded)| match fs::write(self.thumbnail_path(&key), encoded) {
+                Result::Ok(()) => Some(thumbnail),
+                Err(error) => {
+                    warn!(?error, %url, "Failed to cache thumbnail");
+                    None
+                }
+            });
+        self.write_meta(url, &Meta {
+            url: url.as_str().to_owned(),
+            mime_type: mime_type.to_owned(),
+            thumbnail,
+        });
+    }
+
+    fn write_meta(&self, url: &Url, meta: &Meta) {
+        let path = self.meta(&key(url));
+        let written = serde_json::to_vec(meta)
+            .map_err(Error::from)
+            .and_then(|meta| Ok(fs::write(&path, meta)?));
+        if let Err(error) = written {
+            warn!(?error, ?path, "Failed to cache image");
+        }
+    }
+}
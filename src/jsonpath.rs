@@ -0,0 +1,159 @@
+use anyhow::*;
+use serde_json::Value;
+use std::{convert::TryFrom, fmt, str::FromStr};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Key(String),
+    Index(i64),
+    Wildcard,
+    Descendant(String),
+}
+
+/// A subset of JSONPath: `$`, `.key`, `['key']`, `[0]`, `[-1]`, `[*]`, `.*` and `..key`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath {
+    source: String,
+    steps: Vec<Step>,
+}
+
+fn key(path: &str, rest: &str) -> Result<(String, usize)> {
+    let end = rest.find(['.', '[']).unwrap_or(rest.len());
+    ensure!(end > 0, "Empty key in JSON path {:?}", path);
+    Ok((rest[..end].to_owned(), end))
+}
+
+impl FromStr for JsonPath {
+    type Err = Error;
+
+    fn from_str(path: &str) -> Result<JsonPath> {
+        let trimmed = path.trim();
+        let normalized = match trimmed.strip_prefix('$') {
+            Some(rest) => rest.to_owned(),
+            None if trimmed.starts_with(['.', '[']) => trimmed.to_owned(),
+            None => format!(".{}", trimmed),
+        };
+        let mut rest = normalized.as_str();
+        let mut steps = Vec::new();
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix("..") {
+                let (key, len) = key(path, after)?;
+                steps.push(Step::Descendant(key));
+                rest = &after[len..];
+            } else if let Some(after) = rest.strip_prefix(".*") {
+                steps.push(Step::Wildcard);
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix('.') {
+                let (key, len) = key(path, after)?;
+                steps.push(Step::Key(key));
+                rest = &after[len..];
+            } else if let Some(after) = rest.strip_prefix('[') {
+                let end = after
+                    .find(']')
+                    .with_context(|| format!("Unclosed bracket in JSON path {:?}", path))?;
+                let inner = after[..end].trim();
+                steps.push(match inner {
+                    "*" => Step::Wildcard,
+                    _ if inner.len() >= 2
+                        && (inner.starts_with('\'') && inner.ends_with('\'')
+                            || inner.starts_with('"') && inner.ends_with('"')) =>
+                    {
+                        Step::Key(inner[1..inner.len() - 1].to_owned())
+                    }
+                    _ => Step::Index(inner.parse().with_context(|| {
+                        format!("Invalid index {:?} in JSON path {:?}", inner, path)
+                    })?),
+                });
+                rest = &after[end + 1..];
+            } else {
+                bail!("Invalid JSON path {:?}", path);
+            }
+        }
+        Ok(JsonPath {
+            source: path.to_owned(),
+            steps,
+        })
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+fn descendants<'a>(value: &'a Value, key: &str, found: &mut Vec<&'a Value>) {
+    match value {
+        Value::Object(object) => {
+            for (name, child) in object {
+                if name == key {
+                    found.push(child);
+                }
+                descendants(child, key, found);
+            }
+        }
+        Value::Array(array) => {
+            for child in array {
+                descendants(child, key, found);
+            }
+        }
+        _ => {}
+    }
+}
+
+impl JsonPath {
+    pub fn select<'a>(&self, value: &'a Value) -> Vec<&'a Value> {
+        let mut current = vec![value];
+        for step in &self.steps {
+            let mut next = Vec::new();
+            for value in current {
+                match (step, value) {
+                    (Step::Key(key), Value::Object(object)) => next.extend(object.get(key)),
+                    (Step::Index(index), Value::Array(array)) => {
+                        let index = if *index < 0 {
+                            array.len() as i64 + index
+                        } else {
+                            *index
+                        };
+                        next.extend(usize::try_from(index).ok().and_then(|i| array.get(i)))
+                    }
+                    (Step::Wildcard, Value::Array(array)) => next.extend(array),
+                    (Step::Wildcard, Value::Object(object)) => next.extend(object.values()),
+                    (Step::Descendant(key), value) => descendants(value, key, &mut next),
+                    _ => {}
+                }
+            }
+            current = next;
+        }
+        current
+    }
+
+    pub fn first<'a>(&self, value: &'a Value) -> Option<&'a Value> {
+        self.select(value)
+            .into_iter()
+            .find(|value| !value.is_null())
+    }
+
+    pub fn string(&self, value: &Value) -> Option<String> {
+        self.first(value).and_then(scalar)
+    }
+
+    pub fn strings(&self, value: &Value) -> Vec<String> {
+        self.select(value)
+            .into_iter()
+            .flat_map(|value| match value {
+                Value::Array(values) => values.iter().filter_map(scalar).collect::<Vec<_>>(),
+                value => scalar(value).into_iter().collect(),
+            })
+            .collect()
+    }
+}
+
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(string) => Some(string.trim().to_owned()).filter(|s| !s.is_empty()),
+        Value::Number(number) => Some(number.to_string()),
+        Value::Bool(bool) => Some(bool.to_string()),
+        _ => None,
+    }
+}
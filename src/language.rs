@@ -0,0 +1,105 @@
+use nipper::Document;
+use whatlang::Lang;
+
+/// The language an article's text is in, as a BCP 47 primary subtag. Only confident detections
+/// count, since languages whatlang doesn't know, like Irish, come out as weak guesses at their
+/// neighbours.
+pub(crate) fn detect(headline: &str, body: &str) -> Option<String> {
+    let text = Document::from(body).select("body").text();
+    whatlang::detect(&format!("{} {}", headline, text))
+        .filter(|info| info.is_reliable())
+        .map(|info| code(info.lang()).to_owned())
+}
+
+/// The language a page declares, from its `lang` attribute or `og:locale`.
+pub(crate) fn declared(document: &Document) -> Option<String> {
+    let lang = document.select("html[lang]").attr("lang").or_else(|| {
+        document
+            .select(r#"meta[property="og:locale"]"#)
+            .attr("content")
+    })?;
+    tag(&lang)
+}
+
+/// The primary subtag of a language tag or locale, such as `ga` from `ga-IE` or `en_GB`.
+fn tag(lang: &str) -> Option<String> {
+    let primary = lang.trim().split(['-', '_']).next()?;
+    let valid = (2..=3).contains(&primary.len()) && primary.bytes().all(|byte| byte.is_ascii_alphabetic());
+    valid.then(|| primary.to_ascii_lowercase())
+}
+
+/// ISO 639-1 codes, which BCP 47 prefers to the ISO 639-3 codes whatlang gives.
+fn code(lang: Lang) -> &'static str {
+    match lang {
+        Lang::Epo => "eo",
+        Lang::Eng => "en",
+        Lang::Rus => "ru",
+        Lang::Cmn => "zh",
+        Lang::Spa => "es",
+        Lang::Por => "pt",
+        Lang::Ita => "it",
+        Lang::Ben => "bn",
+        Lang::Fra => "fr",
+        Lang::Deu => "de",
+        Lang::Ukr => "uk",
+        Lang::Kat => "ka",
+        Lang::Ara => "ar",
+        Lang::Hin => "hi",
+        Lang::Jpn => "ja",
+        Lang::Heb => "he",
+        Lang::Yid => "yi",
+        Lang::Pol => "pl",
+        Lang::Amh => "am",
+        Lang::Jav => "jv",
+        Lang::Kor => "ko",
+        Lang::Nob => "nb",
+        Lang::Dan => "da",
+        Lang::Swe => "sv",
+        Lang::Fin => "fi",
+        Lang::Tur => "tr",
+        Lang::Nld => "nl",
+        Lang::Hun => "hu",
+        Lang::Ces => "cs",
+        Lang::Ell => "el",
+        Lang::Bul => "bg",
+        Lang::Bel => "be",
+        Lang::Mar => "mr",
+        Lang::Kan => "kn",
+        Lang::Ron => "ro",
+        Lang::Slv => "sl",
+        Lang::Hrv => "hr",
+        Lang::Srp => "sr",
+        Lang::Mkd => "mk",
+        Lang::Lit => "lt",
+        Lang::Lav => "lv",
+        Lang::Est => "et",
+        Lang::Tam => "ta",
+        Lang::Vie => "vi",
+        Lang::Urd => "ur",
+        Lang::Tha => "th",
+        Lang::Guj => "gu",
+        Lang::Uzb => "uz",
+        Lang::Pan => "pa",
+        Lang::Aze => "az",
+        Lang::Ind => "id",
+        Lang::Tel => "te",
+        Lang::Pes => "fa",
+        Lang::Mal => "ml",
+        Lang::Ori => "or",
+        Lang::Mya => "my",
+        Lang::Nep => "ne",
+        Lang::Sin => "si",
+        Lang::Khm => "km",
+        Lang::Tuk => "tk",
+        Lang::Aka => "ak",
+        Lang::Zul => "zu",
+        Lang::Sna => "sn",
+        Lang::Afr => "af",
+        Lang::Lat => "la",
+        Lang::Slk => "sk",
+        Lang::Cat => "ca",
+        Lang::Tgl => "tl",
+        Lang::Hye => "hy",
+        Lang::Cym => "cy",
+    }
+}
@@ -1,134 +1,1260 @@
 use anyhow::*;
-use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone, Utc};
 use chrono_tz::{Europe, Tz};
+use fnv::FnvHasher;
+use futures::{stream, StreamExt};
 use nipper::{Document, Selection};
-use reqwest::{Client, Url};
-use std::fmt::Debug;
-use tracing::{Level, Span, instrument, span, trace};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Client, Url,
+};
+use serde::Deserialize;
+use std::{
+    borrow::Cow,
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    hash::Hasher,
+    path::Path,
+    sync::{LazyLock, Mutex},
+    time::Duration,
+};
+use images::ImageCache;
+use script::Script;
+use store::Store;
+use rewrite::Rewrite;
+use translate::Translation;
+use tracing::{Level, Span, instrument, span, trace, warn};
 
-#[derive(Debug)]
+pub mod activitypub;
+pub mod alert;
+pub mod auth;
+mod api;
+#[cfg(feature = "browser")]
+mod browser;
+mod builder;
+pub mod cache;
+mod canonical;
+pub mod client;
+pub mod cluster;
+mod config;
+pub mod debug;
+pub mod digest;
+mod encoding;
+pub mod export;
+mod fixtures;
+pub mod feed;
+pub mod images;
+mod jsonpath;
+mod language;
+pub mod metrics;
+mod passthrough;
+mod plugin;
+pub mod politeness;
+mod readability;
+pub mod push;
+pub mod render;
+mod retry;
+pub mod rewrite;
+pub mod s3;
+pub mod schedule;
+pub mod script;
+mod search;
+mod selectors;
+pub mod smtp;
+mod source;
+pub mod status;
+pub mod store;
+pub mod telegram;
+pub mod throttle;
+pub mod tls;
+pub mod translate;
+pub mod websub;
+
+pub use api::JsonApi;
+pub use builder::ScraperBuilder;
+pub use client::ClientConfig;
+pub use feed::{Feed, Retention};
+pub use jsonpath::JsonPath;
+pub use passthrough::Passthrough;
+pub use plugin::Plugin;
+pub use retry::{RateLimited, RetryPolicy};
+pub use schedule::{Adaptive, Stagger};
+pub use search::Query;
+pub use selectors::Selectors;
+pub use source::Source;
+
+const DEFAULT_CONCURRENCY: usize = 4;
+const DEFAULT_MAX_PAGES: usize = 5;
+
+// Icons found for each listing page, including the lack of one, so each is only fetched once.
+static ICONS: LazyLock<Mutex<HashMap<Url, Option<Image>>>> = LazyLock::new(Default::default);
+
+#[derive(Debug, Clone, Hash)]
+pub struct Image {
+    pub url: Url,
+    pub mime_type: Option<String>,
+    pub length: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
 pub struct Article {
     pub headline: String,
     pub link: Url,
     pub body: String,
-    pub image: Option<Url>,
+    /// Plain text for previews. Without one, the start of the body is used.
+    pub description: Option<String>,
+    /// Detected from the text, or else as the page declares it.
+    pub language: Option<String>,
+    pub image: Option<Image>,
+    pub images: Vec<Image>,
+    pub author: Option<String>,
+    pub categories: Vec<String>,
     pub date: DateTime<Tz>,
+    pub updated: Option<DateTime<Tz>>,
+    pub guid: Option<String>,
+    /// Other sources' articles on the same story, found when combining feeds.
+    pub alternates: Vec<Alternate>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Alternate {
+    pub source: String,
+    pub headline: String,
+    pub link: Url,
+}
+
+impl Article {
+    pub fn id(&self) -> &str {
+        self.guid.as_deref().unwrap_or_else(|| self.link.as_str())
+    }
+
+    pub fn summary(&self) -> Cow<'_, str> {
+        match &self.description {
+            Some(description) => Cow::Borrowed(description),
+            None => Cow::Owned(render::summary(&self.body)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Scraped {
+    pub articles: Vec<Article>,
+    pub failed: usize,
+    /// The site's icon, for sources that look for one.
+    pub icon: Option<Image>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateFallback {
+    #[default]
+    Fail,
+    Now,
+    Skip,
+}
+
+/// How dates are read. The timezone belongs to the source rather than the parser, so every
+/// parser shares the same handling of it.
+#[derive(Debug)]
+pub enum DateParser {
+    Custom(fn(&str) -> Result<DateTime<FixedOffset>>),
+    /// `strftime` formats, tried in order before falling back to RFC 3339 and RFC 2822.
+    Format {
+        formats: Cow<'static, [Cow<'static, str>]>,
+        fallback: DateFallback,
+    },
+}
+
+impl DateParser {
+    /// A date in `timezone`. Formats without an offset (`%z`) are taken as local times there.
+    fn parse(&self, date: String, timezone: Tz) -> Result<Option<DateTime<Tz>>> {
+        Ok(self.parse_or_now(date, timezone)?.map(|(date, _)| date))
+    }
+
+    /// Like `parse`, but also saying whether the date is the current time standing in for one
+    /// that couldn't be read.
+    fn parse_or_now(&self, date: String, timezone: Tz) -> Result<Option<(DateTime<Tz>, bool)>> {
+        let trimmed = date.trim();
+        let (formats, fallback) = match self {
+            DateParser::Custom(parse) => {
+                return parse(trimmed).map(|date| Some((date.with_timezone(&timezone), false)))
+            }
+            DateParser::Format { formats, fallback } => (formats, fallback),
+        };
+        let parsed = formats
+            .iter()
+            .find_map(|format| {
+                DateTime::parse_from_str(trimmed, format)
+                    .map(|date| date.with_timezone(&timezone))
+                    .ok()
+                    .or_else(|| {
+                        NaiveDateTime::parse_from_str(trimmed, format)
+                            .ok()
+                            .and_then(|date| timezone.from_local_datetime(&date).earliest())
+                    })
+            })
+            .or_else(|| {
+                DateTime::parse_from_rfc3339(trimmed)
+                    .or_else(|_| DateTime::parse_from_rfc2822(trimmed))
+                    .map(|date| date.with_timezone(&timezone))
+                    .ok()
+            });
+        match (parsed, fallback) {
+            (Some(date), _) => Ok(Some((date, false))),
+            (None, DateFallback::Now) => {
+                trace!(date = date.as_str(), "Falling back to the current time");
+                Ok(Some((Utc::now().with_timezone(&timezone), true)))
+            }
+            (None, DateFallback::Skip) => Ok(None),
+            (None, DateFallback::Fail) => {
+                bail!("Failed to parse date {:?} as any of {:?}", date, formats)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Renderer {
+    #[default]
+    Http,
+    Browser,
+}
+
+/// How article bodies are found on their pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Extractor {
+    /// Whatever the body selector matches.
+    #[default]
+    Selector,
+    /// The element that content-density heuristics pick, when there is no body selector or it
+    /// matches nothing.
+    Readability,
+}
+
+#[derive(Debug, Clone, Default)]
+pub enum GuidStrategy {
+    #[default]
+    Link,
+    Hash,
+    Selector {
+        selector: Selectors,
+        attribute: Option<Cow<'static, str>>,
+    },
+}
+
+#[derive(Debug)]
+pub enum Pagination {
+    Next { selector: Selectors, max_pages: usize },
+    Template { template: Cow<'static, str>, max_pages: usize },
 }
 
 #[derive(Debug)]
 pub struct Scraper {
-    pub name: &'static str,
-    base_url: &'static str,
-    news_url: &'static str,
-    article_selector: &'static str,
-    headline_selector: &'static str,
-    image_selector: Option<&'static str>,
-    date_selector: &'static str,
-    parse_date: fn(String) -> Result<DateTime<Tz>>,
-    link_selector: &'static str,
-    body_selector: &'static str,
+    pub name: Cow<'static, str>,
+    description: Option<Cow<'static, str>>,
+    language: Option<Cow<'static, str>>,
+    base_url: Cow<'static, str>,
+    news_url: Cow<'static, str>,
+    article_selector: Cow<'static, str>,
+    headline_selector: Selectors,
+    image_selector: Option<Selectors>,
+    collect_images: bool,
+    author_selector: Option<Selectors>,
+    categories_selector: Option<Selectors>,
+    date_selector: Selectors,
+    date_attribute: Option<Cow<'static, str>>,
+    parse_date: DateParser,
+    /// Where the site's dates are local to, and what every article's date is given in.
+    timezone: Tz,
+    link_selector: Selectors,
+    body_selector: Option<Selectors>,
+    extractor: Extractor,
+    /// Matches in-article junk, such as related links or ads, to remove from bodies.
+    remove_selectors: Cow<'static, [Cow<'static, str>]>,
+    /// Cleans up extracted fields, such as a prefix every headline repeats.
+    rewrites: Cow<'static, [Rewrite]>,
+    script: Option<Script>,
+    article_next_page_selector: Option<Selectors>,
+    prefer_amp: bool,
+    #[cfg_attr(not(feature = "browser"), allow(dead_code))]
+    renderer: Renderer,
+    interval: Duration,
+    concurrency: usize,
+    retry: RetryPolicy,
+    retention: Retention,
+    stagger: Stagger,
+    adaptive: Option<Adaptive>,
+    headers: Cow<'static, [(Cow<'static, str>, Cow<'static, str>)]>,
+    pagination: Option<Pagination>,
+    guid: GuidStrategy,
+    track_updates: bool,
+    /// Builds articles from the listing page alone, never fetching article pages.
+    summary_only: bool,
+    /// Describes articles by the start of their body rather than the page's meta description.
+    summary_sentences: Option<usize>,
+    /// Tags articles with the language their text is in, rather than only what pages declare.
+    detect_language: bool,
+    translation: Option<Translation>,
+    bump_pub_date: bool,
+    max_items: Option<usize>,
 }
 
 impl Scraper {
     #[instrument(skip(self), fields(self.base_url))]
     fn url(&self, path: &str) -> Result<Url> {
-        Url::parse(self.base_url)
+        Url::parse(&self.base_url)
             .expect("Expected base URL to be valid")
             .join(path)
             .map_err(Into::into)
     }
 
-    #[instrument(skip(self, client), fields(self.name))]
-    pub async fn get_articles(&self, client: &Client) -> Result<Vec<Article>> {
-        let news = client
-            .get(self.url(self.news_url)?)
-            .send()
-            .await?
-            .error_for_status()?
-            .text()
-            .await?;
-        let news = Document::from(&news);
-        let articles = news.select(self.article_selector);
-        let articles = futures::future::try_join_all(
-            articles
+    fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in self.headers.iter() {
+            headers.append(
+                HeaderName::from_bytes(name.as_bytes()).expect("Expected header name to be valid"),
+                HeaderValue::from_str(value).expect("Expected header value to be valid"),
+            );
+        }
+        headers
+    }
+
+    #[instrument(skip(self, client, store), fields(self.name))]
+    pub async fn get_articles(&self, client: &Client, store: Option<&Store>) -> Result<Option<Scraped>> {
+        self.scrape(Pages::Http(client), store).await
+    }
+
+    async fn scrape(&self, pages: Pages<'_>, store: Option<&Store>) -> Result<Option<Scraped>> {
+        let _timer = metrics::SCRAPE_DURATION
+            .with_label_values(&[&self.name])
+            .start_timer();
+        let news_url = self.url(&self.news_url)?;
+        let (news, validators) = match pages.fetch_if_modified(self, news_url.clone()).await? {
+            Some(news) => news,
+            None => {
+                metrics::scraped(&self.name);
+                return Ok(None);
+            }
+        };
+        let mut documents = vec![Document::from(&news)];
+        let icon = self.icon(pages, &documents[0], &news_url).await;
+        self.paginate(pages, news_url, &mut documents).await?;
+        let mut articles = stream::iter(
+            documents
                 .iter()
-                .map(|article| self.get_article(client, article)),
-        ).await?;
-        Ok(articles)
+                .flat_map(|document| document.select(&self.article_selector).iter())
+                .map(|article| self.get_article(pages, store, article)),
+        )
+        .buffered(self.concurrency);
+        let mut results = Vec::new();
+        while let Some(result) = articles.next().await {
+            // The rest would only be refused too, so the whole scrape waits for another time. The
+            // listing's validators aren't saved, so it's fetched whole again then.
+            match result {
+                Err(error) if RateLimited::find(&error).is_some() => return Err(error),
+                result => results.push(result),
+            }
+        }
+        let mut scraped = Scraped {
+            articles: Vec::with_capacity(results.len()),
+            failed: 0,
+            icon,
+        };
+        for result in results {
+            match result {
+                Ok(Some(article)) => {
+                    if !scraped.articles.iter().any(|scraped| scraped.id() == article.id()) {
+                        scraped.articles.push(article);
+                    }
+                }
+                Ok(None) => trace!("Skipped article without a date"),
+                Err(error) => {
+                    warn!(?error, "Failed to scrape article");
+                    scraped.failed += 1;
+                }
+            }
+        }
+        metrics::ARTICLE_FAILURES
+            .with_label_values(&[&self.name])
+            .inc_by(scraped.failed as u64);
+        metrics::scraped(&self.name);
+        if scraped.failed > 0 {
+            warn!(
+                failed = scraped.failed,
+                succeeded = scraped.articles.len(),
+                "Some articles failed to scrape"
+            );
+        } else if let Some(validators) = validators {
+            // Failed articles are only retried if the listing is fetched again.
+            validators.save().await;
+        }
+        Ok(Some(scraped))
     }
 
-    #[instrument(skip(self, client, article), fields(self.name, article))]
-    async fn get_article<'a>(&self, client: &Client, article: Selection<'a>) -> Result<Article> {
-        let headline = article
-            .select(self.headline_selector)
+    async fn icon(&self, pages: Pages<'_>, document: &Document, url: &Url) -> Option<Image> {
+        // Fixtures for different sites share URLs, so their icons aren't remembered.
+        let remember = matches!(pages, Pages::Http(_));
+        if let Some(icon) = ICONS.lock().unwrap().get(url).filter(|_| remember) {
+            return icon.clone();
+        }
+        let icon = find_icon(pages, document, url).await;
+        if remember {
+            ICONS.lock().unwrap().insert(url.clone(), icon.clone());
+        }
+        icon
+    }
+
+    async fn paginate(
+        &self,
+        pages: Pages<'_>,
+        mut url: Url,
+        documents: &mut Vec<Document>,
+    ) -> Result<()> {
+        match &self.pagination {
+            None => {}
+            Some(Pagination::Next { selector, max_pages }) => {
+                let mut visited = HashSet::from([url.clone()]);
+                while documents.len() < *max_pages {
+                    let next = selector
+                        .select(|selector| documents.last().unwrap().select(selector))
+                        .attr("href")
+                        .and_then(|href| url.join(&href).ok());
+                    url = match next {
+                        Some(next) if visited.insert(next.clone()) => next,
+                        _ => break,
+                    };
+                    match pages.fetch(self, url.clone()).await {
+                        Ok(page) => documents.push(Document::from(&page)),
+                        Err(error) => {
+                            warn!(?error, "Failed to fetch next page");
+                            break;
+                        }
+                    }
+                }
+            }
+            Some(Pagination::Template { template, max_pages }) => {
+                for page in 2..=*max_pages {
+                    let url = self.url(&template.replace("{page}", &page.to_string()))?;
+                    let page = match pages.fetch(self, url).await {
+                        Ok(page) => Document::from(&page),
+                        Err(error) => {
+                            trace!(?error, page, "Stopping pagination");
+                            break;
+                        }
+                    };
+                    if !page.select(&self.article_selector).exists() {
+                        break;
+                    }
+                    documents.push(page);
+                }
+            }
+        }
+        trace!(pages = documents.len(), "Fetched listing pages");
+        Ok(())
+    }
+
+    fn body<'a>(&self, page: &'a Document) -> Selection<'a> {
+        let body = self
+            .body_selector
+            .as_ref()
+            .map(|body| body.select(|selector| page.select(selector)));
+        let body = match body {
+            Some(body) if body.exists() || self.extractor == Extractor::Selector => body,
+            _ => readability::extract(page),
+        };
+        self.strip(&body);
+        body
+    }
+
+    fn strip(&self, body: &Selection) {
+        for selector in self.remove_selectors.iter() {
+            let mut junk = body.select(selector);
+            junk.remove();
+        }
+    }
+
+    /// `date` is `None` when the page had no date and the scrape time stood in for it, which
+    /// would give the article a new GUID every scrape.
+    fn guid(&self, page: &Selection, headline: &str, date: Option<&DateTime<Tz>>) -> Option<String> {
+        let id = match &self.guid {
+            GuidStrategy::Link => return None,
+            GuidStrategy::Hash => {
+                let mut hasher = FnvHasher::default();
+                hasher.write(headline.as_bytes());
+                if let Some(date) = date {
+                    hasher.write_u8(0);
+                    hasher.write_i64(date.timestamp());
+                }
+                format!("{:016x}", hasher.finish())
+            }
+            GuidStrategy::Selector { selector, attribute } => {
+                let id = selector.select(|selector| page.select(selector));
+                let id = match attribute {
+                    Some(attribute) => id.attr(attribute).map(|id| id.to_string()),
+                    None => Some(id.text().to_string()),
+                };
+                match id.map(|id| id.trim().to_owned()).filter(|id| !id.is_empty()) {
+                    Some(id) => id,
+                    None => {
+                        trace!("GUID selector matched nothing, using the link");
+                        return None;
+                    }
+                }
+            }
+        };
+        Some(guid(&self.name, &id))
+    }
+
+    async fn amp_page(&self, pages: Pages<'_>, link: &Url, document: Document) -> (Document, Url) {
+        let amp = document
+            .select(r#"link[rel="amphtml"]"#)
+            .attr("href")
+            .and_then(|href| link.join(&href).ok());
+        let amp = match amp {
+            Some(amp) if amp != *link => amp,
+            _ => return (document, link.clone()),
+        };
+        match pages.fetch(self, amp.clone()).await {
+            Ok(page) => {
+                trace!(amp = amp.as_str(), "Using AMP page");
+                (Document::from(&page), amp)
+            }
+            Err(error) => {
+                warn!(?error, "Failed to fetch AMP page, using the canonical page");
+                (document, link.clone())
+            }
+        }
+    }
+
+    async fn follow_body_pages(
+        &self,
+        pages: Pages<'_>,
+        link: &Url,
+        document: &Document,
+        body: &mut String,
+    ) {
+        let next_selector = match &self.article_next_page_selector {
+            Some(next_selector) => next_selector,
+            None => return,
+        };
+        let next = |document: &Document, url: &Url| {
+            next_selector
+                .select(|selector| document.select(selector))
+                .attr("href")
+                .and_then(|href| url.join(&href).ok())
+        };
+        let mut visited = HashSet::from([link.clone()]);
+        let mut url = next(document, link);
+        while let Some(page_url) = url.take().filter(|url| visited.insert(url.clone())) {
+            if visited.len() > DEFAULT_MAX_PAGES {
+                trace!("Reached the article page limit");
+                break;
+            }
+            let page = match pages.fetch(self, page_url.clone()).await {
+                Ok(page) => Document::from(&page),
+                Err(error) => {
+                    warn!(?error, "Failed to fetch article page");
+                    break;
+                }
+            };
+            let part = self.body(&page);
+            if part.exists() {
+                absolutize_urls(&part, &page_url);
+                body.push_str(&part.html());
+            }
+            url = next(&page, &page_url);
+        }
+    }
+
+    #[instrument(skip(self, pages, store, article), fields(self.name, article))]
+    async fn get_article<'a>(
+        &self,
+        pages: Pages<'_>,
+        store: Option<&Store>,
+        article: Selection<'a>,
+    ) -> Result<Option<Article>> {
+        let headline = self
+            .headline_selector
+            .select(|selector| article.select(selector))
             .text()
             .to_string()
             .trim()
             .to_owned();
-        let link = self.url(
-            &article
-                .select(self.link_selector)
-                .attr("href")
-                .context("Require article link to have href")?
-                .to_string(),
-        )?;
+        let link = canonical::strip_tracking(
+            self.url(
+                &self
+                    .link_selector
+                    .select(|selector| article.select(selector))
+                    .attr("href")
+                    .context("Require article link to have href")?,
+            )?,
+        );
         Span::current().record("article", &link.as_str());
+        if self.summary_only {
+            let listed = self.listed(&article, headline, link)?;
+            drop(article);
+            return Ok(match listed {
+                Some(listed) => Some(self.finish(pages, listed).await?),
+                None => None,
+            });
+        }
         drop(article);
-        let document = Document::from(
-            &client
-                .get(link.clone())
-                .send()
-                .await?
-                .error_for_status()?
-                .text()
-                .await?,
-        );
+        let stored = match store {
+            Some(store) if !self.track_updates => store.get(&self.name, &link)?,
+            _ => None,
+        };
+        if let Some(stored) = stored {
+            trace!("Using stored article");
+            return Ok(Some(stored));
+        }
+        let document = Document::from(&pages.article(self, link.clone()).await?);
+        let canonical = canonical::canonical(&document, &link).filter(|canonical| *canonical != link);
+        if let Some(canonical) = &canonical {
+            trace!(canonical = canonical.as_str(), "Using canonical link");
+            // The article is stored under its canonical link, but found by the listing's.
+            if let Some(Err(error)) = store.map(|store| store.alias(&self.name, &link, canonical)) {
+                warn!(?error, "Failed to store the listing link");
+            }
+        }
+        let (document, page_url) = if self.prefer_amp {
+            self.amp_page(pages, &link, document).await
+        } else {
+            (document, link.clone())
+        };
 
-        let body = document.select(self.body_selector).html().to_string();
-        let image = if let Some(sel) = self.image_selector {
-            Some(
-                document
-                    .select(sel)
-                    .attr("src")
-                    .context("Expect image to have src")?
-                    .to_string()
-                    .parse()?,
-            )
+        let base = document
+            .select("base[href]")
+            .attr("href")
+            .and_then(|base| page_url.join(&base).ok())
+            .unwrap_or_else(|| page_url.clone());
+        let headline = if headline.is_empty() {
+            meta(&document, "og:title").unwrap_or(headline)
+        } else {
+            headline
+        };
+        let body = self.body(&document);
+        let mut images = if self.collect_images {
+            body_images(&body, &base)
         } else {
-            None
+            Vec::new()
+        };
+        let body = if body.exists() {
+            absolutize_urls(&body, &base);
+            let mut body = body.html().to_string();
+            self.follow_body_pages(pages, &page_url, &document, &mut body).await;
+            body
+        } else if let Some(description) = meta(&document, "og:description") {
+            format!("<p>{}</p>", escape_html(&description))
+        } else {
+            String::new()
+        };
+        let image = self
+            .image_selector
+            .as_ref()
+            .map(|image_selector| image_selector.select(|selector| document.select(selector)))
+            .and_then(|image| image_source(&image, &base))
+            .map(|(url, _)| url)
+            .or_else(|| meta(&document, "og:image").and_then(|src| base.join(&src).ok()))
+            .or_else(|| lead_image(&mut images));
+        images.retain(|(url, _)| Some(url) != image.as_ref());
+        let image = match image {
+            Some(url) => Some(pages.image(url).await),
+            None => None,
+        };
+        let author = self
+            .author_selector
+            .as_ref()
+            .map(|author_selector| {
+                author_selector
+                    .select(|selector| document.select(selector))
+                    .text()
+                    .trim()
+                    .to_owned()
+            })
+            .filter(|author| !author.is_empty())
+            .or_else(|| meta(&document, "author"));
+        let mut categories = self
+            .categories_selector
+            .as_ref()
+            .map(|categories_selector| {
+                categories_selector
+                    .select(|selector| document.select(selector))
+                    .iter()
+                    .map(|category| category.text().trim().to_owned())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        if categories.is_empty() {
+            categories = document
+                .select(r#"meta[property="article:section"], meta[property="article:tag"]"#)
+                .iter()
+                .filter_map(|category| category.attr("content"))
+                .map(|category| category.trim().to_owned())
+                .collect();
+        }
+        categories.retain(|category| !category.is_empty());
+        dedup(&mut categories);
+
+        let date = self.date_selector.select(|selector| document.select(selector));
+        let date = match &self.date_attribute {
+            Some(attribute) => date.attr(attribute).map(|date| date.to_string()).unwrap_or_default(),
+            None => date.text().to_string(),
+        };
+        let published_time = meta(&document, "article:published_time");
+        let (date, now) = match published_time {
+            Some(published_time) if date.trim().is_empty() => (
+                DateTime::parse_from_rfc3339(&published_time)
+                    .context("Failed to parse article:published_time")?
+                    .with_timezone(&self.timezone),
+                false,
+            ),
+            _ => match self.parse_date(date)? {
+                Some(date) => date,
+                None => return Ok(None),
+            },
         };
 
-        let date = (self.parse_date)(document.select(self.date_selector).text().to_string())?;
+        let guid = self.guid(
+            &Selection::from(document.root()),
+            &headline,
+            Some(&date).filter(|_| !now),
+        );
+        let description = match self.summary_sentences {
+            Some(count) => Some(render::sentences(&body, count)),
+            None => meta(&document, "description").or_else(|| meta(&document, "og:description")),
+        };
+        let language = self
+            .detect_language
+            .then(|| language::detect(&headline, &body))
+            .flatten()
+            .or_else(|| language::declared(&document));
 
-        Ok(Article {
+        metrics::ARTICLES_FETCHED.with_label_values(&[&self.name]).inc();
+        let article = Article {
+            guid,
+            updated: None,
+            alternates: Vec::new(),
+            headline,
+            link: canonical.unwrap_or(link),
+            description: description.filter(|description| !description.is_empty()),
+            language,
+            body,
+            image,
+            images: images
+                .into_iter()
+                .map(|(url, _)| Image {
+                    url,
+                    mime_type: None,
+                    length: None,
+                })
+                .collect(),
+            author,
+            categories,
+            date,
+        };
+        Ok(Some(self.finish(pages, article).await?))
+    }
+
+    /// Runs the script, rewrites, and translation on a freshly extracted article.
+    async fn finish(&self, pages: Pages<'_>, mut article: Article) -> Result<Article> {
+        if let Some(script) = &self.script {
+            article.headline = script.headline(article.headline)?;
+            article.body = script.body(article.body)?;
+        }
+        let article = rewrite::apply(&self.rewrites, article);
+        Ok(match (&self.translation, pages, translate::get()) {
+            (Some(translation), Pages::Http(client), Some(translator)) => {
+                translator.article(client, translation, article).await
+            }
+            _ => article,
+        })
+    }
+
+    /// The date, and whether it's the current time standing in for one that couldn't be read.
+    fn parse_date(&self, date: String) -> Result<Option<(DateTime<Tz>, bool)>> {
+        let date = match &self.script {
+            Some(script) => script.date(date)?,
+            None => date,
+        };
+        self.parse_date.parse_or_now(date, self.timezone)
+    }
+
+    /// An article from what its listing item shows: the body selector picks out any teaser, and
+    /// images aren't probed, so scraping a listing takes a single request.
+    fn listed(&self, item: &Selection, headline: String, link: Url) -> Result<Option<Article>> {
+        let select = |selectors: &Selectors| selectors.select(|selector| item.select(selector));
+        let base = self.url(&self.news_url)?;
+        let date = select(&self.date_selector);
+        let date = match &self.date_attribute {
+            Some(attribute) => date.attr(attribute).map(|date| date.to_string()).unwrap_or_default(),
+            None => date.text().to_string(),
+        };
+        let (date, now) = match self.parse_date(date)? {
+            Some(date) => date,
+            None => return Ok(None),
+        };
+        let body = self.body_selector.as_ref().map(select).unwrap_or_default();
+        self.strip(&body);
+        let body = if body.exists() {
+            absolutize_urls(&body, &base);
+            body.html().to_string()
+        } else {
+            String::new()
+        };
+        let image = self
+            .image_selector
+            .as_ref()
+            .map(select)
+            .and_then(|image| image_source(&image, &base))
+            .map(|(url, _)| Image {
+                url,
+                mime_type: None,
+                length: None,
+            });
+        let author = self
+            .author_selector
+            .as_ref()
+            .map(|author| select(author).text().trim().to_owned())
+            .filter(|author| !author.is_empty());
+        let mut categories = self
+            .categories_selector
+            .as_ref()
+            .map(|categories| {
+                select(categories)
+                    .iter()
+                    .map(|category| category.text().trim().to_owned())
+                    .filter(|category| !category.is_empty())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        dedup(&mut categories);
+        let language = self
+            .detect_language
+            .then(|| language::detect(&headline, &body))
+            .flatten();
+        metrics::ARTICLES_FETCHED.with_label_values(&[&self.name]).inc();
+        Ok(Some(Article {
+            guid: self.guid(item, &headline, Some(&date).filter(|_| !now)),
+            alternates: Vec::new(),
+            updated: None,
             headline,
             link,
+            description: self
+                .summary_sentences
+                .map(|count| render::sentences(&body, count))
+                .filter(|description| !description.is_empty()),
+            language,
             body,
             image,
+            images: Vec::new(),
+            author,
+            categories,
             date,
+        }))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Pages<'a> {
+    Http(&'a Client),
+    Fixtures(&'a Path),
+}
+
+impl Pages<'_> {
+    async fn fetch(self, scraper: &Scraper, url: Url) -> Result<String> {
+        match self {
+            #[cfg(feature = "browser")]
+            Pages::Http(client) if scraper.renderer == Renderer::Browser => {
+                browser::render(client, &url).await
+            }
+            Pages::Http(client) => retry::fetch(client, url, scraper.retry, &scraper.headers()).await,
+            Pages::Fixtures(dir) => fixtures::read(dir, &url),
+        }
+    }
+
+    async fn article(self, scraper: &Scraper, url: Url) -> Result<String> {
+        match self {
+            Pages::Http(client) if scraper.renderer == Renderer::Http => {
+                retry::fetch_cached(client, url, scraper.retry, &scraper.headers()).await
+            }
+            _ => self.fetch(scraper, url).await,
+        }
+    }
+
+    async fn fetch_if_modified(
+        self,
+        scraper: &Scraper,
+        url: Url,
+    ) -> Result<Option<(String, Option<retry::PendingValidators>)>> {
+        match self {
+            #[cfg(feature = "browser")]
+            Pages::Http(client) if scraper.renderer == Renderer::Browser => {
+                browser::render(client, &url).await.map(|text| Some((text, None)))
+            }
+            // Unchanged listings can still link to edited articles.
+            Pages::Http(_) if scraper.track_updates => {
+                self.fetch(scraper, url).await.map(|text| Some((text, None)))
+            }
+            Pages::Http(client) => {
+                retry::fetch_if_modified(client, url, scraper.retry, &scraper.headers())
+                    .await
+                    .map(|fetched| fetched.map(|(text, validators)| (text, Some(validators))))
+            }
+            Pages::Fixtures(dir) => fixtures::read(dir, &url).map(|text| Some((text, None))),
+        }
+    }
+
+    async fn image(self, url: Url) -> Image {
+        match self {
+            Pages::Http(client) => match images::get() {
+                Some(images) => cache_image(client, images, url).await,
+                None => probe_image(client, url).await,
+            },
+            Pages::Fixtures(_) => Image {
+                url,
+                mime_type: None,
+                length: None,
+            },
+        }
+    }
+}
+
+async fn probe_image(client: &Client, url: Url) -> Image {
+    let (mime_type, length) = match retry::probe(client, &url).await {
+        Ok(probed) => probed,
+        Err(error) => {
+            warn!(?error, "Failed to probe image");
+            (None, None)
+        }
+    };
+    Image {
+        url,
+        mime_type,
+        length,
+    }
+}
+
+/// Downloads an image into the image cache, which also tells its type and size. Responses that
+/// aren't images, or are too large to copy, are left to be linked from their origin.
+async fn cache_image(client: &Client, images: &'static ImageCache, url: Url) -> Image {
+    if let Some((mime_type, length)) = images.seen(&url) {
+        trace!(url = url.as_str(), "Using cached image");
+        return Image {
+            url,
+            mime_type: Some(mime_type),
+            length: Some(length),
+        };
+    }
+    let (mime_type, body) = match retry::download(client, &url, images::MAX_SIZE).await {
+        Ok(downloaded) => downloaded,
+        Err(error) => {
+            warn!(?error, "Failed to download image");
+            return Image {
+                url,
+                mime_type: None,
+                length: None,
+            };
+        }
+    };
+    let length = body.len() as u64;
+    match mime_type.clone() {
+        Some(mime_type) if mime_type.starts_with("image/") => {
+            let url = url.clone();
+            let stored = tokio::task::spawn_blocking(move || images.put(&url, &mime_type, &body));
+            if let Err(error) = stored.await {
+                warn!(?error, "Failed to cache image");
+            }
+        }
+        _ => trace!(?mime_type, "Not caching a response that isn't an image"),
+    }
+    Image {
+        url,
+        mime_type,
+        length: Some(length),
+    }
+}
+
+/// A declared icon, preferring touch icons, which are larger and square, or else `/favicon.ico`
+/// if the site has one.
+async fn find_icon(pages: Pages<'_>, document: &Document, url: &Url) -> Option<Image> {
+    let base = document
+        .select("base[href]")
+        .attr("href")
+        .and_then(|base| url.join(&base).ok())
+        .unwrap_or_else(|| url.clone());
+    let declared = [r#"link[rel~="apple-touch-icon"]"#, r#"link[rel~="icon"]"#]
+        .iter()
+        .find_map(|selector| document.select(selector).attr("href"))
+        .map(|href| href.to_string())
+        .or_else(|| meta(document, "og:logo"))
+        .and_then(|href| base.join(&href).ok())
+        .filter(|icon| matches!(icon.scheme(), "http" | "https"));
+    if let Some(icon) = declared {
+        trace!(icon = icon.as_str(), "Found site icon");
+        return Some(pages.image(icon).await);
+    }
+    let icon = pages.image(url.join("/favicon.ico").ok()?).await;
+    icon.mime_type.is_some().then_some(icon)
+}
+
+fn image_source(image: &Selection, base: &Url) -> Option<(Url, u32)> {
+    let srcset = ["srcset", "data-srcset"]
+        .iter()
+        .find_map(|attr| image.attr(attr))
+        .and_then(|srcset| {
+            srcset
+                .split(',')
+                .filter_map(|candidate| {
+                    let mut parts = candidate.split_whitespace();
+                    let url = parts.next()?.to_owned();
+                    let width = parts
+                        .next()
+                        .and_then(|descriptor| descriptor.strip_suffix('w'))
+                        .and_then(|width| width.parse().ok())
+                        .unwrap_or(0);
+                    Some((url, width))
+                })
+                .max_by_key(|(_, width)| *width)
+        });
+    let width = image
+        .attr("width")
+        .and_then(|width| width.parse().ok())
+        .unwrap_or(0);
+    let (src, width) = match srcset {
+        Some((src, srcset_width)) => (src, srcset_width.max(width)),
+        None => (
+            ["data-src", "data-lazy-src", "src"]
+                .iter()
+                .find_map(|attr| image.attr(attr))?
+                .to_string(),
+            width,
+        ),
+    };
+    if src.starts_with("data:") {
+        return None;
+    }
+    Some((base.join(&src).ok()?, width))
+}
+
+fn body_images(body: &Selection, base: &Url) -> Vec<(Url, u32)> {
+    let mut images = Vec::<(Url, u32)>::new();
+    for image in body.select("img").iter() {
+        if let Some(image) = image_source(&image, base) {
+            if !images.iter().any(|(url, _)| *url == image.0) {
+                images.push(image);
+            }
+        }
+    }
+    images
+}
+
+fn lead_image(images: &mut Vec<(Url, u32)>) -> Option<Url> {
+    let (index, _) = images
+        .iter()
+        .enumerate()
+        .max_by_key(|(index, (_, width))| (*width, Reverse(*index)))?;
+    Some(images.remove(index).0)
+}
+
+/// Drops repeats wherever they are, keeping the first of each.
+fn dedup(items: &mut Vec<String>) {
+    let mut seen = HashSet::new();
+    items.retain(|item| seen.insert(item.clone()));
+}
+
+fn meta(document: &Document, property: &str) -> Option<String> {
+    document
+        .select(&format!(r#"meta[property="{0}"], meta[name="{0}"]"#, property))
+        .attr("content")
+        .map(|content| content.trim().to_owned())
+        .filter(|content| !content.is_empty())
+}
+
+fn guid(feed: &str, id: &str) -> String {
+    format!("urn:news-rss:{}:{}", sanitise(&feed.to_lowercase()), sanitise(id))
+}
+
+/// `text` with everything but unreserved characters replaced by `-`, to fit in a URN.
+pub(crate) fn sanitise(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '.' | '_' | '~' => c,
+            _ => '-',
         })
+        .collect()
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub(crate) fn absolutize_urls(body: &Selection, base: &Url) {
+    for attr in ["href", "src"] {
+        for mut element in body.select(&format!("[{}]", attr)).iter() {
+            let url = element
+                .attr(attr)
+                .and_then(|url| base.join(&url).ok());
+            if let Some(url) = url {
+                element.set_attr(attr, url.as_str());
+            }
+        }
     }
 }
+
 pub const RTE: Scraper = Scraper {
-    name: "RTE",
-    base_url: "https://www.rte.ie/",
-    news_url: "/news/",
-    article_selector: ":not(.av-box) ~ .article-meta",
-    headline_selector: "span.underline",
-    link_selector: "a",
-    body_selector: "section.article-body",
+    name: Cow::Borrowed("RTE"),
+    description: Some(Cow::Borrowed("Latest news from RTÉ")),
+    language: Some(Cow::Borrowed("en-ie")),
+    base_url: Cow::Borrowed("https://www.rte.ie/"),
+    news_url: Cow::Borrowed("/news/"),
+    article_selector: Cow::Borrowed(":not(.av-box) ~ .article-meta"),
+    headline_selector: Selectors::Static(&["span.underline"]),
+    link_selector: Selectors::Static(&["a"]),
+    extractor: Extractor::Selector,
+    remove_selectors: Cow::Borrowed(&[]),
+    rewrites: Cow::Borrowed(&[]),
+    script: None,
+    body_selector: Some(Selectors::Static(&["section.article-body"])),
     image_selector: None,
-    date_selector: "span.modified-date",
-    parse_date: |date| {
-        let span = span!(Level::TRACE, "RTE.parse_date", date = date.as_str());
+    collect_images: false,
+    date_selector: Selectors::Static(&["span.modified-date"]),
+    author_selector: None,
+    categories_selector: None,
+    date_attribute: None,
+    parse_date: DateParser::Format {
+        formats: Cow::Borrowed(&[Cow::Borrowed("Updated / %A, %-d %b %Y %R")]),
+        fallback: DateFallback::Now,
+    },
+    timezone: Europe::Dublin,
+    interval: Duration::from_secs(60 * 60),
+    concurrency: DEFAULT_CONCURRENCY,
+    retry: RetryPolicy::DEFAULT,
+    retention: Retention::DEFAULT,
+    stagger: Stagger::DEFAULT,
+    adaptive: None,
+    headers: Cow::Borrowed(&[]),
+    pagination: None,
+    article_next_page_selector: None,
+    prefer_amp: false,
+    renderer: Renderer::Http,
+    guid: GuidStrategy::Link,
+    track_updates: false,
+    summary_only: false,
+    summary_sentences: None,
+    detect_language: true,
+    translation: None,
+    bump_pub_date: false,
+    max_items: None,
+};
+
+pub const BBC: Scraper = Scraper {
+    name: Cow::Borrowed("BBC"),
+    description: Some(Cow::Borrowed("Latest news from the BBC")),
+    language: Some(Cow::Borrowed("en-gb")),
+    base_url: Cow::Borrowed("https://www.bbc.co.uk/"),
+    news_url: Cow::Borrowed("/news/"),
+    article_selector: Cow::Borrowed(r#"[data-testid$="-card"]"#),
+    headline_selector: Selectors::Static(&[r#"[data-testid="card-headline"]"#, "h2", "h3"]),
+    link_selector: Selectors::Static(&[r#"a[data-testid="internal-link"]"#, "a"]),
+    extractor: Extractor::Selector,
+    remove_selectors: Cow::Borrowed(&[]),
+    rewrites: Cow::Borrowed(&[]),
+    script: None,
+    body_selector: Some(Selectors::Static(&["main article", "article"])),
+    image_selector: Some(Selectors::Static(&["article figure img", "article img"])),
+    collect_images: false,
+    date_selector: Selectors::Static(&[r#"time[data-testid="timestamp"]"#, "time[datetime]"]),
+    author_selector: None,
+    categories_selector: None,
+    date_attribute: Some(Cow::Borrowed("datetime")),
+    parse_date: DateParser::Custom(|date| {
+        let span = span!(Level::TRACE, "BBC.parse_date", date);
         let _entered = span.enter();
         trace!("Parsing date");
-        Europe::Dublin
-            .from_local_datetime(&NaiveDateTime::parse_from_str(
-                date.trim(),
-                "Updated / %A, %-d %b %Y %R",
-            ).unwrap_or_else(|_| Utc::now().with_timezone(&Europe::Dublin).naive_local()))
-            .earliest()
-            .context("No local date")
+        DateTime::parse_from_rfc3339(date).with_context(|| format!("Failed to parse date {:?}", date))
+    }),
+    timezone: Europe::London,
+    interval: Duration::from_secs(60 * 60),
+    concurrency: DEFAULT_CONCURRENCY,
+    retry: RetryPolicy::DEFAULT,
+    retention: Retention::DEFAULT,
+    stagger: Stagger::DEFAULT,
+    adaptive: None,
+    headers: Cow::Borrowed(&[]),
+    pagination: None,
+    article_next_page_selector: None,
+    prefer_amp: false,
+    renderer: Renderer::Http,
+    guid: GuidStrategy::Link,
+    track_updates: false,
+    summary_only: false,
+    summary_sentences: None,
+    detect_language: true,
+    translation: None,
+    bump_pub_date: false,
+    max_items: None,
+};
+
+pub const GUARDIAN: Scraper = Scraper {
+    name: Cow::Borrowed("Guardian"),
+    description: Some(Cow::Borrowed("Latest international news from the Guardian")),
+    language: Some(Cow::Borrowed("en-gb")),
+    base_url: Cow::Borrowed("https://www.theguardian.com/"),
+    news_url: Cow::Borrowed("/international"),
+    article_selector: Cow::Borrowed(".fc-item__container"),
+    headline_selector: Selectors::Static(&[".js-headline-text", ".fc-item__title", "h3"]),
+    link_selector: Selectors::Static(&["a.fc-item__link", "a"]),
+    extractor: Extractor::Selector,
+    remove_selectors: Cow::Borrowed(&[]),
+    rewrites: Cow::Borrowed(&[]),
+    script: None,
+    body_selector: Some(Selectors::Static(&[
+        ".article-body-commercial-selector",
+        r#"[data-gu-name="body"]"#,
+        "#maincontent",
+    ])),
+    image_selector: Some(Selectors::Static(&[r#"[data-gu-name="media"] img"#, "figure img"])),
+    collect_images: false,
+    author_selector: Some(Selectors::Static(&[r#"a[rel="author"]"#])),
+    categories_selector: Some(Selectors::Static(&[r#"a[data-link-name="article section"]"#])),
+    date_selector: Selectors::Static(&[
+        r#"[data-gu-name="meta"] details summary"#,
+        ".content__dateline",
+    ]),
+    date_attribute: None,
+    parse_date: DateParser::Format {
+        formats: Cow::Borrowed(&[
+            Cow::Borrowed("%a %-d %b %Y %H.%M GMT"),
+            Cow::Borrowed("%a %-d %b %Y %H.%M BST"),
+            Cow::Borrowed("Published: %a %-d %b %Y %H.%M GMT"),
+            Cow::Borrowed("Published: %a %-d %b %Y %H.%M BST"),
+        ]),
+        fallback: DateFallback::Fail,
     },
+    timezone: Europe::London,
+    interval: Duration::from_secs(60 * 60),
+    concurrency: DEFAULT_CONCURRENCY,
+    retry: RetryPolicy::DEFAULT,
+    retention: Retention::DEFAULT,
+    stagger: Stagger::DEFAULT,
+    adaptive: None,
+    headers: Cow::Borrowed(&[]),
+    pagination: None,
+    article_next_page_selector: None,
+    prefer_amp: false,
+    renderer: Renderer::Http,
+    guid: GuidStrategy::Link,
+    track_updates: false,
+    summary_only: false,
+    summary_sentences: None,
+    detect_language: true,
+    translation: None,
+    bump_pub_date: false,
+    max_items: None,
 };
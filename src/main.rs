@@ -1,14 +1,70 @@
 use anyhow::*;
-use axum::{handler::get, http::StatusCode, Router};
-use futures::future::try_join_all;
-use news_rss::{Article, Scraper, RTE};
-use rss::{ChannelBuilder, GuidBuilder, ItemBuilder};
-use std::{collections::HashMap, net::SocketAddr, time::Duration};
-use tokio::{select, sync::Mutex, time::sleep};
-use tracing::{instrument, span, trace, Instrument, Level};
+use futures::{
+    future::pending,
+    stream::{FuturesUnordered, StreamExt},
+    FutureExt,
+};
+use news_rss::{
+    activitypub::{self, ActivityPub},
+    alert::{self, Alert, Event, Webhook},
+    auth::{self, Auth},
+    cache::{self, HttpCache},
+    cluster::{self, Clustering},
+    digest::Digest,
+    export::{self, Export},
+    images::{self, ImageCache, Thumbnails},
+    telegram::{self, Telegram},
+    throttle::{self, Throttle},
+    tls::{self, Certificates, Tls},
+    translate::{self, Translator},
+    push::{self, Push},
+    feed::{self, VirtualFeed}, metrics, render::{Channel, Format}, status, Article,
+    store::{LastScrape, Store},
+    client::USER_AGENT,
+    politeness,
+    websub::{self, Hub}, ClientConfig, Feed, JsonApi, Passthrough, Plugin, RateLimited, Scraper, Source, GUARDIAN, RTE,
+};
+use admin::Admin;
+use cli::{Cli, Command};
+use registry::Registry;
+use server::{server, Listen};
+use chrono::Utc;
+use reqwest::Client;
+use serde_json::json;
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    fs,
+    iter::once,
+    panic::AssertUnwindSafe,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{
+    select,
+    signal::{
+        ctrl_c,
+        unix::{signal, SignalKind},
+    },
+    sync::{watch, Mutex, Notify},
+    time::sleep,
+    try_join,
+};
+use tracing::{error, info, instrument, trace, warn};
 use tracing_subscriber::EnvFilter;
 use std::env::args;
 
+mod admin;
+mod cli;
+mod registry;
+mod server;
+
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+const CIRCUIT_THRESHOLD: u32 = 3;
+const MAX_BACKOFF: Duration = Duration::from_secs(24 * 60 * 60);
+/// The least a rate limited scraper waits, for sources that ask for less than retrying already
+/// waited.
+const MIN_PAUSE: Duration = Duration::from_secs(60);
+
 #[tokio::main]
 async fn main() -> Result<()> {
     tracing::subscriber::set_global_default(
@@ -16,86 +72,1055 @@ async fn main() -> Result<()> {
             .with_env_filter(EnvFilter::from_default_env())
             .finish(),
     )?;
-    let address = args().skip(1).next().unwrap_or_else(|| "0.0.0.0:2048".to_owned());
-    let (address, port) = address.split_once(":").unwrap_or_else(|| ("0.0.0.0", &address));
-    let address = SocketAddr::new(address.parse()?, port.parse()?);
-    let feeds = Box::leak(Box::new(Mutex::new(HashMap::new())));
-    select!(
-        r = server(address, feeds) => r,
-        r = scrape(&[RTE], feeds) => r
+    let cli = Cli::parse(args().skip(1))?;
+    let command = cli.command;
+    let config = cli.get("config");
+    let client = ClientConfig {
+        user_agent: cli.get("user-agent"),
+        proxy: cli.get("proxy"),
+        timeout: cli.parse_value("timeout")?.map(Duration::from_secs),
+        connect_timeout: cli.parse_value("connect-timeout")?.map(Duration::from_secs),
+        max_redirects: cli.parse_value("max-redirects")?,
+        cache_dir: cli.get("cache-dir").map(Into::into),
+        image_dir: cli.get("image-dir").map(Into::into),
+        thumbnail_width: cli.parse_value("thumbnail-width")?,
+        thumbnail_format: None,
+    };
+    let webhook = cli.get("webhook");
+    let webhook_format = cli.parse_value("webhook-format")?;
+    let push = cli.get("push-webhook");
+    let hub = cli.get("hub");
+    let output_dir = cli.get("output-dir");
+    let client = match &config {
+        Some(config) => client.or(ClientConfig::from_config(config)?),
+        None => client,
+    };
+    politeness::configure(client.user_agent.as_deref().unwrap_or(USER_AGENT));
+    if let Some(dir) = &client.cache_dir {
+        cache::configure(HttpCache::open(dir)?);
+    }
+    let image_dir = client.image_dir.clone();
+    let thumbnails = client.thumbnail_width.map(|width| Thumbnails {
+        width,
+        format: client.thumbnail_format.unwrap_or_default(),
+    });
+    let client = client.build()?;
+    let webhook = match (webhook, &config) {
+        (Some(url), _) => Some(Webhook {
+            url: url.parse().context("Invalid webhook URL")?,
+            format: webhook_format.unwrap_or_default(),
+        }),
+        (None, Some(config)) => Webhook::from_config(config)?.map(|webhook| Webhook {
+            format: webhook_format.unwrap_or(webhook.format),
+            ..webhook
+        }),
+        (None, None) => None,
+    };
+    if let Some(webhook) = webhook {
+        alert::configure(webhook);
+    }
+    let push = match (push, &config) {
+        (Some(url), _) => Some(Push {
+            url: url.parse().context("Invalid push webhook URL")?,
+            headers: Default::default(),
+        }),
+        (None, Some(config)) => Push::from_config(config)?,
+        (None, None) => None,
+    };
+    if let Some(push) = push {
+        push::configure(push);
+    }
+    if let Some(telegram) = match &config {
+        Some(config) => Telegram::from_config(config)?,
+        None => None,
+    } {
+        telegram::configure(telegram);
+    }
+    if let Some(translator) = match &config {
+        Some(config) => Translator::from_config(config)?,
+        None => None,
+    } {
+        translate::configure(translator);
+    }
+    if let Some(clustering) = match &config {
+        Some(config) => Clustering::from_config(config)?,
+        None => None,
+    } {
+        cluster::configure(clustering);
+    }
+    let digest = match &config {
+        Some(config) => Digest::from_config(config)?,
+        None => None,
+    };
+    let hub = match (hub, &config) {
+        (Some(url), _) => Some(Hub {
+            url: url.parse().context("Invalid WebSub hub URL")?,
+        }),
+        (None, Some(config)) => Hub::from_config(config)?,
+        (None, None) => None,
+    };
+    let export = match &config {
+        Some(config) => Export::from_config(config)?,
+        None => None,
+    };
+    let export = match (output_dir, export) {
+        (Some(dir), Some(export)) => Some(Export {
+            dir: Some(dir.into()),
+            ..export
+        }),
+        (Some(dir), None) => Some(Export {
+            dir: Some(dir.into()),
+            s3: None,
+            index: true,
+            public_url: None,
+        }),
+        (None, export) => export,
+    };
+    let scrapers = match &config {
+        Some(config) => Scraper::from_config(config)?,
+        None => vec![RTE, GUARDIAN],
+    };
+    if command == Command::DebugSelectors {
+        let (name, location) = match cli.positional.as_slice() {
+            [name, location] => (name, location),
+            _ => bail!("Usage: news-rss debug-selectors SCRAPER LOCATION"),
+        };
+        return debug_selectors(&scrapers, &client, name, location).await;
+    }
+    let passthroughs = match &config {
+        Some(config) => Passthrough::from_config(config)?,
+        None => vec![],
+    };
+    let apis = match &config {
+        Some(config) => JsonApi::from_config(config)?,
+        None => vec![],
+    };
+    let plugins = match &config {
+        Some(config) => Plugin::from_config(config)?,
+        None => vec![],
+    };
+    let virtual_feeds = match &config {
+        Some(config) => VirtualFeed::from_config(config)?,
+        None => vec![],
+    };
+    match command {
+        Command::ListScrapers => {
+            list_scrapers(&scrapers, &passthroughs, &apis, &plugins);
+            return Ok(());
+        }
+        Command::ValidateConfig => {
+            let config = config.context("Expected a config file to validate")?;
+            ActivityPub::from_config(&config, "https://localhost")?;
+            Tls::from_config(&config)?.map(Certificates::new).transpose()?;
+            Throttle::from_config(&config)?;
+            Auth::from_config(&config)?;
+            println!(
+                "{} is valid: {} scrapers, {} feeds, {} APIs, {} plugins, {} virtual feeds",
+                config,
+                scrapers.len(),
+                passthroughs.len(),
+                apis.len(),
+                plugins.len(),
+                virtual_feeds.len()
+            );
+            return Ok(());
+        }
+        _ => {}
+    }
+    let sources = boxed(scrapers, passthroughs, apis, plugins);
+    if command == Command::ScrapeOnce {
+        return scrape_once(&sources, &client, cli.positional.first().map(String::as_str)).await;
+    }
+    ensure!(
+        command != Command::Export || export.is_some(),
+        "Expected --output-dir or an [export] section to export to"
+    );
+    let listen: Listen = cli
+        .get("listen")
+        .or_else(|| cli.positional.first().cloned())
+        .as_deref()
+        .unwrap_or("0.0.0.0:2048")
+        .parse()?;
+    let tls = match &config {
+        Some(config) if command == Command::Serve => Tls::from_config(config)?,
+        _ => None,
+    };
+    match (tls, &listen) {
+        (Some(tls), Listen::Tcp(_)) => tls::configure(Certificates::new(tls)?),
+        (Some(_), Listen::Unix(_)) => warn!("Ignoring TLS, which requires a TCP listen address"),
+        (None, _) => {}
+    }
+    if let Some(throttle) = config.as_ref().map(Throttle::from_config).transpose()?.flatten() {
+        throttle::configure(throttle);
+    }
+    if let Some(auth) = config.as_ref().map(Auth::from_config).transpose()?.flatten() {
+        auth::configure(auth);
+    }
+    let store: Option<&'static Store> = cli
+        .get("store")
+        .map(Store::open)
+        .transpose()?
+        .map(|store| &*Box::leak(Box::new(store)));
+    let public_url = cli.get("public-url").map(|url| url.trim_end_matches('/').to_owned());
+    match (hub, &public_url) {
+        (Some(hub), Some(_)) => websub::configure(hub),
+        (Some(_), None) => warn!("Ignoring WebSub hub, which requires --public-url"),
+        (None, _) => {}
+    }
+    let activitypub = match (&public_url, cli.get("activitypub-key"), &config) {
+        (Some(url), Some(key), _) => Some(ActivityPub::new(
+            url,
+            &fs::read_to_string(&key).with_context(|| format!("Failed to read private key {}", key))?,
+        )?),
+        (Some(url), None, Some(config)) => ActivityPub::from_config(config, url)?,
+        (None, Some(_), _) => {
+            warn!("Ignoring ActivityPub key, which requires --public-url");
+            None
+        }
+        _ => None,
+    };
+    match (activitypub, store) {
+        (Some(activitypub), Some(_)) => activitypub::configure(activitypub),
+        (Some(_), None) => warn!("Ignoring ActivityPub, which requires --store"),
+        (None, _) => {}
+    }
+    if let Some(export) = export {
+        export::configure(Export {
+            public_url: public_url.clone(),
+            ..export
+        });
+    }
+    match (image_dir, &public_url) {
+        (Some(dir), Some(_)) if command == Command::Serve && export::get().is_none() => {
+            let images = ImageCache::open(dir)?;
+            images::configure(match thumbnails {
+                Some(thumbnails) => images.with_thumbnails(thumbnails),
+                None => images,
+            });
+        }
+        (Some(_), _) => warn!("Ignoring image cache, which requires serving with --public-url"),
+        (None, _) => {}
+    }
+    let registry = Registry::new(
+        sources,
+        virtual_feeds,
+        None,
+        public_url.as_deref(),
+        store.is_some(),
+    );
+    let mut restored = HashMap::new();
+    if let Some(store) = store {
+        for source in &registry.sources {
+            let articles = store.load(source.name())?;
+            let channel = registry.channels[source.name()].clone();
+            restored.insert(source.name().to_owned(), Feed::new(channel, articles));
+        }
+        combine(&mut restored, &registry);
+    }
+    if command == Command::Export {
+        return export_once(&registry, &client, store, restored).await;
+    }
+    if let Some(export) = export::get().filter(|_| !restored.is_empty()) {
+        let names = restored.keys().map(String::as_str).collect::<Vec<_>>();
+        export.publish(&client, &export.files(&restored, &names)).await?;
+    }
+    let feeds = Box::leak(Box::new(Mutex::new(restored)));
+    let (registry_tx, registry) = watch::channel(registry);
+    let refresh_token = cli.get("refresh-token");
+    ensure!(
+        refresh_token.as_ref().is_none_or(|token| !token.trim().is_empty()),
+        "Expected a non-empty refresh token"
+    );
+    let refresh_token = refresh_token.map(|token| &*Box::leak(token.into_boxed_str()));
+    let edited: &'static Notify = Box::leak(Box::new(Notify::new()));
+    let admin_token = cli.get("admin-token");
+    // An empty token would let in anyone sending an empty `Authorization: Bearer`.
+    ensure!(
+        admin_token.as_ref().is_none_or(|token| !token.trim().is_empty()),
+        "Expected a non-empty admin token"
+    );
+    match (admin_token, &config) {
+        (Some(token), Some(config)) => admin::configure(Admin {
+            token: Box::leak(token.into_boxed_str()),
+            config: Box::leak(config.clone().into_boxed_str()),
+            reload: edited,
+        }),
+        (Some(_), None) => warn!("Ignoring admin token, as there is no config file to edit"),
+        (None, _) => {}
+    }
+    let (shutdown_tx, shutdown) = watch::channel(false);
+    let signal = async move {
+        shutdown_signal().await?;
+        info!("Shutting down");
+        shutdown_tx.send(true).ok();
+        Ok(())
+    };
+    let server = server(
+        listen,
+        client.clone(),
+        Box::leak(Box::new(registry.clone())),
+        feeds,
+        store,
+        refresh_token,
+        shutdown.clone(),
+    );
+    let reload = reload(
+        config.as_deref(),
+        registry_tx,
+        feeds,
+        store,
+        public_url.as_deref(),
+        edited,
+        shutdown.clone(),
+    );
+    let serve = async move {
+        if export::get().is_some() {
+            info!("Exporting feeds instead of serving them");
+            return Ok(());
+        }
+        server.await
+    };
+    try_join!(
+        signal,
+        serve,
+        reload,
+        scrape(&client, store, feeds, registry.clone(), shutdown.clone()),
+        send_digests(digest, feeds, registry.clone(), shutdown)
     )?;
+    if let Some(store) = store {
+        store.flush()?;
+    }
     Ok(())
 }
 
-#[instrument(skip(feeds))]
-async fn server(address: SocketAddr, feeds: &'static Mutex<HashMap<&'static str, Vec<Article>>>) -> Result<()> {
-    let feed = |name: &'static str| {
-        move || {
-            async move {
-                trace!("Entered feed handler");
-                let feeds = feeds.lock().await;
-                let feed = feeds.get(name);
-                let feed = if let Some(feed) = feed {
-                    feed
-                } else {
-                    trace!("Feed not found");
-                    return Err(StatusCode::NOT_FOUND);
+fn article_json(article: &Article) -> serde_json::Value {
+    json!({
+        "headline": article.headline,
+        "link": article.link.as_str(),
+        "guid": article.id(),
+        "date": article.date.to_rfc3339(),
+        "updated": article.updated.map(|updated| updated.to_rfc3339()),
+        "author": article.author,
+        "categories": article.categories,
+        "image": article.image.as_ref().map(|image| image.url.as_str()),
+        "images": article.images.iter().map(|image| image.url.as_str()).collect::<Vec<_>>(),
+        "body": article.body,
+    })
+}
+
+async fn scrape_once(
+    sources: &[Box<dyn Source>],
+    client: &Client,
+    name: Option<&str>,
+) -> Result<()> {
+    let sources = sources
+        .iter()
+        .filter(|source| name.is_none_or(|name| source.name().eq_ignore_ascii_case(name)))
+        .collect::<Vec<_>>();
+    if let Some(name) = name {
+        ensure!(!sources.is_empty(), "No scraper named {:?}", name);
+    }
+    let mut output = Vec::new();
+    let mut problems = 0;
+    for source in sources {
+        let scraped = source
+            .fetch(client, None)
+            .await
+            .with_context(|| format!("Failed to scrape {}", source.name()))?
+            .context("Expected a fresh listing page")?;
+        let empty = scraped
+            .articles
+            .iter()
+            .filter(|article| article.headline.is_empty() || article.body.is_empty())
+            .count();
+        if scraped.articles.is_empty() {
+            warn!(feed = source.name(), "No articles found");
+            problems += 1;
+        }
+        problems += empty + scraped.failed;
+        output.push(json!({
+            "feed": source.name(),
+            "failed": scraped.failed,
+            "empty": empty,
+            "articles": scraped.articles.iter().map(article_json).collect::<Vec<_>>(),
+        }));
+    }
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    ensure!(problems == 0, "{} problems found while scraping", problems);
+    Ok(())
+}
+
+/// Scrapes every source once and exports the feeds, for running from cron without a server.
+async fn export_once(
+    registry: &Registry,
+    client: &Client,
+    store: Option<&Store>,
+    mut feeds: HashMap<String, Feed>,
+) -> Result<()> {
+    let export = export::get().context("Expected an export directory or bucket")?;
+    let mut failed = 0;
+    for source in &registry.sources {
+        let name = source.name();
+        let scraped = match source.fetch(client, store).await {
+            Ok(Some(scraped)) => scraped,
+            Ok(None) => continue,
+            Err(error) => {
+                warn!(?error, feed = name, "Failed to scrape");
+                failed += 1;
+                continue;
+            }
+        };
+        match feeds.entry(name.to_owned()) {
+            Entry::Occupied(entry) => {
+                let feed = entry.into_mut();
+                feed.set_icon(scraped.icon);
+                feed.update(scraped.articles, source.retention());
+            }
+            Entry::Vacant(entry) => {
+                let channel = Channel {
+                    icon: scraped.icon,
+                    ..registry.channels[name].clone()
                 };
-                let items = feed
-                    .iter()
-                    .map(|article| {
-                        trace!(?article, "Generating article RSS");
-                        ItemBuilder::default()
-                            .title(article.headline.to_owned())
-                            .guid(
-                                GuidBuilder::default()
-                                    .value(article.link.as_str().to_owned())
-                                    .permalink(true)
-                                    .build()
-                                    .unwrap(),
-                            )
-                            .link(article.link.as_str().to_owned())
-                            .pub_date(article.date.to_rfc2822())
-                            .content(article.body.to_owned())
-                            .build()
-                            .unwrap()
-                    })
-                    .collect::<Vec<_>>();
-                Ok(ChannelBuilder::default()
-                    .title(name)
-                    .items(items)
-                    .build()
-                    .unwrap()
-                    .to_string())
+                entry.insert(Feed::new(channel, scraped.articles));
             }
-            .instrument(span!(
-                Level::TRACE,
-                "feed-handler",
-                name
-            ))
         }
-    };
-    let app = Router::new().route("/rte.rss", get(feed("RTE")));
+        if let Some(store) = store {
+            store.save(name, &feeds[name].articles)?;
+        }
+    }
+    combine(&mut feeds, registry);
+    let names = feeds.keys().map(String::as_str).collect::<Vec<_>>();
+    export.publish(client, &export.files(&feeds, &names)).await?;
+    if let Some(store) = store {
+        store.flush()?;
+    }
+    ensure!(failed == 0, "{} feeds failed to scrape", failed);
+    Ok(())
+}
 
-    axum::Server::bind(&address)
-        .serve(app.into_make_service())
-        .await?;
+fn boxed(
+    scrapers: Vec<Scraper>,
+    passthroughs: Vec<Passthrough>,
+    apis: Vec<JsonApi>,
+    plugins: Vec<Plugin>,
+) -> Vec<Box<dyn Source>> {
+    scrapers
+        .into_iter()
+        .map(|scraper| Box::new(scraper) as Box<dyn Source>)
+        .chain(passthroughs.into_iter().map(|feed| Box::new(feed) as Box<dyn Source>))
+        .chain(apis.into_iter().map(|api| Box::new(api) as Box<dyn Source>))
+        .chain(plugins.into_iter().map(|plugin| Box::new(plugin) as Box<dyn Source>))
+        .collect()
+}
+
+type Loaded = (Vec<Box<dyn Source>>, Vec<VirtualFeed>);
+
+fn load(config: &str) -> Result<Loaded> {
+    let sources = boxed(
+        Scraper::from_config(config)?,
+        Passthrough::from_config(config)?,
+        JsonApi::from_config(config)?,
+        Plugin::from_config(config)?,
+    );
+    Ok((sources, VirtualFeed::from_config(config)?))
+}
+
+fn list_scrapers(
+    scrapers: &[Scraper],
+    passthroughs: &[Passthrough],
+    apis: &[JsonApi],
+    plugins: &[Plugin],
+) {
+    let sources = scrapers
+        .iter()
+        .map(|scraper| ("scraper", scraper as &dyn Source))
+        .chain(passthroughs.iter().map(|feed| ("feed", feed as &dyn Source)))
+        .chain(apis.iter().map(|api| ("api", api as &dyn Source)))
+        .chain(plugins.iter().map(|plugin| ("plugin", plugin as &dyn Source)));
+    for (kind, source) in sources {
+        println!(
+            "{:<24} {:<8} every {:>6}s  {}",
+            source.name(),
+            kind,
+            source.interval().as_secs(),
+            source.channel().link.unwrap_or_default()
+        );
+    }
+}
+
+fn truncate(text: &str, length: usize) -> String {
+    match text.char_indices().nth(length) {
+        Some((index, _)) => format!("{}…", &text[..index]),
+        None => text.to_owned(),
+    }
+}
+
+async fn debug_selectors(
+    scrapers: &[Scraper],
+    client: &Client,
+    name: &str,
+    location: &str,
+) -> Result<()> {
+    let scraper = scrapers
+        .iter()
+        .find(|scraper| scraper.name.eq_ignore_ascii_case(name))
+        .with_context(|| format!("No scraper named {:?}", name))?;
+    for report in scraper.debug_selectors(client, location).await? {
+        println!(
+            "{:<10} {:?}: {} matches{}",
+            report.field,
+            report.selector,
+            report.count,
+            if report.used { " (used)" } else { "" }
+        );
+        for element in report.elements {
+            println!("    text: {:?}", truncate(&element.text, 120));
+            for (name, value) in [
+                ("href", element.href),
+                ("src", element.src),
+                ("attribute", element.attribute),
+            ] {
+                if let Some(value) = value {
+                    println!("    {}: {}", name, value);
+                }
+            }
+        }
+    }
     Ok(())
 }
 
-#[instrument(skip(out))]
+async fn shutdown_signal() -> Result<()> {
+    let mut terminate = signal(SignalKind::terminate())?;
+    select!(
+        r = ctrl_c() => r?,
+        _ = terminate.recv() => {},
+    );
+    Ok(())
+}
+
+async fn shutdown_timeout(mut shutdown: watch::Receiver<bool>) {
+    while !*shutdown.borrow() {
+        if shutdown.changed().await.is_err() {
+            return pending().await;
+        }
+    }
+    sleep(SHUTDOWN_TIMEOUT).await;
+}
+
+#[instrument(skip_all)]
+async fn send_digests(
+    digest: Option<Digest>,
+    feeds: &Mutex<HashMap<String, Feed>>,
+    registry: watch::Receiver<Arc<Registry>>,
+    mut shutdown: watch::Receiver<bool>,
+) -> Result<()> {
+    let digest = match digest {
+        Some(digest) => digest,
+        None => return Ok(()),
+    };
+    let mut since = digest.next(Utc::now()) - digest.period();
+    loop {
+        let next = digest.next(Utc::now());
+        info!(%next, "Scheduled digest");
+        select!(
+            _ = sleep((next - Utc::now()).to_std().unwrap_or_default()) => {},
+            _ = shutdown.changed() => return Ok(()),
+        );
+        // The combined and virtual feeds only repeat articles from the sources.
+        let current = registry.borrow().clone();
+        let sections = {
+            let feeds = feeds.lock().await;
+            let mut sections = current
+                .sources
+                .iter()
+                .map(|source| source.name())
+                .filter(|name| digest.includes(name))
+                .filter_map(|name| feeds.get(name).map(|feed| (name, feed)))
+                .map(|(name, feed)| {
+                    let articles = feed
+                        .articles
+                        .iter()
+                        .filter(|article| article.date > since)
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    (name, articles)
+                })
+                .collect::<Vec<_>>();
+            sections.sort_unstable_by_key(|(name, _)| *name);
+            sections
+        };
+        match digest.message(&sections) {
+            Some(message) => match digest.smtp.send(&message).await {
+                Ok(()) => info!("Sent digest"),
+                Err(error) => warn!(?error, "Failed to send digest"),
+            },
+            None => trace!("No new articles for digest"),
+        }
+        since = next;
+    }
+}
+
+fn backoff(interval: Duration, failures: u32) -> Duration {
+    match failures.checked_sub(CIRCUIT_THRESHOLD) {
+        Some(opened) => interval
+            .saturating_mul(2u32.saturating_pow(opened + 1))
+            .min(MAX_BACKOFF.max(interval)),
+        None => interval,
+    }
+}
+
+fn topics(channels: &[&Channel]) -> Vec<String> {
+    channels
+        .iter()
+        .filter_map(|channel| channel.self_link.as_ref())
+        .flat_map(|link| {
+            Format::ALL
+                .iter()
+                .map(move |format| format!("{}.{}", link, format.extension()))
+        })
+        .collect()
+}
+
+/// Recomputes the combined and virtual feeds from the sources, returning those that changed.
+fn combine<'a>(feeds: &mut HashMap<String, Feed>, registry: &'a Registry) -> Vec<&'a str> {
+    let derived = {
+        let sources = registry
+            .sources
+            .iter()
+            .filter_map(|source| feeds.get(source.name()))
+            .collect::<Vec<_>>();
+        registry
+            .virtual_feeds
+            .iter()
+            .map(|virtual_feed| {
+                (virtual_feed.name.as_str(), virtual_feed.select(sources.iter().copied()))
+            })
+            .chain(once((feed::COMBINED, feed::combine(sources.iter().copied()))))
+            .collect::<Vec<_>>()
+    };
+    let mut changed = Vec::new();
+    for (name, articles) in derived {
+        let updated = match feeds.entry(name.to_owned()) {
+            Entry::Occupied(mut entry) => entry.get_mut().replace(articles),
+            Entry::Vacant(entry) => {
+                entry.insert(Feed::new(registry.channels[name].clone(), articles));
+                true
+            }
+        };
+        if updated {
+            changed.push(name);
+        }
+    }
+    changed
+}
+
+/// Runs a scrape loop for each registered source, starting and stopping loops as the registry
+/// is replaced.
+#[instrument(skip_all)]
 async fn scrape(
-    feeds: &[Scraper],
-    out: &Mutex<HashMap<&'static str, Vec<Article>>>,
+    client: &Client,
+    store: Option<&Store>,
+    out: &Mutex<HashMap<String, Feed>>,
+    mut registry: watch::Receiver<Arc<Registry>>,
+    mut shutdown: watch::Receiver<bool>,
 ) -> Result<()> {
-    let client = reqwest::ClientBuilder::new().build()?;
+    let mut running = FuturesUnordered::new();
+    let mut stops: Vec<(Arc<dyn Source>, watch::Sender<bool>)> = Vec::new();
     loop {
-        let articles = try_join_all(feeds.iter().map(|x| x.get_articles(&client))).await?;
-        let mut out = out.lock().await;
-        for (feed, articles) in feeds.iter().zip(articles) {
-            out.insert(feed.name, articles);
+        let current = registry.borrow_and_update().clone();
+        stops.retain(|(source, stop)| {
+            let registered = current.contains(source);
+            if !registered {
+                stop.send(true).ok();
+            }
+            registered
+        });
+        for source in &current.sources {
+            if stops.iter().any(|(other, _)| Arc::ptr_eq(other, source)) {
+                continue;
+            }
+            let (stop, stopped) = watch::channel(false);
+            stops.push((source.clone(), stop));
+            running.push(supervise(
+                source.clone(),
+                client,
+                store,
+                out,
+                current.clone(),
+                registry.clone(),
+                stopped,
+            ));
         }
-        drop(out);
-        sleep(Duration::from_secs(60 * 60)).await;
+        select!(
+            Some(()) = running.next() => {},
+            Ok(()) = registry.changed() => {},
+            _ = shutdown.changed() => {
+                for (_, stop) in &stops {
+                    stop.send(true).ok();
+                }
+                while running.next().await.is_some() {}
+                return Ok(());
+            }
+        );
     }
 }
+
+/// Restarts a source's scrape loop if it panics, until it is stopped.
+async fn supervise(
+    source: Arc<dyn Source>,
+    client: &Client,
+    store: Option<&Store>,
+    out: &Mutex<HashMap<String, Feed>>,
+    registry: Arc<Registry>,
+    current: watch::Receiver<Arc<Registry>>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    loop {
+        let scraped = AssertUnwindSafe(scrape_feed(
+            &*source,
+            client,
+            store,
+            out,
+            &registry,
+            &current,
+            shutdown.clone(),
+        ))
+        .catch_unwind()
+        .await;
+        if scraped.is_ok() {
+            return;
+        }
+        error!(feed = source.name(), "Scraper panicked, restarting");
+        select!(
+            _ = sleep(source.interval()) => {},
+            _ = shutdown.changed() => return,
+        );
+    }
+}
+
+/// Re-reads the config on SIGHUP or an edit through the admin API and replaces the registry,
+/// keeping the articles of every feed that is still configured.
+#[instrument(skip_all)]
+async fn reload(
+    config: Option<&str>,
+    registry: watch::Sender<Arc<Registry>>,
+    feeds: &Mutex<HashMap<String, Feed>>,
+    store: Option<&Store>,
+    public_url: Option<&str>,
+    edited: &Notify,
+    mut shutdown: watch::Receiver<bool>,
+) -> Result<()> {
+    let mut hangup = signal(SignalKind::hangup())?;
+    loop {
+        select!(
+            _ = hangup.recv() => {},
+            _ = edited.notified() => {},
+            _ = shutdown.changed() => return Ok(()),
+        );
+        if let Some(Err(error)) = tls::get().map(|certificates| certificates.refresh()) {
+            warn!(?error, "Failed to reload TLS certificate, keeping the current one");
+        }
+        let config = match config {
+            Some(config) => config,
+            None => {
+                warn!("Ignoring SIGHUP, as there is no config file to reload");
+                continue;
+            }
+        };
+        let (sources, virtual_feeds) = match load(config) {
+            Ok(loaded) => loaded,
+            Err(error) => {
+                warn!(?error, "Failed to reload config, keeping the current one");
+                continue;
+            }
+        };
+        let previous = registry.borrow().clone();
+        let next = Registry::new(
+            sources,
+            virtual_feeds,
+            Some(&previous),
+            public_url,
+            store.is_some(),
+        );
+        let mut feeds = feeds.lock().await;
+        // Virtual feeds are rebuilt from scratch, as their filters may have changed.
+        feeds.retain(|name, _| {
+            name == feed::COMBINED || next.sources.iter().any(|source| source.name() == name)
+        });
+        for source in &next.sources {
+            let channel = &next.channels[source.name()];
+            match feeds.entry(source.name().to_owned()) {
+                Entry::Occupied(mut entry) if !previous.contains(source) => {
+                    let articles = std::mem::take(&mut entry.get_mut().articles);
+                    // The icon is found when scraping, so it outlives the channel's config.
+                    let channel = Channel {
+                        icon: entry.get().channel.icon.clone(),
+                        ..channel.clone()
+                    };
+                    entry.insert(Feed::new(channel, articles));
+                }
+                Entry::Occupied(_) => {}
+                Entry::Vacant(entry) => match store.map(|store| store.load(source.name())) {
+                    Some(Ok(articles)) => {
+                        entry.insert(Feed::new(channel.clone(), articles));
+                    }
+                    Some(Err(error)) => warn!(?error, feed = source.name(), "Failed to load feed"),
+                    None => {}
+                },
+            }
+        }
+        combine(&mut feeds, &next);
+        drop(feeds);
+        let kept = next
+            .sources
+            .iter()
+            .filter(|source| previous.contains(source))
+            .count();
+        info!(
+            sources = next.sources.len(),
+            unchanged = kept,
+            "Reloaded config"
+        );
+        registry.send(next).ok();
+    }
+}
+
+#[instrument(
+    skip(feed, client, store, out, registry, current, shutdown),
+    fields(feed = feed.name())
+)]
+async fn scrape_feed(
+    feed: &dyn Source,
+    client: &Client,
+    store: Option<&Store>,
+    out: &Mutex<HashMap<String, Feed>>,
+    registry: &Registry,
+    current: &watch::Receiver<Arc<Registry>>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let (refresh, channels) = (&*registry.refresh[feed.name()], &registry.channels);
+    let last = match store.map(|store| store.last_scrape(feed.name())).transpose() {
+        Ok(last) => last.flatten(),
+        Err(error) => {
+            warn!(?error, "Failed to load last scrape");
+            None
+        }
+    };
+    let mut failures = last.as_ref().map_or(0, |last| last.failures);
+    let mut succeeded = last.as_ref().and_then(|last| last.succeeded);
+    let mut last_error = last.as_ref().and_then(|last| last.error.clone());
+    if let Some(succeeded) = succeeded {
+        metrics::scraped_at(feed.name(), succeeded);
+    }
+    if let Some((at, message)) = &last_error {
+        status::failed_at(feed.name(), *at, message.clone());
+    }
+    metrics::circuit(feed.name(), failures, failures >= CIRCUIT_THRESHOLD);
+    // Only a feed's first scrape loop since starting carries on from the last scrape, and only
+    // with its articles to serve meanwhile; one restarted by a reload has been reconfigured.
+    let resume = status::get(feed.name()).next_scrape.is_none()
+        && out.lock().await.contains_key(feed.name());
+    if let Some(last) = last.filter(|_| resume) {
+        let period = backoff(interval(feed, out).await, failures);
+        let stagger = feed.stagger();
+        let next = chrono::Duration::from_std(stagger.delay(feed.name(), period, last.at))
+            .ok()
+            .and_then(|delay| last.at.checked_add_signed(delay));
+        // Feeds that fell due while stopped are spread out rather than all scraped at once.
+        let delay = next
+            .and_then(|next| (next - Utc::now()).to_std().ok())
+            .unwrap_or_else(|| stagger.jitter(period));
+        info!(?delay, "Resuming schedule from last scrape");
+        if !wait(feed.name(), delay, refresh, &mut shutdown).await {
+            return;
+        }
+    }
+    let mut empty = false;
+    loop {
+        let mut paused = None;
+        let scraped = select!(
+            r = feed.fetch(client, store) => r,
+            _ = shutdown_timeout(shutdown.clone()) => {
+                warn!("Cancelled scrape in progress");
+                return;
+            }
+        );
+        let scraped = match scraped {
+            Ok(scraped) => {
+                if failures >= CIRCUIT_THRESHOLD {
+                    info!(failures, "Closing circuit after successful scrape");
+                    alert::send(client, Alert::new(feed.name(), Event::Recovered)).await;
+                }
+                failures = 0;
+                succeeded = Some(Utc::now());
+                scraped
+            }
+            Err(error) => match RateLimited::find(&error) {
+                Some(limited) => {
+                    let retry_after = limited.retry_after;
+                    warn!(status = %limited.status, ?retry_after, "Rate limited, pausing");
+                    metrics::RATE_LIMITED.with_label_values(&[feed.name()]).inc();
+                    paused = Some(limited.retry_after.max(MIN_PAUSE));
+                    None
+                }
+                None => {
+                    failures += 1;
+                    warn!(?error, failures, "Failed to scrape feed");
+                    status::failed(feed.name(), &error);
+                    last_error = Some((Utc::now(), error.to_string()));
+                    if failures == CIRCUIT_THRESHOLD {
+                        warn!(failures, "Opening circuit, backing off");
+                        let event = Event::Failing {
+                            failures,
+                            error: error.to_string(),
+                        };
+                        alert::send(client, Alert::new(feed.name(), event)).await;
+                    }
+                    None
+                }
+            },
+        };
+        metrics::circuit(feed.name(), failures, failures >= CIRCUIT_THRESHOLD);
+        if let Some(scraped) = scraped {
+            if scraped.articles.is_empty() && !empty {
+                warn!("Scrape returned no articles");
+                alert::send(client, Alert::new(feed.name(), Event::Empty)).await;
+            }
+            empty = scraped.articles.is_empty();
+            let mut out = out.lock().await;
+            // Without a previous scrape there is no telling which articles are new.
+            let (articles, added, changed) = match out.entry(feed.name().to_owned()) {
+                Entry::Occupied(entry) => {
+                    let updated = entry.into_mut();
+                    let previous = updated
+                        .articles
+                        .iter()
+                        .map(|article| article.id().to_owned())
+                        .collect::<HashSet<_>>();
+                    let iconed = updated.set_icon(scraped.icon);
+                    let changed = updated.update(scraped.articles, feed.retention()) || iconed;
+                    let added = updated
+                        .articles
+                        .iter()
+                        .filter(|article| !previous.contains(article.id()))
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    (updated.articles.clone(), Some(added), changed)
+                }
+                Entry::Vacant(entry) => (
+                    entry
+                        .insert(Feed::new(
+                            Channel {
+                                icon: scraped.icon,
+                                ..channels[feed.name()].clone()
+                            },
+                            scraped.articles,
+                        ))
+                        .articles
+                        .clone(),
+                    None,
+                    true,
+                ),
+            };
+            if changed {
+                // Virtual feeds are built from the current registry, which may have been reloaded
+                // since this loop started.
+                let current = current.borrow().clone();
+                let mut names = vec![feed.name(), feed::COMBINED];
+                names.extend(
+                    combine(&mut out, &current)
+                        .into_iter()
+                        .filter(|name| *name != feed::COMBINED),
+                );
+                let files = export::get().map(|export| export.files(&out, &names));
+                drop(out);
+                if let (Some(export), Some(files)) = (export::get(), files) {
+                    if let Err(error) = export.publish(client, &files).await {
+                        warn!(?error, "Failed to export feed");
+                    }
+                }
+                if let Err(error) = store.map_or(Ok(()), |store| store.save(feed.name(), &articles)) {
+                    warn!(?error, "Failed to save feed");
+                }
+                if added.as_ref().map_or(!articles.is_empty(), |added| !added.is_empty()) {
+                    let channels = names
+                        .iter()
+                        .filter_map(|name| current.channels.get(*name).or_else(|| channels.get(*name)))
+                        .collect::<Vec<_>>();
+                    let topics = topics(&channels);
+                    websub::publish(client, &topics).await;
+                }
+                if let Some(added) = added {
+                    push::send(client, feed.name(), &added).await;
+                    telegram::post(client, store, feed.name(), &added).await;
+                    activitypub::publish(client, store, feed.name(), &added).await;
+                }
+            } else {
+                // Storage, exports and subscribers already have exactly this feed.
+                trace!("Scraped articles unchanged");
+            }
+        } else if failures == 0 && paused.is_none() {
+            trace!("Feed unchanged");
+        }
+        let last = LastScrape {
+            at: Utc::now(),
+            succeeded,
+            failures,
+            error: last_error.clone(),
+        };
+        if let Err(error) = store.map_or(Ok(()), |store| store.record_scrape(feed.name(), &last)) {
+            warn!(?error, "Failed to record scrape");
+        }
+        // Scrapes keep to the feed's slot in each period, so sites aren't all hit at once.
+        let interval = interval(feed, out).await;
+        let delay = paused.unwrap_or_else(|| {
+            let period = backoff(interval, failures);
+            feed.stagger().delay(feed.name(), period, Utc::now())
+        });
+        if !wait(feed.name(), delay, refresh, &mut shutdown).await {
+            return;
+        }
+    }
+}
+
+/// The feed's interval, following how often it has been publishing if it adapts.
+async fn interval(feed: &dyn Source, out: &Mutex<HashMap<String, Feed>>) -> Duration {
+    match feed.adaptive() {
+        Some(adaptive) => {
+            let out = out.lock().await;
+            let dates = out.get(feed.name()).into_iter().flat_map(|feed| {
+                feed.articles
+                    .iter()
+                    .map(|article| article.date.with_timezone(&Utc))
+            });
+            let interval = adaptive.interval(dates, Utc::now());
+            trace!(?interval, "Adapted interval to posting rate");
+            interval
+        }
+        None => feed.interval(),
+    }
+}
+
+/// Waits out the delay before a feed's next scrape, or until a refresh is requested. Returns
+/// false on shutdown.
+async fn wait(
+    feed: &str,
+    delay: Duration,
+    refresh: &Notify,
+    shutdown: &mut watch::Receiver<bool>,
+) -> bool {
+    if let Some(next) = chrono::Duration::from_std(delay)
+        .ok()
+        .and_then(|delay| Utc::now().checked_add_signed(delay))
+    {
+        status::scheduled(feed, next);
+    }
+    select!(
+        _ = sleep(delay) => true,
+        _ = refresh.notified() => {
+            trace!("Manual refresh requested");
+            true
+        },
+        _ = shutdown.changed() => false,
+    )
+}
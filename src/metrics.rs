@@ -0,0 +1,160 @@
+use chrono::{DateTime, Utc};
+use prometheus::{
+    core::Collector, proto::Metric, register_gauge_vec, register_histogram_vec,
+    register_int_counter_vec, register_int_gauge_vec, Encoder, GaugeVec, HistogramVec,
+    IntCounterVec, IntGaugeVec, TextEncoder,
+};
+use std::sync::LazyLock;
+
+pub static SCRAPE_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "news_rss_scrape_duration_seconds",
+        "Time taken to scrape a feed",
+        &["feed"]
+    )
+    .unwrap()
+});
+
+pub static ARTICLES_FETCHED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "news_rss_articles_fetched_total",
+        "Articles successfully scraped",
+        &["feed"]
+    )
+    .unwrap()
+});
+
+pub static ARTICLE_FAILURES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "news_rss_article_failures_total",
+        "Articles that failed to scrape",
+        &["feed"]
+    )
+    .unwrap()
+});
+
+pub static HTTP_REQUESTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "news_rss_http_requests_total",
+        "HTTP requests served",
+        &["handler", "status"]
+    )
+    .unwrap()
+});
+
+pub static LAST_SUCCESS: LazyLock<GaugeVec> = LazyLock::new(|| {
+    register_gauge_vec!(
+        "news_rss_feed_last_success_timestamp_seconds",
+        "Unix time of the last successful scrape of a feed",
+        &["feed"]
+    )
+    .unwrap()
+});
+
+pub static STALENESS: LazyLock<GaugeVec> = LazyLock::new(|| {
+    register_gauge_vec!(
+        "news_rss_feed_staleness_seconds",
+        "Seconds since the last successful scrape of a feed",
+        &["feed"]
+    )
+    .unwrap()
+});
+
+pub static CONSECUTIVE_FAILURES: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "news_rss_feed_consecutive_failures",
+        "Consecutive failed scrapes of a feed",
+        &["feed"]
+    )
+    .unwrap()
+});
+
+pub static RATE_LIMITED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "news_rss_rate_limited_total",
+        "Scrapes paused because the source asked us to retry later",
+        &["feed"]
+    )
+    .unwrap()
+});
+
+pub static CIRCUIT_OPEN: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "news_rss_feed_circuit_open",
+        "Whether scraping of a feed is backing off after repeated failures",
+        &["feed"]
+    )
+    .unwrap()
+});
+
+pub fn scraped(feed: &str) {
+    scraped_at(feed, Utc::now());
+}
+
+pub fn scraped_at(feed: &str, at: DateTime<Utc>) {
+    LAST_SUCCESS
+        .with_label_values(&[feed])
+        .set(at.timestamp_millis() as f64 / 1000.0);
+}
+
+fn metric(collector: &impl Collector, feed: &str) -> Option<Metric> {
+    collector
+        .collect()
+        .iter()
+        .flat_map(|family| family.get_metric())
+        .find(|metric| {
+            metric
+                .get_label()
+                .iter()
+                .any(|label| label.name() == "feed" && label.value() == feed)
+        })
+        .cloned()
+}
+
+pub fn last_success(feed: &str) -> Option<f64> {
+    metric(&*LAST_SUCCESS, feed).map(|metric| metric.get_gauge().get_value())
+}
+
+pub fn consecutive_failures(feed: &str) -> u32 {
+    metric(&*CONSECUTIVE_FAILURES, feed).map_or(0, |metric| metric.get_gauge().get_value() as u32)
+}
+
+pub fn circuit_open(feed: &str) -> bool {
+    metric(&*CIRCUIT_OPEN, feed).is_some_and(|metric| metric.get_gauge().get_value() > 0.0)
+}
+
+pub fn average_duration(feed: &str) -> Option<f64> {
+    metric(&*SCRAPE_DURATION, feed)
+        .map(|metric| metric.get_histogram().clone())
+        .filter(|histogram| histogram.get_sample_count() > 0)
+        .map(|histogram| histogram.get_sample_sum() / histogram.get_sample_count() as f64)
+}
+
+pub fn circuit(feed: &str, failures: u32, open: bool) {
+    CONSECUTIVE_FAILURES
+        .with_label_values(&[feed])
+        .set(failures.into());
+    CIRCUIT_OPEN.with_label_values(&[feed]).set(open.into());
+}
+
+pub fn render() -> String {
+    let now = Utc::now().timestamp_millis() as f64 / 1000.0;
+    for family in Collector::collect(&*LAST_SUCCESS) {
+        for metric in family.get_metric() {
+            if let Some(feed) = metric
+                .get_label()
+                .iter()
+                .find(|label| label.name() == "feed")
+            {
+                STALENESS
+                    .with_label_values(&[feed.value()])
+                    .set(now - metric.get_gauge().get_value());
+            }
+        }
+    }
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&prometheus::gather(), &mut buffer)
+        .unwrap();
+    String::from_utf8(buffer).unwrap()
+}
@@ -0,0 +1,293 @@
+use crate::{
+    absolutize_urls, canonical, escape_html, metrics, render::Channel, retry, store::Store,
+    Adaptive, Article, Image, Retention, RetryPolicy, Scraped, Selectors, Source, Stagger,
+};
+use anyhow::*;
+use async_trait::async_trait;
+use atom_syndication::{Feed as AtomFeed, Text, TextType};
+use chrono::{DateTime, FixedOffset, Utc};
+use chrono_tz::Tz;
+use futures::{stream, StreamExt};
+use nipper::Document;
+use reqwest::{header::HeaderMap, Client, Url};
+use std::{borrow::Cow, time::Duration};
+use tracing::{instrument, trace, warn};
+
+#[derive(Debug)]
+pub struct Passthrough {
+    pub name: Cow<'static, str>,
+    pub description: Option<Cow<'static, str>>,
+    pub language: Option<Cow<'static, str>>,
+    pub link: Option<Cow<'static, str>>,
+    pub feed_url: Url,
+    pub body_selector: Selectors,
+    pub timezone: Tz,
+    pub interval: Duration,
+    pub concurrency: usize,
+    pub retry: RetryPolicy,
+    pub retention: Retention,
+    pub stagger: Stagger,
+    pub adaptive: Option<Adaptive>,
+    pub max_items: Option<usize>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    headline: String,
+    link: Url,
+    date: Option<DateTime<FixedOffset>>,
+    content: Option<String>,
+    author: Option<String>,
+    categories: Vec<String>,
+    image: Option<Image>,
+}
+
+fn html(text: &Text) -> String {
+    match text.r#type {
+        TextType::Text => format!("<p>{}</p>", escape_html(&text.value)),
+        TextType::Html | TextType::Xhtml => text.value.to_owned(),
+    }
+}
+
+fn rss_entries(channel: &rss::Channel, base: &Url) -> Vec<Entry> {
+    channel
+        .items()
+        .iter()
+        .filter_map(|item| {
+            Some(Entry {
+                headline: item.title().unwrap_or_default().trim().to_owned(),
+                link: canonical::strip_tracking(base.join(item.link()?.trim()).ok()?),
+                date: item
+                    .pub_date()
+                    .and_then(|date| DateTime::parse_from_rfc2822(date.trim()).ok()),
+                content: item
+                    .content()
+                    .or_else(|| item.description())
+                    .map(str::to_owned),
+                author: item
+                    .dublin_core_ext()
+                    .and_then(|dc| dc.creators().first())
+                    .map(String::as_str)
+                    .or_else(|| item.author())
+                    .map(str::to_owned),
+                categories: item
+                    .categories()
+                    .iter()
+                    .map(|category| category.name().to_owned())
+                    .collect(),
+                image: item
+                    .enclosure()
+                    .filter(|enclosure| enclosure.mime_type().starts_with("image/"))
+                    .and_then(|enclosure| {
+                        Some(Image {
+                            url: base.join(enclosure.url()).ok()?,
+                            mime_type: Some(enclosure.mime_type().to_owned()),
+                            length: enclosure.length().parse().ok().filter(|length| *length > 0),
+                        })
+                    }),
+            })
+        })
+        .collect()
+}
+
+fn atom_entries(feed: &AtomFeed, base: &Url) -> Vec<Entry> {
+    feed.entries()
+        .iter()
+        .filter_map(|entry| {
+            let link = entry
+                .links()
+                .iter()
+                .find(|link| link.rel() == "alternate")
+                .or_else(|| entry.links().first())?;
+            Some(Entry {
+                headline: entry.title().value.trim().to_owned(),
+                link: canonical::strip_tracking(base.join(link.href()).ok()?),
+                date: Some(*entry.published().unwrap_or_else(|| entry.updated())),
+                content: entry
+                    .content()
+                    .and_then(|content| content.value())
+                    .map(str::to_owned)
+                    .or_else(|| entry.summary().map(html)),
+                author: entry
+                    .authors()
+                    .first()
+                    .map(|author| author.name().to_owned()),
+                categories: entry
+                    .categories()
+                    .iter()
+                    .map(|category| category.term().to_owned())
+                    .collect(),
+                image: entry
+                    .links()
+                    .iter()
+                    .filter(|link| link.rel() == "enclosure")
+                    .filter(|link| {
+                        link.mime_type()
+                            .is_some_and(|mime| mime.starts_with("image/"))
+                    })
+                    .find_map(|link| {
+                        Some(Image {
+                            url: base.join(link.href()).ok()?,
+                            mime_type: link.mime_type().map(str::to_owned),
+                            length: link.length().and_then(|length| length.parse().ok()),
+                        })
+                    }),
+            })
+        })
+        .collect()
+}
+
+fn entries(text: &str, base: &Url) -> Result<Vec<Entry>> {
+    match rss::Channel::read_from(text.as_bytes()) {
+        Ok(channel) => Ok(rss_entries(&channel, base)),
+        Err(rss_error) => match AtomFeed::read_from(text.as_bytes()) {
+            Ok(feed) => Ok(atom_entries(&feed, base)),
+            Err(atom_error) => bail!(
+                "Failed to parse feed as RSS ({}) or Atom ({})",
+                rss_error,
+                atom_error
+            ),
+        },
+    }
+}
+
+impl Passthrough {
+    #[instrument(skip(self, client, store), fields(self.name))]
+    pub async fn get_articles(
+        &self,
+        client: &Client,
+        store: Option<&Store>,
+    ) -> Result<Option<Scraped>> {
+        let _timer = metrics::SCRAPE_DURATION
+            .with_label_values(&[&self.name])
+            .start_timer();
+        let (text, validators) = match retry::fetch_if_modified(
+            client,
+            self.feed_url.clone(),
+            self.retry,
+            &HeaderMap::new(),
+        )
+        .await?
+        {
+            Some(text) => text,
+            None => {
+                metrics::scraped(&self.name);
+                return Ok(None);
+            }
+        };
+        let entries = entries(&text, &self.feed_url)?;
+        validators.save().await;
+        let articles = stream::iter(
+            entries
+                .into_iter()
+                .map(|entry| self.expand(client, store, entry)),
+        )
+        .buffered(self.concurrency)
+        .collect::<Vec<_>>()
+        .await;
+        metrics::ARTICLES_FETCHED
+            .with_label_values(&[&self.name])
+            .inc_by(articles.len() as u64);
+        metrics::scraped(&self.name);
+        Ok(Some(Scraped {
+            articles,
+            failed: 0,
+            icon: None,
+        }))
+    }
+
+    #[instrument(skip(self, client, store, entry), fields(self.name, article = entry.link.as_str()))]
+    async fn expand(&self, client: &Client, store: Option<&Store>, entry: Entry) -> Article {
+        if let Some(stored) =
+            store.and_then(|store| store.get(&self.name, &entry.link).ok().flatten())
+        {
+            trace!("Using stored article");
+            return stored;
+        }
+        let (body, canonical) =
+            match retry::fetch(client, entry.link.clone(), self.retry, &HeaderMap::new()).await {
+                Ok(page) => {
+                    let document = Document::from(&page);
+                    let body = self
+                        .body_selector
+                        .select(|selector| document.select(selector));
+                    let body = if body.exists() {
+                        absolutize_urls(&body, &entry.link);
+                        Some(body.html().to_string())
+                    } else {
+                        trace!("Body selector matched nothing, keeping feed content");
+                        None
+                    };
+                    (body, canonical::canonical(&document, &entry.link))
+                }
+                Err(error) => {
+                    warn!(?error, "Failed to fetch article, keeping feed content");
+                    (None, None)
+                }
+            };
+        if let Some(canonical) = canonical.as_ref().filter(|canonical| **canonical != entry.link) {
+            // The article is stored under its canonical link, but found by the feed's.
+            if let Some(Err(error)) =
+                store.map(|store| store.alias(&self.name, &entry.link, canonical))
+            {
+                warn!(?error, "Failed to store the feed's link");
+            }
+        }
+        Article {
+            headline: entry.headline,
+            body: body.or(entry.content).unwrap_or_default(),
+            description: None,
+            language: None,
+            image: entry.image,
+            images: Vec::new(),
+            guid: None,
+            alternates: Vec::new(),
+            updated: None,
+            author: entry.author,
+            categories: entry.categories,
+            date: entry
+                .date
+                .map_or_else(Utc::now, |date| date.with_timezone(&Utc))
+                .with_timezone(&self.timezone),
+            link: canonical.unwrap_or(entry.link),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl Source for Passthrough {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    fn retention(&self) -> Retention {
+        self.retention
+    }
+
+    fn stagger(&self) -> Stagger {
+        self.stagger
+    }
+
+    fn adaptive(&self) -> Option<Adaptive> {
+        self.adaptive
+    }
+
+    fn channel(&self) -> Channel {
+        Channel {
+            description: self.description.as_deref().map(str::to_owned),
+            language: self.language.as_deref().map(str::to_owned),
+            link: self.link.as_deref().map(str::to_owned),
+            ttl: Some(self.interval),
+            max_items: self.max_items,
+            ..Channel::new(self.name())
+        }
+    }
+
+    async fn fetch(&self, client: &Client, store: Option<&Store>) -> Result<Option<Scraped>> {
+        self.get_articles(client, store).await
+    }
+}
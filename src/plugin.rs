@@ -0,0 +1,298 @@
+//! Scrapers loaded as WebAssembly modules, so third parties can ship site support without forking
+//! the crate. The host fetches, schedules, and generates feeds; a module only extracts from HTML.
+//!
+//! A module exports its `memory` and three functions:
+//!
+//! - `alloc(len: i32) -> i32` returns space for the host to write `len` bytes of input into.
+//! - `links(ptr: i32, len: i32) -> i64` is given the listing page's HTML, and returns a JSON array
+//!   of article links, which may be relative to the listing.
+//! - `extract(ptr: i32, len: i32) -> i64` is given an article's HTML, and returns a JSON object
+//!   with its `headline` and RFC 3339 `date`, and optionally its `body`, `description`, `author`,
+//!   `image`, and `categories`. Articles without a date are skipped.
+//!
+//! Both return their output's address in the high 32 bits and its length in the low 32 bits. Every
+//! call gets a fresh instance, with limited memory and fuel, so a module can't keep state between
+//! pages or stall a scrape.
+
+use crate::{
+    canonical, metrics, render::Channel, retry, store::Store, Adaptive, Article, Image, Retention,
+    RetryPolicy, Scraped, Source, Stagger,
+};
+use anyhow::*;
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset};
+use chrono_tz::Tz;
+use futures::{stream, StreamExt};
+use reqwest::{header::HeaderMap, Client, Url};
+use serde::Deserialize;
+use std::{borrow::Cow, convert::TryFrom, sync::LazyLock, time::Duration};
+use tracing::{instrument, trace, warn};
+use wasmi::{Engine, Linker, Memory, Module, StoreLimits, StoreLimitsBuilder};
+
+pub const DEFAULT_FUEL: u64 = 1_000_000_000;
+const MEMORY: usize = 64 * 1024 * 1024;
+
+static ENGINE: LazyLock<Engine> = LazyLock::new(|| {
+    let mut config = wasmi::Config::default();
+    config.consume_fuel(true);
+    Engine::new(&config)
+});
+
+#[derive(Debug)]
+pub struct Plugin {
+    pub name: Cow<'static, str>,
+    pub description: Option<Cow<'static, str>>,
+    pub language: Option<Cow<'static, str>>,
+    pub link: Option<Cow<'static, str>>,
+    pub url: Url,
+    pub timezone: Tz,
+    pub interval: Duration,
+    pub concurrency: usize,
+    pub retry: RetryPolicy,
+    pub retention: Retention,
+    pub stagger: Stagger,
+    pub adaptive: Option<Adaptive>,
+    pub max_items: Option<usize>,
+    /// Roughly how many instructions each call may run.
+    pub fuel: u64,
+    pub(crate) module: Module,
+}
+
+/// The article fields returned by a module's `extract`.
+#[derive(Debug, Deserialize)]
+pub struct Extracted {
+    pub headline: String,
+    pub date: Option<DateTime<FixedOffset>>,
+    #[serde(default)]
+    pub body: String,
+    pub description: Option<String>,
+    pub author: Option<String>,
+    pub image: Option<String>,
+    #[serde(default)]
+    pub categories: Vec<String>,
+}
+
+fn error(error: impl std::fmt::Display) -> Error {
+    anyhow!("{}", error)
+}
+
+impl Plugin {
+    /// A plugin with the defaults of a config-defined source, from a compiled module.
+    pub fn new(name: impl Into<Cow<'static, str>>, url: Url, wasm: &[u8]) -> Result<Plugin> {
+        let module = Module::new(&ENGINE, wasm)
+            .map_err(error)
+            .context("Invalid WASM module")?;
+        Ok(Plugin {
+            name: name.into(),
+            description: None,
+            language: None,
+            link: None,
+            url,
+            timezone: Tz::UTC,
+            interval: Duration::from_secs(60 * 60),
+            concurrency: crate::DEFAULT_CONCURRENCY,
+            retry: RetryPolicy::DEFAULT,
+            retention: Retention::DEFAULT,
+            stagger: Stagger::DEFAULT,
+            adaptive: None,
+            max_items: None,
+            fuel: DEFAULT_FUEL,
+            module,
+        })
+    }
+
+    fn call(&self, function: &str, input: &str) -> Result<String> {
+        let mut store = wasmi::Store::new(
+            &ENGINE,
+            StoreLimitsBuilder::new().memory_size(MEMORY).build(),
+        );
+        store.limiter(|limits: &mut StoreLimits| limits);
+        store.set_fuel(self.fuel).map_err(error)?;
+        let instance = Linker::new(&ENGINE)
+            .instantiate_and_start(&mut store, &self.module)
+            .map_err(error)
+            .context("Failed to instantiate module")?;
+        let memory: Memory = instance
+            .get_memory(&store, "memory")
+            .context("Module doesn't export its memory")?;
+        let length = i32::try_from(input.len()).context("Input is too long for the module")?;
+        let input_ptr = instance
+            .get_typed_func::<i32, i32>(&store, "alloc")
+            .map_err(error)?
+            .call(&mut store, length)
+            .map_err(error)
+            .context("alloc failed")?;
+        memory
+            .write(&mut store, input_ptr as u32 as usize, input.as_bytes())
+            .map_err(error)?;
+        let output = instance
+            .get_typed_func::<(i32, i32), i64>(&store, function)
+            .map_err(error)?
+            .call(&mut store, (input_ptr, length))
+            .map_err(error)
+            .with_context(|| format!("{} failed", function))?;
+        let mut buffer = vec![0; output as u32 as usize];
+        memory
+            .read(&store, (output as u64 >> 32) as usize, &mut buffer)
+            .map_err(error)?;
+        String::from_utf8(buffer).with_context(|| format!("{} returned invalid UTF-8", function))
+    }
+
+    /// Runs the module's `links` on a listing page.
+    pub fn links(&self, html: &str) -> Result<Vec<Url>> {
+        let links: Vec<String> = serde_json::from_str(&self.call("links", html)?)
+            .context("links returned invalid JSON")?;
+        links
+            .iter()
+            .map(|link| {
+                self.url
+                    .join(link)
+                    .map(canonical::strip_tracking)
+                    .with_context(|| format!("Invalid link {:?}", link))
+            })
+            .collect()
+    }
+
+    /// Runs the module's `extract` on an article page.
+    pub fn extract(&self, html: &str) -> Result<Extracted> {
+        serde_json::from_str(&self.call("extract", html)?).context("extract returned invalid JSON")
+    }
+
+    #[instrument(skip(self, client, store), fields(self.name, article = link.as_str()))]
+    async fn article(
+        &self,
+        client: &Client,
+        store: Option<&Store>,
+        link: Url,
+    ) -> Result<Option<Article>> {
+        if let Some(stored) = store.and_then(|store| store.get(&self.name, &link).ok().flatten()) {
+            trace!("Using stored article");
+            return Ok(Some(stored));
+        }
+        let page = retry::fetch(client, link.clone(), self.retry, &HeaderMap::new()).await?;
+        let extracted = self.extract(&page)?;
+        let date = match extracted.date {
+            Some(date) => date.with_timezone(&self.timezone),
+            None => {
+                trace!("Skipping article without a date");
+                return Ok(None);
+            }
+        };
+        Ok(Some(Article {
+            headline: extracted.headline,
+            body: extracted.body,
+            description: extracted.description,
+            language: None,
+            image: extracted
+                .image
+                .and_then(|image| link.join(&image).ok())
+                .map(|url| Image {
+                    url,
+                    mime_type: None,
+                    length: None,
+                }),
+            images: Vec::new(),
+            guid: None,
+            alternates: Vec::new(),
+            updated: None,
+            author: extracted.author,
+            categories: extracted.categories,
+            date,
+            link,
+        }))
+    }
+
+    #[instrument(skip(self, client, store), fields(self.name))]
+    pub async fn get_articles(
+        &self,
+        client: &Client,
+        store: Option<&Store>,
+    ) -> Result<Option<Scraped>> {
+        let _timer = metrics::SCRAPE_DURATION
+            .with_label_values(&[&self.name])
+            .start_timer();
+        let (listing, validators) =
+            match retry::fetch_if_modified(client, self.url.clone(), self.retry, &HeaderMap::new())
+                .await?
+            {
+                Some(listing) => listing,
+                None => {
+                    metrics::scraped(&self.name);
+                    return Ok(None);
+                }
+            };
+        let article_results = stream::iter(
+            self.links(&listing)?
+                .into_iter()
+                .map(|link| self.article(client, store, link)),
+        )
+        .buffered(self.concurrency)
+        .collect::<Vec<_>>()
+        .await;
+        let mut articles = Vec::new();
+        let mut failed = 0;
+        for result in article_results {
+            match result {
+                Ok(Some(article)) => articles.push(article),
+                Ok(None) => {}
+                Err(error) => {
+                    warn!(?error, "Failed to scrape article");
+                    failed += 1;
+                }
+            }
+        }
+        if failed == 0 {
+            validators.save().await;
+        }
+        metrics::ARTICLES_FETCHED
+            .with_label_values(&[&self.name])
+            .inc_by(articles.len() as u64);
+        metrics::ARTICLE_FAILURES
+            .with_label_values(&[&self.name])
+            .inc_by(failed as u64);
+        metrics::scraped(&self.name);
+        Ok(Some(Scraped {
+            articles,
+            failed,
+            icon: None,
+        }))
+    }
+}
+
+#[async_trait(?Send)]
+impl Source for Plugin {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    fn retention(&self) -> Retention {
+        self.retention
+    }
+
+    fn stagger(&self) -> Stagger {
+        self.stagger
+    }
+
+    fn adaptive(&self) -> Option<Adaptive> {
+        self.adaptive
+    }
+
+    fn channel(&self) -> Channel {
+        Channel {
+            description: self.description.as_deref().map(str::to_owned),
+            language: self.language.as_deref().map(str::to_owned),
+            link: self.link.as_deref().map(str::to_owned),
+            ttl: Some(self.interval),
+            max_items: self.max_items,
+            ..Channel::new(self.name())
+        }
+    }
+
+    async fn fetch(&self, client: &Client, store: Option<&Store>) -> Result<Option<Scraped>> {
+        self.get_articles(client, store).await
+    }
+}
@@ -0,0 +1,218 @@
+use crate::client::USER_AGENT;
+use anyhow::*;
+use regex::Regex;
+use reqwest::{Client, Url};
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock, OnceLock},
+    time::Duration,
+};
+use tokio::{
+    sync::Mutex,
+    time::{sleep_until, Instant},
+};
+use tracing::{instrument, trace, warn};
+
+const ROBOTS_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+// Sites whose robots.txt couldn't be fetched are disallowed, so they're retried sooner.
+const UNREACHABLE_TTL: Duration = Duration::from_secs(60 * 60);
+const MAX_CRAWL_DELAY: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Default)]
+struct Robots {
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+    unreachable: bool,
+}
+
+#[derive(Debug)]
+struct Rule {
+    allow: bool,
+    length: usize,
+    pattern: Regex,
+}
+
+#[derive(Debug, Default)]
+struct Site {
+    robots: Option<(Instant, Arc<Robots>)>,
+    next_request: Option<Instant>,
+}
+
+static SITES: LazyLock<Mutex<HashMap<String, Site>>> = LazyLock::new(Default::default);
+static AGENT: OnceLock<String> = OnceLock::new();
+
+/// Sets the User-Agent whose product token robots.txt groups are matched against.
+pub fn configure(user_agent: &str) {
+    AGENT.set(product_token(user_agent)).ok();
+}
+
+fn agent() -> &'static str {
+    AGENT.get_or_init(|| product_token(USER_AGENT))
+}
+
+/// The product token a User-Agent starts with, lowercased, like `news-rss` for
+/// `news-rss/0.1 (+https://…)`.
+pub fn product_token(user_agent: &str) -> String {
+    user_agent
+        .split(['/', ' '])
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
+
+/// Parses a `Crawl-delay` in seconds, ignoring ones that aren't a valid duration and capping long
+/// ones so a single site can't stall its scrapes indefinitely.
+pub fn crawl_delay(value: &str) -> Option<Duration> {
+    let delay = Duration::try_from_secs_f64(value.parse().ok()?).ok()?;
+    Some(delay.min(MAX_CRAWL_DELAY))
+}
+
+impl Rule {
+    fn new(allow: bool, pattern: &str) -> Option<Rule> {
+        let (path, anchored) = match pattern.strip_suffix('$') {
+            Some(path) => (path, true),
+            None => (pattern, false),
+        };
+        let regex = path
+            .split('*')
+            .map(regex::escape)
+            .collect::<Vec<_>>()
+            .join(".*");
+        let regex = format!("^{}{}", regex, if anchored { "$" } else { "" });
+        Some(Rule {
+            allow,
+            length: pattern.len(),
+            pattern: Regex::new(&regex).ok()?,
+        })
+    }
+}
+
+impl Robots {
+    /// RFC 9309 has crawlers assume everything is disallowed when robots.txt can't be reached.
+    fn unreachable() -> Robots {
+        Robots {
+            rules: Rule::new(false, "/").into_iter().collect(),
+            crawl_delay: None,
+            unreachable: true,
+        }
+    }
+
+    fn ttl(&self) -> Duration {
+        if self.unreachable {
+            UNREACHABLE_TTL
+        } else {
+            ROBOTS_TTL
+        }
+    }
+
+    fn parse(text: &str, agent: &str) -> Robots {
+        let mut specific = None;
+        let mut wildcard = None;
+        let mut agents = Vec::new();
+        let mut in_rules = false;
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim().to_ascii_lowercase(), value.trim()),
+                None => continue,
+            };
+            if key == "user-agent" {
+                if in_rules {
+                    agents.clear();
+                    in_rules = false;
+                }
+                agents.push(value.to_ascii_lowercase());
+                continue;
+            }
+            in_rules = true;
+            for listed in &agents {
+                let robots = match listed.as_str() {
+                    "*" => wildcard.get_or_insert_with(Robots::default),
+                    listed if listed == agent => specific.get_or_insert_with(Robots::default),
+                    _ => continue,
+                };
+                match key.as_str() {
+                    "allow" | "disallow" if !value.is_empty() => {
+                        robots.rules.extend(Rule::new(key == "allow", value))
+                    }
+                    "crawl-delay" => robots.crawl_delay = crawl_delay(value),
+                    _ => {}
+                }
+            }
+        }
+        specific.or(wildcard).unwrap_or_default()
+    }
+
+    fn allowed(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|rule| rule.pattern.is_match(path))
+            .max_by_key(|rule| (rule.length, rule.allow))
+            .is_none_or(|rule| rule.allow)
+    }
+}
+
+#[instrument(skip(client), fields(url = url.as_str()))]
+async fn robots(client: &Client, url: &Url) -> Robots {
+    let response = match client.get(url.join("/robots.txt").unwrap()).send().await {
+        Ok(response) => response,
+        Err(error) => {
+            warn!(?error, "Failed to fetch robots.txt");
+            return Robots::unreachable();
+        }
+    };
+    if response.status().is_server_error() {
+        warn!(status = ?response.status(), "Failed to fetch robots.txt");
+        return Robots::unreachable();
+    }
+    if !response.status().is_success() {
+        trace!(status = ?response.status(), "No robots.txt");
+        return Robots::default();
+    }
+    match response.text().await {
+        Ok(text) => Robots::parse(&text, agent()),
+        Err(error) => {
+            warn!(?error, "Failed to read robots.txt");
+            Robots::unreachable()
+        }
+    }
+}
+
+#[instrument(skip(client), fields(url = url.as_str()))]
+pub(crate) async fn wait(client: &Client, url: &Url) -> Result<()> {
+    let origin = url.origin().ascii_serialization();
+    let cached = SITES
+        .lock()
+        .await
+        .get(&origin)
+        .and_then(|site| site.robots.clone())
+        .filter(|(fetched, robots)| fetched.elapsed() < robots.ttl());
+    let robots = match cached {
+        Some((_, robots)) => robots,
+        None => {
+            let robots = Arc::new(robots(client, url).await);
+            SITES.lock().await.entry(origin.clone()).or_default().robots =
+                Some((Instant::now(), robots.clone()));
+            robots
+        }
+    };
+    let path = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_owned(),
+    };
+    ensure!(robots.allowed(&path), "{} is disallowed by robots.txt", url);
+    if let Some(delay) = robots.crawl_delay {
+        let next = {
+            let mut sites = SITES.lock().await;
+            let site = sites.entry(origin).or_default();
+            let next = site
+                .next_request
+                .map_or_else(Instant::now, |next| next.max(Instant::now()));
+            site.next_request = Some(next + delay);
+            next
+        };
+        trace!(?delay, "Waiting for crawl delay");
+        sleep_until(next).await;
+    }
+    Ok(())
+}
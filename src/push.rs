@@ -0,0 +1,79 @@
+use crate::Article;
+use anyhow::*;
+use futures::future::join_all;
+use reqwest::{
+    header::{HeaderMap, CONTENT_TYPE},
+    Client, Url,
+};
+use serde::Serialize;
+use std::sync::OnceLock;
+use tracing::{instrument, trace, warn};
+
+#[derive(Debug, Clone)]
+pub struct Push {
+    pub url: Url,
+    pub headers: HeaderMap,
+}
+
+#[derive(Debug, Serialize)]
+struct Payload<'a> {
+    feed: &'a str,
+    headline: &'a str,
+    link: &'a str,
+    guid: &'a str,
+    date: String,
+    author: Option<&'a str>,
+    categories: &'a [String],
+    image: Option<&'a str>,
+    body: &'a str,
+}
+
+static PUSH: OnceLock<Push> = OnceLock::new();
+
+pub fn configure(push: Push) {
+    PUSH.set(push).ok();
+}
+
+/// POSTs one JSON payload per newly discovered article.
+#[instrument(skip(client, articles), fields(articles = articles.len()))]
+pub async fn send(client: &Client, feed: &str, articles: &[Article]) {
+    let push = match PUSH.get() {
+        Some(push) => push,
+        None => return,
+    };
+    join_all(articles.iter().map(|article| async move {
+        let payload = Payload {
+            feed,
+            headline: &article.headline,
+            link: article.link.as_str(),
+            guid: article.id(),
+            date: article.date.to_rfc3339(),
+            author: article.author.as_deref(),
+            categories: &article.categories,
+            image: article.image.as_ref().map(|image| image.url.as_str()),
+            body: &article.body,
+        };
+        let sent = async {
+            client
+                .post(push.url.clone())
+                .headers(push.headers.clone())
+                .header(CONTENT_TYPE, "application/json")
+                .body(serde_json::to_string(&payload)?)
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok(())
+        };
+        match sent.await {
+            Ok(()) => trace!(link = article.link.as_str(), "Pushed article"),
+            Err::<_, Error>(error) => {
+                warn!(
+                    ?error,
+                    link = article.link.as_str(),
+                    "Failed to push article"
+                )
+            }
+        }
+    }))
+    .await;
+}
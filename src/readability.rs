@@ -0,0 +1,141 @@
+use nipper::{Document, Node, NodeId, Selection};
+use regex::Regex;
+use std::{collections::HashMap, sync::LazyLock};
+
+// Mostly the patterns of Arc90's original readability.
+static UNLIKELY: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        "(?i)banner|breadcrumb|combx|comment|community|cookie|disqus|extra|footer|header|menu|\
+         modal|nav|newsletter|pager|popup|promo|related|remark|rss|share|shoutbox|sidebar|social|\
+         sponsor|subscribe|ad-break|agegate",
+    )
+    .unwrap()
+});
+static MAYBE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new("(?i)article|body|column|content|main|shadow|story").unwrap());
+static POSITIVE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new("(?i)article|body|content|entry|hentry|h-entry|main|page|post|text|blog|story")
+        .unwrap()
+});
+static NEGATIVE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        "(?i)hidden|banner|combx|comment|com-|contact|foot|footer|footnote|masthead|media|meta|\
+         outbrain|promo|related|scroll|share|shoutbox|sidebar|skyscraper|sponsor|shopping|tags|\
+         tool|widget|ad-",
+    )
+    .unwrap()
+});
+
+/// Elements that never belong in an article body, removed from the chosen one.
+const JUNK: &str = "script, style, noscript, form, iframe, nav, aside, footer, button";
+/// Paragraphs shorter than this are too short to say where the article is.
+const MIN_PARAGRAPH: usize = 25;
+
+fn name(node: &Node) -> String {
+    node.node_name()
+        .map(|name| name.to_lowercase())
+        .unwrap_or_default()
+}
+
+fn class_and_id(node: &Node) -> String {
+    format!(
+        "{} {}",
+        node.attr("class").unwrap_or_default(),
+        node.attr("id").unwrap_or_default()
+    )
+}
+
+fn unlikely(node: &Node) -> bool {
+    let mut node = Some(node.clone());
+    while let Some(current) = node.filter(Node::is_element) {
+        let tag = name(&current);
+        if tag == "body" || tag == "html" {
+            return false;
+        }
+        let names = class_and_id(&current);
+        if matches!(tag.as_str(), "aside" | "nav" | "footer" | "form")
+            || UNLIKELY.is_match(&names) && !MAYBE.is_match(&names)
+        {
+            return true;
+        }
+        node = current.parent();
+    }
+    false
+}
+
+fn initial_score(node: &Node) -> f64 {
+    let score: f64 = match name(node).as_str() {
+        "div" | "article" | "section" | "main" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+    let names = class_and_id(node);
+    let weight = match (POSITIVE.is_match(&names), NEGATIVE.is_match(&names)) {
+        (true, false) => 25.0,
+        (false, true) => -25.0,
+        _ => 0.0,
+    };
+    score + weight
+}
+
+fn link_density(node: &Node) -> f64 {
+    let length = node.text().chars().count();
+    if length == 0 {
+        return 0.0;
+    }
+    let links = Selection::from(node.clone())
+        .select("a")
+        .iter()
+        .map(|link| link.text().chars().count())
+        .sum::<usize>();
+    links as f64 / length as f64
+}
+
+/// Picks the element most likely to hold an article's text, scoring each paragraph's parent
+/// and grandparent by the paragraph's length and commas, then discounting dense links. The
+/// selection is empty when no paragraph is long enough to go on.
+pub(crate) fn extract(document: &Document) -> Selection<'_> {
+    let mut candidates = HashMap::<NodeId, (Node, f64)>::new();
+    for paragraph in document.select("p, pre, td, blockquote").nodes() {
+        if unlikely(paragraph) {
+            continue;
+        }
+        let text = paragraph.text();
+        let length = text.trim().chars().count();
+        if length < MIN_PARAGRAPH {
+            continue;
+        }
+        let score = 1.0 + text.matches(',').count() as f64 + (length / 100).min(3) as f64;
+        let parent = paragraph.parent().filter(Node::is_element);
+        let grandparent = parent
+            .as_ref()
+            .and_then(Node::parent)
+            .filter(Node::is_element);
+        for (ancestor, share) in [(parent, 1.0), (grandparent, 0.5)] {
+            if let Some(ancestor) = ancestor {
+                candidates
+                    .entry(ancestor.id)
+                    .or_insert_with(|| (ancestor.clone(), initial_score(&ancestor)))
+                    .1 += score * share;
+            }
+        }
+    }
+    let best = candidates
+        .into_values()
+        .map(|(node, score)| {
+            let score = score * (1.0 - link_density(&node));
+            (node, score)
+        })
+        .max_by(|(_, a), (_, b)| a.total_cmp(b));
+    match best {
+        Some((node, _)) => {
+            let body = Selection::from(node);
+            let mut junk = body.select(JUNK);
+            junk.remove();
+            body
+        }
+        None => Selection::default(),
+    }
+}
@@ -0,0 +1,123 @@
+use news_rss::{
+    feed::{self, VirtualFeed},
+    images,
+    render::Channel,
+    websub, Source,
+};
+use std::{collections::HashMap, iter::once, sync::Arc};
+use tokio::sync::Notify;
+
+/// The sources being scraped and what is derived from them. A new registry replaces the old one
+/// when the config is reloaded, and is dropped once nothing is using it.
+#[derive(Debug)]
+pub struct Registry {
+    pub sources: Vec<Arc<dyn Source>>,
+    pub virtual_feeds: Vec<VirtualFeed>,
+    pub channels: HashMap<String, Channel>,
+    pub refresh: HashMap<String, Arc<Notify>>,
+    /// Where the server is reachable, which links are built from when it's known.
+    pub public_url: Option<String>,
+}
+
+fn fingerprint(source: &dyn Source) -> String {
+    format!("{:?}", source)
+}
+
+impl Registry {
+    /// Sources that are configured exactly as before keep their identity (and refresh handle),
+    /// so their scrape loops carry on undisturbed.
+    pub fn new(
+        sources: Vec<Box<dyn Source>>,
+        virtual_feeds: Vec<VirtualFeed>,
+        previous: Option<&Registry>,
+        public_url: Option<&str>,
+        archive: bool,
+    ) -> Arc<Registry> {
+        let sources = sources
+            .into_iter()
+            .map(|source| {
+                previous
+                    .and_then(|previous| {
+                        previous.sources.iter().find(|old| {
+                            old.name() == source.name()
+                                && fingerprint(&***old) == fingerprint(&*source)
+                        })
+                    })
+                    .cloned()
+                    .unwrap_or_else(|| Arc::from(source))
+            })
+            .collect::<Vec<_>>();
+        let refresh = sources
+            .iter()
+            .map(|source| {
+                let notify = previous
+                    .filter(|previous| previous.contains(source))
+                    .and_then(|previous| previous.refresh.get(source.name()).cloned())
+                    .unwrap_or_default();
+                (source.name().to_owned(), notify)
+            })
+            .collect();
+        let combined = Channel {
+            description: Some("Articles from every source".to_owned()),
+            ..Channel::new(feed::COMBINED)
+        };
+        let channels = sources
+            .iter()
+            .map(|source| (source.name(), source.channel()))
+            .chain(virtual_feeds.iter().map(|virtual_feed| {
+                let channel = Channel {
+                    description: virtual_feed.description.clone(),
+                    ..Channel::new(&virtual_feed.name)
+                };
+                (virtual_feed.name.as_str(), channel)
+            }))
+            .chain(once((feed::COMBINED, combined)))
+            .map(|(name, channel)| {
+                let self_link = public_url.map(|url| format!("{}/{}", url, name.to_lowercase()));
+                let archive = public_url
+                    .filter(|_| archive && sources.iter().any(|source| source.name() == name))
+                    .map(|url| format!("{}/archive/{}", url, name.to_lowercase()));
+                (
+                    name.to_owned(),
+                    Channel {
+                        self_link,
+                        archive,
+                        hub: websub::hub().map(|hub| hub.to_string()),
+                        images: public_url
+                            .filter(|_| images::get().is_some())
+                            .map(|url| format!("{}/img", url)),
+                        ..channel
+                    },
+                )
+            })
+            .collect();
+        Arc::new(Registry {
+            sources,
+            virtual_feeds,
+            channels,
+            refresh,
+            public_url: public_url.map(str::to_owned),
+        })
+    }
+
+    /// Every feed to list: the sources, then the virtual feeds, both in alphabetical order, then
+    /// the combined feed.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names = self.sources.iter().map(|source| source.name()).collect::<Vec<_>>();
+        names.sort_unstable();
+        let mut virtual_names = self
+            .virtual_feeds
+            .iter()
+            .map(|virtual_feed| virtual_feed.name.as_str())
+            .collect::<Vec<_>>();
+        virtual_names.sort_unstable();
+        names.extend(virtual_names);
+        names.push(feed::COMBINED);
+        names
+    }
+
+    /// Whether this exact source, rather than just one with the same name, is registered.
+    pub fn contains(&self, source: &Arc<dyn Source>) -> bool {
+        self.sources.iter().any(|other| Arc::ptr_eq(other, source))
+    }
+}
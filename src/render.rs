@@ -0,0 +1,878 @@
+use crate::{
+    escape_html,
+    images::{self, Thumbnail},
+    sanitise, Article, Feed, Image,
+};
+use atom_syndication::{
+    extension::{Extension as AtomExtension, ExtensionMap as AtomExtensionMap},
+    CategoryBuilder as AtomCategoryBuilder, ContentBuilder, EntryBuilder, FeedBuilder, Generator,
+    LinkBuilder, PersonBuilder, Text,
+};
+use bytes::Bytes;
+use chrono::{DateTime, FixedOffset, Utc};
+use nipper::Document;
+use rss::{
+    extension::{dublincore::DublinCoreExtensionBuilder, Extension, ExtensionMap},
+    CategoryBuilder, ChannelBuilder, EnclosureBuilder, GuidBuilder, ImageBuilder, ItemBuilder,
+};
+use serde::Serialize;
+use std::{
+    borrow::Cow,
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+use tracing::trace;
+
+const ATOM_NAMESPACE: &str = "http://www.w3.org/2005/Atom";
+const MEDIA_NAMESPACE: &str = "http://search.yahoo.com/mrss/";
+const HISTORY_NAMESPACE: &str = "http://purl.org/syndication/history/1.0";
+const SUMMARY_LENGTH: usize = 280;
+
+/// Where the server and exports put [`STYLESHEET`], which RSS and Atom feeds link to.
+pub const STYLESHEET_PATH: &str = "/feed.xsl";
+/// Turns RSS and Atom into a readable page with a note on subscribing, when opened in a browser.
+pub const STYLESHEET: &str = include_str!("feed.xsl");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Rss,
+    Atom,
+    Json,
+}
+
+/// Whether an `Accept-Encoding` header allows gzip. An explicit `gzip` entry wins over `*`, and
+/// either can be refused with `q=0`.
+pub fn accepts_gzip(accept_encoding: &str) -> bool {
+    let mut wildcard = None;
+    for coding in accept_encoding.split(',') {
+        let mut parameters = coding.split(';');
+        let name = parameters.next().unwrap_or_default().trim();
+        let quality = parameters
+            .filter_map(|parameter| parameter.trim().strip_prefix("q="))
+            .find_map(|quality| quality.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        if name.eq_ignore_ascii_case("gzip") || name.eq_ignore_ascii_case("x-gzip") {
+            return quality > 0.0;
+        }
+        if name == "*" {
+            wildcard = Some(quality > 0.0);
+        }
+    }
+    wildcard.unwrap_or(false)
+}
+
+impl Format {
+    pub const ALL: [Format; 3] = [Format::Rss, Format::Atom, Format::Json];
+
+    pub fn from_extension(extension: &str) -> Option<Format> {
+        Format::ALL
+            .iter()
+            .find(|format| format.extension() == extension)
+            .copied()
+    }
+
+    pub fn split(file: &str) -> Option<(&str, Format)> {
+        let (slug, extension) = file.rsplit_once('.')?;
+        Some((slug, Format::from_extension(extension)?))
+    }
+
+    /// Picks the format an `Accept` header prefers, by quality and then by order, falling back
+    /// to RSS when nothing listed is a feed type.
+    pub fn negotiate(accept: &str) -> Format {
+        let mut best = (0.0, Format::Rss);
+        for range in accept.split(',') {
+            let mut parameters = range.split(';');
+            let media_type = parameters.next().unwrap_or_default().trim();
+            let format = match media_type.to_ascii_lowercase().as_str() {
+                "application/rss+xml" | "application/xml" | "text/xml" => Format::Rss,
+                "application/atom+xml" => Format::Atom,
+                "application/feed+json" | "application/json" => Format::Json,
+                _ => continue,
+            };
+            let quality = parameters
+                .filter_map(|parameter| parameter.trim().strip_prefix("q="))
+                .find_map(|quality| quality.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            if quality > best.0 {
+                best = (quality, format);
+            }
+        }
+        best.1
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Rss => "rss",
+            Format::Atom => "atom",
+            Format::Json => "json",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Rss => "application/rss+xml",
+            Format::Atom => "application/atom+xml",
+            Format::Json => "application/feed+json",
+        }
+    }
+
+    pub fn render(self, channel: &Channel, feed: &[Article]) -> String {
+        let feed = &feed[..feed.len().min(channel.max_items.unwrap_or(usize::MAX))];
+        match self {
+            Format::Rss => styled(rss(channel, feed)),
+            Format::Atom => styled(atom(channel, feed)),
+            Format::Json => json(channel, feed),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Channel {
+    pub title: String,
+    /// Identifies the feed in its Atom id, so that stays put when the title changes.
+    pub slug: String,
+    pub description: Option<String>,
+    pub language: Option<String>,
+    pub link: Option<String>,
+    pub ttl: Option<Duration>,
+    pub self_link: Option<String>,
+    pub bump_pub_date: bool,
+    pub max_items: Option<usize>,
+    pub paging: Option<Paging>,
+    pub archive: Option<String>,
+    pub hub: Option<String>,
+    /// Where cached images are served from, to link them in place of the originals.
+    pub images: Option<String>,
+    /// The site's icon, found when scraping it.
+    pub icon: Option<Image>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Paging {
+    pub archive: String,
+    pub current: String,
+    pub page: usize,
+    pub pages: usize,
+}
+
+impl Channel {
+    pub fn new(title: impl Into<String>) -> Channel {
+        let title = title.into();
+        Channel {
+            slug: title.to_lowercase(),
+            title,
+            ..Channel::default()
+        }
+    }
+
+    fn description(&self) -> String {
+        self.description
+            .clone()
+            .unwrap_or_else(|| self.title.clone())
+    }
+
+    fn self_link(&self, format: Format) -> Option<String> {
+        match &self.paging {
+            Some(paging) => Some(paging.href(format, paging.page)),
+            None => self
+                .self_link
+                .as_ref()
+                .map(|link| format!("{}.{}", link, format.extension())),
+        }
+    }
+
+    // Only images already in the cache are served from it; the rest still load from their origin.
+    fn image_url(&self, image: &Image) -> String {
+        match (&self.images, images::get()) {
+            (Some(proxy), Some(images)) if images.contains(&image.url) => {
+                format!("{}/{}", proxy, images::key(&image.url))
+            }
+            _ => image.url.as_str().to_owned(),
+        }
+    }
+
+    fn thumbnail(&self, image: &Image) -> Option<(String, Thumbnail)> {
+        let proxy = self.images.as_ref()?;
+        let thumbnail = images::get()?.thumbnail(&image.url)?;
+        Some((
+            format!("{}/{}/thumbnail", proxy, images::key(&image.url)),
+            thumbnail,
+        ))
+    }
+
+    /// The configured language, or else the one most articles are in.
+    fn language(&self, feed: &[Article]) -> Option<String> {
+        if let Some(language) = &self.language {
+            return Some(language.clone());
+        }
+        let mut counts = HashMap::<_, usize>::new();
+        for language in feed.iter().filter_map(|article| article.language.as_deref()) {
+            *counts.entry(language).or_default() += 1;
+        }
+        counts
+            .into_iter()
+            .max_by_key(|&(language, count)| (count, Reverse(language)))
+            .map(|(language, _)| language.to_owned())
+    }
+
+    // Subscribers need a topic to subscribe to, so a hub is only advertised next to a self link.
+    fn hub(&self) -> Option<&str> {
+        self.hub.as_deref().filter(|_| self.self_link.is_some())
+    }
+
+    fn page_links(&self, format: Format) -> Vec<(&'static str, String)> {
+        let paging = match (&self.paging, &self.archive) {
+            (Some(paging), _) => paging,
+            (None, Some(archive)) => {
+                return vec![(
+                    "prev-archive",
+                    format!("{}.{}", archive, format.extension()),
+                )]
+            }
+            (None, None) => return Vec::new(),
+        };
+        // Pages count up from the oldest, so the first page is the newest one.
+        let mut links = vec![
+            ("first", paging.href(format, paging.pages)),
+            ("last", paging.href(format, 1)),
+        ];
+        if paging.page < paging.pages {
+            links.push(("previous", paging.href(format, paging.page + 1)));
+            links.push(("next-archive", paging.href(format, paging.page + 1)));
+        }
+        if paging.page > 1 {
+            links.push(("next", paging.href(format, paging.page - 1)));
+            links.push(("prev-archive", paging.href(format, paging.page - 1)));
+        }
+        links.push((
+            "current",
+            format!("{}.{}", paging.current, format.extension()),
+        ));
+        links
+    }
+}
+
+impl Paging {
+    /// Whether the page is full, so it will never change. Only the last, newest page fills up.
+    pub fn archived(&self) -> bool {
+        self.page < self.pages
+    }
+
+    fn href(&self, format: Format, page: usize) -> String {
+        format!("{}.{}?page={}", self.archive, format.extension(), page)
+    }
+}
+
+fn extension(name: &str, elements: Vec<Vec<(&str, String)>>) -> ExtensionMap {
+    let (prefix, local) = name.split_once(':').expect("Expected a prefixed name");
+    let elements = elements
+        .into_iter()
+        .map(|attrs| Extension {
+            name: name.to_owned(),
+            attrs: attrs
+                .into_iter()
+                .map(|(key, value)| (key.to_owned(), value))
+                .collect(),
+            ..Extension::default()
+        })
+        .collect::<Vec<_>>();
+    let mut extensions = ExtensionMap::new();
+    if !elements.is_empty() {
+        extensions
+            .entry(prefix.to_owned())
+            .or_default()
+            .insert(local.to_owned(), elements);
+    }
+    extensions
+}
+
+/// The body, followed by links to other sources' articles on the same story.
+fn content(article: &Article) -> Cow<'_, str> {
+    if article.alternates.is_empty() {
+        return Cow::Borrowed(&article.body);
+    }
+    let links = article
+        .alternates
+        .iter()
+        .map(|alternate| {
+            format!(
+                r#"<li><a href="{}">{}</a> ({})</li>"#,
+                escape_html(alternate.link.as_str()),
+                escape_html(&alternate.headline),
+                escape_html(&alternate.source)
+            )
+        })
+        .collect::<String>();
+    Cow::Owned(format!("{}<p>Also reported by:</p><ul>{}</ul>", article.body, links))
+}
+
+fn mime_type(image: &Image) -> String {
+    if let Some(mime_type) = &image.mime_type {
+        return mime_type.to_owned();
+    }
+    let extension = image
+        .url
+        .path()
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("png") => "image/png",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("svg") => "image/svg+xml",
+        Some("avif") => "image/avif",
+        _ => "image/jpeg",
+    }
+    .to_owned()
+}
+
+pub(crate) fn summary(body: &str) -> String {
+    let text = Document::from(body).select("body").text();
+    let words = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if words.chars().count() <= SUMMARY_LENGTH {
+        return words;
+    }
+    let cut = words
+        .char_indices()
+        .nth(SUMMARY_LENGTH)
+        .map_or(words.len(), |(index, _)| index);
+    let cut = words[..cut].rfind(' ').unwrap_or(cut);
+    format!("{}…", &words[..cut])
+}
+
+/// The first `count` sentences of an HTML body as plain text. A sentence ends at a word ending
+/// in `.`, `!` or `?` (perhaps inside quotes or brackets) that is followed by a capitalised word,
+/// which passes over decimals and mid-sentence abbreviations like "e.g. this". Bodies without any
+/// such ending fall back to `summary`, and text after the last ending is dropped.
+pub(crate) fn sentences(body: &str, count: usize) -> String {
+    let text = Document::from(body).select("body").text();
+    let words = text.split_whitespace().collect::<Vec<_>>();
+    let ends = |word: &str| {
+        word.trim_end_matches(['"', '\'', ')', ']', '’', '”'])
+            .ends_with(['.', '!', '?'])
+    };
+    let starts = |word: &str| {
+        word.trim_start_matches(['"', '\'', '(', '[', '‘', '“'])
+            .starts_with(|c: char| c.is_uppercase() || c.is_ascii_digit())
+    };
+    let mut found = 0;
+    let mut end = None;
+    for (index, word) in words.iter().enumerate() {
+        if ends(word) && words.get(index + 1).is_none_or(|next| starts(next)) {
+            found += 1;
+            end = Some(index);
+            if found == count {
+                break;
+            }
+        }
+    }
+    match end {
+        Some(end) => words[..=end].join(" "),
+        None => summary(body),
+    }
+}
+
+// The processing instruction has to follow the XML declaration, if there is one.
+fn styled(mut xml: String) -> String {
+    let instruction = format!(
+        r#"<?xml-stylesheet type="text/xsl" href="{}"?>"#,
+        STYLESHEET_PATH
+    );
+    let position = match xml.starts_with("<?xml ") {
+        true => xml.find("?>").map_or(0, |end| end + 2),
+        false => 0,
+    };
+    xml.insert_str(position, &instruction);
+    xml
+}
+
+// When the newest article changed rather than when the feed was rendered, so re-rendering an
+// unchanged feed (after a restart, say) doesn't look like an update to readers.
+fn built(feed: &[Article]) -> DateTime<FixedOffset> {
+    feed.iter()
+        .map(|article| article.updated.unwrap_or(article.date).fixed_offset())
+        .max()
+        .unwrap_or_else(|| Utc::now().fixed_offset())
+}
+
+fn media(channel: &Channel, article: &Article) -> ExtensionMap {
+    let mut media = extension(
+        "media:content",
+        article
+            .image
+            .iter()
+            .chain(&article.images)
+            .map(|image| {
+                vec![
+                    ("url", channel.image_url(image)),
+                    ("type", mime_type(image)),
+                    ("medium", "image".to_owned()),
+                ]
+            })
+            .collect(),
+    );
+    let thumbnail = article
+        .image
+        .as_ref()
+        .and_then(|image| channel.thumbnail(image));
+    let thumbnail = extension(
+        "media:thumbnail",
+        thumbnail
+            .into_iter()
+            .map(|(url, thumbnail)| {
+                vec![
+                    ("url", url),
+                    ("width", thumbnail.width.to_string()),
+                    ("height", thumbnail.height.to_string()),
+                ]
+            })
+            .collect(),
+    );
+    for (prefix, elements) in thumbnail {
+        media.entry(prefix).or_default().extend(elements);
+    }
+    media
+}
+
+pub fn rss(channel: &Channel, feed: &[Article]) -> String {
+    let items = feed
+        .iter()
+        .map(|article| {
+            trace!(?article, "Generating article RSS");
+            ItemBuilder::default()
+                .title(article.headline.to_owned())
+                .guid(
+                    GuidBuilder::default()
+                        .value(article.id().to_owned())
+                        .permalink(article.guid.is_none())
+                        .build()
+                        .unwrap(),
+                )
+                .link(article.link.as_str().to_owned())
+                .pub_date(
+                    article
+                        .updated
+                        .filter(|_| channel.bump_pub_date)
+                        .unwrap_or(article.date)
+                        .to_rfc2822(),
+                )
+                .description(article.summary().into_owned())
+                .content(content(article).into_owned())
+                .enclosure(article.image.as_ref().map(|image| {
+                    EnclosureBuilder::default()
+                        .url(channel.image_url(image))
+                        .length(image.length.unwrap_or_default().to_string())
+                        .mime_type(mime_type(image))
+                        .build()
+                        .unwrap()
+                }))
+                .extensions(media(channel, article))
+                .categories(
+                    article
+                        .categories
+                        .iter()
+                        .map(|category| {
+                            CategoryBuilder::default()
+                                .name(category.to_owned())
+                                .build()
+                                .unwrap()
+                        })
+                        .collect::<Vec<_>>(),
+                )
+                .dublin_core_ext((article.author.is_some() || article.language.is_some()).then(|| {
+                    DublinCoreExtensionBuilder::default()
+                        .creators(article.author.iter().cloned().collect::<Vec<_>>())
+                        .languages(article.language.iter().cloned().collect::<Vec<_>>())
+                        .build()
+                        .unwrap()
+                }))
+                .build()
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    let mut namespaces = HashMap::new();
+    if channel.self_link(Format::Rss).is_some() {
+        namespaces.insert("atom".to_owned(), ATOM_NAMESPACE.to_owned());
+    }
+    if feed
+        .iter()
+        .any(|article| article.image.is_some() || !article.images.is_empty())
+    {
+        namespaces.insert("media".to_owned(), MEDIA_NAMESPACE.to_owned());
+    }
+    let extensions = extension(
+        "atom:link",
+        channel
+            .self_link(Format::Rss)
+            .map(|href| {
+                vec![
+                    ("href", href),
+                    ("rel", "self".to_owned()),
+                    ("type", Format::Rss.content_type().to_owned()),
+                ]
+            })
+            .into_iter()
+            .chain(
+                channel
+                    .hub()
+                    .map(|hub| vec![("href", hub.to_owned()), ("rel", "hub".to_owned())]),
+            )
+            .chain(
+                channel
+                    .page_links(Format::Rss)
+                    .into_iter()
+                    .map(|(rel, href)| vec![("href", href), ("rel", rel.to_owned())]),
+            )
+            .collect(),
+    );
+    ChannelBuilder::default()
+        .title(channel.title.to_owned())
+        .description(channel.description())
+        .link(channel.link.clone().unwrap_or_default())
+        .language(channel.language(feed))
+        .ttl(channel.ttl.map(|ttl| (ttl.as_secs() / 60).to_string()))
+        .image(channel.icon.as_ref().map(|icon| {
+            ImageBuilder::default()
+                .url(channel.image_url(icon))
+                .title(channel.title.to_owned())
+                .link(channel.link.clone().unwrap_or_default())
+                .build()
+                .unwrap()
+        }))
+        .last_build_date(built(feed).to_rfc2822())
+        .generator("news-rss".to_owned())
+        .namespaces(namespaces)
+        .extensions(extensions)
+        .items(items)
+        .build()
+        .unwrap()
+        .to_string()
+}
+
+pub fn atom(channel: &Channel, feed: &[Article]) -> String {
+    let entries = feed
+        .iter()
+        .map(|article| {
+            trace!(?article, "Generating article Atom");
+            EntryBuilder::default()
+                .title(article.headline.to_owned())
+                .id(article.id())
+                .updated(article.updated.unwrap_or(article.date).fixed_offset())
+                .published(Some(article.date.fixed_offset()))
+                .authors(
+                    article
+                        .author
+                        .iter()
+                        .map(|author| PersonBuilder::default().name(author.to_owned()).build())
+                        .collect::<Vec<_>>(),
+                )
+                .links(
+                    Some(LinkBuilder::default().href(article.link.as_str()).build())
+                        .into_iter()
+                        .chain(article.image.iter().chain(&article.images).map(|image| {
+                            LinkBuilder::default()
+                                .href(channel.image_url(image))
+                                .rel("enclosure")
+                                .mime_type(Some(mime_type(image)))
+                                .length(image.length.map(|length| length.to_string()))
+                                .build()
+                        }))
+                        .chain(article.alternates.iter().map(|alternate| {
+                            LinkBuilder::default()
+                                .href(alternate.link.as_str())
+                                .rel("related")
+                                .title(Some(format!("{}: {}", alternate.source, alternate.headline)))
+                                .build()
+                        }))
+                        .collect::<Vec<_>>(),
+                )
+                .categories(
+                    article
+                        .categories
+                        .iter()
+                        .map(|category| {
+                            AtomCategoryBuilder::default()
+                                .term(category.to_owned())
+                                .build()
+                        })
+                        .collect::<Vec<_>>(),
+                )
+                .summary(Some(Text {
+                    lang: article.language.clone(),
+                    ..Text::plain(article.summary().into_owned())
+                }))
+                .content(Some(
+                    ContentBuilder::default()
+                        .value(Some(content(article).into_owned()))
+                        .content_type(Some("html".to_owned()))
+                        .lang(article.language.clone())
+                        .build(),
+                ))
+                .build()
+        })
+        .collect::<Vec<_>>();
+    let updated = built(feed);
+    let links = channel
+        .link
+        .iter()
+        .map(|href| LinkBuilder::default().href(href).rel("alternate").build())
+        .chain(channel.self_link(Format::Atom).map(|href| {
+            LinkBuilder::default()
+                .href(href)
+                .rel("self")
+                .mime_type(Some(Format::Atom.content_type().to_owned()))
+                .build()
+        }))
+        .chain(
+            channel
+                .hub()
+                .map(|hub| LinkBuilder::default().href(hub).rel("hub").build()),
+        )
+        .chain(
+            channel
+                .page_links(Format::Atom)
+                .into_iter()
+                .map(|(rel, href)| LinkBuilder::default().href(href).rel(rel).build()),
+        )
+        .collect::<Vec<_>>();
+    let mut namespaces = BTreeMap::new();
+    let mut extensions = AtomExtensionMap::new();
+    if channel.paging.as_ref().is_some_and(Paging::archived) {
+        namespaces.insert("fh".to_owned(), HISTORY_NAMESPACE.to_owned());
+        extensions.entry("fh".to_owned()).or_default().insert(
+            "archive".to_owned(),
+            vec![AtomExtension {
+                name: "fh:archive".to_owned(),
+                ..AtomExtension::default()
+            }],
+        );
+    }
+    FeedBuilder::default()
+        .title(channel.title.as_str())
+        .id(format!("urn:news-rss:{}", sanitise(&channel.slug)))
+        .updated(updated)
+        .subtitle(channel.description.as_deref().map(Text::plain))
+        .lang(channel.language(feed))
+        .icon(channel.icon.as_ref().map(|icon| channel.image_url(icon)))
+        .links(links)
+        .generator(Some(Generator {
+            value: "news-rss".to_owned(),
+            ..Generator::default()
+        }))
+        .entries(entries)
+        .namespaces(namespaces)
+        .extensions(extensions)
+        .build()
+        .to_string()
+}
+
+#[derive(Serialize)]
+struct JsonFeed<'a> {
+    version: &'static str,
+    title: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    home_page_url: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    feed_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    language: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    favicon: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    hubs: Vec<JsonHub<'a>>,
+    items: Vec<JsonItem<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonHub<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    url: &'a str,
+}
+
+#[derive(Serialize)]
+struct JsonItem<'a> {
+    id: &'a str,
+    url: &'a str,
+    title: &'a str,
+    content_html: Cow<'a, str>,
+    summary: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    language: Option<&'a str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    authors: Vec<JsonAuthor<'a>>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    tags: &'a [String],
+    date_published: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    date_modified: Option<String>,
+}
+
+#[derive(Serialize)]
+struct JsonAuthor<'a> {
+    name: &'a str,
+}
+
+pub fn json(channel: &Channel, feed: &[Article]) -> String {
+    let items = feed
+        .iter()
+        .map(|article| {
+            trace!(?article, "Generating article JSON");
+            JsonItem {
+                id: article.id(),
+                url: article.link.as_str(),
+                title: &article.headline,
+                content_html: content(article),
+                summary: article.summary().into_owned(),
+                image: article.image.as_ref().map(|image| channel.image_url(image)),
+                language: article.language.as_deref(),
+                authors: article
+                    .author
+                    .iter()
+                    .map(|name| JsonAuthor { name })
+                    .collect(),
+                tags: &article.categories,
+                date_published: article.date.to_rfc3339(),
+                date_modified: article.updated.map(|updated| updated.to_rfc3339()),
+            }
+        })
+        .collect();
+    serde_json::to_string(&JsonFeed {
+        version: "https://jsonfeed.org/version/1.1",
+        title: &channel.title,
+        home_page_url: channel.link.as_deref(),
+        feed_url: channel.self_link(Format::Json),
+        next_url: channel
+            .page_links(Format::Json)
+            .into_iter()
+            .find(|(rel, _)| *rel == "next")
+            .map(|(_, href)| href),
+        description: channel.description.as_deref(),
+        language: channel.language(feed),
+        favicon: channel.icon.as_ref().map(|icon| channel.image_url(icon)),
+        hubs: channel
+            .hub()
+            .map(|url| JsonHub {
+                kind: "WebSub",
+                url,
+            })
+            .into_iter()
+            .collect(),
+        items,
+    })
+    .unwrap()
+}
+
+pub fn opml(base: &str, names: &[&str]) -> String {
+    let groups = [("RSS", Format::Rss), ("Atom", Format::Atom)]
+        .iter()
+        .map(|(title, format)| {
+            let outlines = names
+                .iter()
+                .map(|name| {
+                    format!(
+                        r#"<outline type="{0}" text="{1}" title="{1}" xmlUrl="{2}/{3}.{0}"/>"#,
+                        format.extension(),
+                        escape_html(name),
+                        escape_html(base),
+                        escape_html(&name.to_lowercase()),
+                    )
+                })
+                .collect::<String>();
+            format!(
+                r#"<outline text="{0}" title="{0}">{1}</outline>"#,
+                title, outlines
+            )
+        })
+        .collect::<String>();
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?><opml version="2.0"><head><title>news-rss</title></head><body>{}</body></opml>"#,
+        groups
+    )
+}
+
+pub fn index(feeds: &[(&str, Option<&Feed>)]) -> String {
+    let rows = feeds
+        .iter()
+        .map(|(name, feed)| {
+            let slug = escape_html(&name.to_lowercase());
+            let links = Format::ALL
+                .iter()
+                .map(|format| format!(r#"<a href="/{}.{1}">{1}</a>"#, slug, format.extension()))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let (updated, count) = match feed {
+                Some(feed) => (feed.updated.to_rfc2822(), feed.articles.len().to_string()),
+                None => ("never".to_owned(), "0".to_owned()),
+            };
+            format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                escape_html(name),
+                links,
+                updated,
+                count
+            )
+        })
+        .collect::<String>();
+    format!(
+        r#"<!DOCTYPE html><html><head><meta charset="utf-8"><title>news-rss</title></head><body><h1>news-rss</h1><table><tr><th>Feed</th><th>Formats</th><th>Updated</th><th>Items</th></tr>{}</table><p><a href="/opml">OPML</a></p></body></html>"#,
+        rows
+    )
+}
+
+pub fn digest(title: &str, sections: &[(&str, Vec<Article>)]) -> String {
+    let sections = sections
+        .iter()
+        .map(|(name, articles)| {
+            let items = articles
+                .iter()
+                .map(|article| {
+                    format!(
+                        r#"<li><a href="{}">{}</a><br>{}</li>"#,
+                        escape_html(article.link.as_str()),
+                        escape_html(&article.headline),
+                        escape_html(&article.summary())
+                    )
+                })
+                .collect::<String>();
+            format!("<h2>{}</h2><ul>{}</ul>", escape_html(name), items)
+        })
+        .collect::<String>();
+    format!(
+        r#"<!DOCTYPE html><html><head><meta charset="utf-8"><title>{0}</title></head><body><h1>{0}</h1>{1}</body></html>"#,
+        escape_html(title),
+        sections
+    )
+}
+
+#[derive(Debug, Clone)]
+pub struct Rendered {
+    rss: Bytes,
+    atom: Bytes,
+    json: Bytes,
+}
+
+impl Rendered {
+    pub fn new(channel: &Channel, articles: &[Article]) -> Rendered {
+        Rendered {
+            rss: Format::Rss.render(channel, articles).into(),
+            atom: Format::Atom.render(channel, articles).into(),
+            json: Format::Json.render(channel, articles).into(),
+        }
+    }
+
+    pub fn get(&self, format: Format) -> Bytes {
+        match format {
+            Format::Rss => self.rss.clone(),
+            Format::Atom => self.atom.clone(),
+            Format::Json => self.json.clone(),
+        }
+    }
+}
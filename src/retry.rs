@@ -0,0 +1,319 @@
+use crate::{cache, encoding, politeness};
+use anyhow::*;
+use rand::Rng;
+use chrono::{DateTime, Utc};
+use reqwest::{
+    header::{
+        HeaderMap, HeaderValue, CONTENT_LENGTH, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE,
+        IF_NONE_MATCH, LAST_MODIFIED, RETRY_AFTER,
+    },
+    Client, StatusCode, Url,
+};
+use std::{collections::HashMap, fmt, sync::LazyLock, time::Duration};
+use tokio::{sync::Mutex, time::sleep};
+use tracing::{instrument, trace, warn};
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub const DEFAULT: RetryPolicy = RetryPolicy {
+        attempts: 3,
+        base_delay: Duration::from_secs(1),
+        max_delay: Duration::from_secs(60),
+    };
+
+    fn delay(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.max_delay);
+        delay.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::DEFAULT
+    }
+}
+
+/// A source answered `429 Too Many Requests` or `503 Service Unavailable` with a `Retry-After`
+/// longer than retrying would wait, so its scraper should pause rather than fail.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimited {
+    pub status: StatusCode,
+    pub retry_after: Duration,
+}
+
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Rate limited with {}, retry after {}s",
+            self.status,
+            self.retry_after.as_secs()
+        )
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+impl RateLimited {
+    /// The rate limit behind an error, however much context it has been given since.
+    pub fn find(error: &Error) -> Option<&RateLimited> {
+        error.chain().find_map(|cause| cause.downcast_ref())
+    }
+
+    /// The pause a response asks for, if it is a rate limit with a `Retry-After`.
+    pub fn from_response(status: StatusCode, headers: &HeaderMap) -> Option<RateLimited> {
+        if status != StatusCode::TOO_MANY_REQUESTS && status != StatusCode::SERVICE_UNAVAILABLE {
+            return None;
+        }
+        let retry_after = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+        // Either a number of seconds or an HTTP date.
+        let retry_after = match retry_after.parse().ok() {
+            Some(seconds) => Duration::from_secs(seconds),
+            None => (DateTime::parse_from_rfc2822(retry_after).ok()?.with_timezone(&Utc) - Utc::now())
+                .to_std()
+                .unwrap_or_default(),
+        };
+        Some(RateLimited {
+            status,
+            retry_after,
+        })
+    }
+}
+
+fn retryable(error: &Error) -> bool {
+    error.downcast_ref::<reqwest::Error>().is_some_and(|error| {
+        error.is_timeout()
+            || error.is_connect()
+            || error.status().is_some_and(|status| {
+                status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+            })
+    })
+}
+
+#[derive(Debug, Clone, Default)]
+struct Validators {
+    etag: Option<HeaderValue>,
+    last_modified: Option<HeaderValue>,
+}
+
+impl Validators {
+    fn new(headers: &HeaderMap) -> Validators {
+        Validators {
+            etag: headers.get(ETAG).cloned(),
+            last_modified: headers.get(LAST_MODIFIED).cloned(),
+        }
+    }
+}
+
+enum Fetched {
+    Modified(String, HeaderMap),
+    NotModified(HeaderMap),
+}
+
+static VALIDATORS: LazyLock<Mutex<HashMap<Url, Validators>>> = LazyLock::new(Default::default);
+
+/// Validators of a changed page, which are only saved once it's been scraped: a scrape that fails
+/// after the fetch needs the whole page again next time, not a 304.
+#[must_use]
+pub(crate) struct PendingValidators {
+    url: Url,
+    validators: Validators,
+}
+
+impl PendingValidators {
+    pub(crate) async fn save(self) {
+        VALIDATORS.lock().await.insert(self.url, self.validators);
+    }
+}
+
+async fn try_fetch(
+    client: &Client,
+    url: Url,
+    headers: &HeaderMap,
+    validators: &Validators,
+) -> Result<Fetched> {
+    let mut request = client.get(url).headers(headers.clone());
+    if let Some(etag) = &validators.etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = &validators.last_modified {
+        request = request.header(IF_MODIFIED_SINCE, last_modified);
+    }
+    let response = request.send().await?;
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(Fetched::NotModified(response.headers().clone()));
+    }
+    if let Some(limited) = RateLimited::from_response(response.status(), response.headers()) {
+        return Err(limited.into());
+    }
+    let response = response.error_for_status()?;
+    let headers = response.headers().clone();
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned);
+    let bytes = response.bytes().await?;
+    Ok(Fetched::Modified(
+        encoding::decode(&bytes, content_type.as_deref()),
+        headers,
+    ))
+}
+
+async fn fetch_with(
+    client: &Client,
+    url: Url,
+    policy: RetryPolicy,
+    headers: &HeaderMap,
+    validators: &Validators,
+) -> Result<Fetched> {
+    politeness::wait(client, &url).await?;
+    let mut attempt = 1;
+    loop {
+        match try_fetch(client, url.clone(), headers, validators).await {
+            Ok(fetched) => return Ok(fetched),
+            Err(error) if attempt < policy.attempts => {
+                // A short enough Retry-After is waited out like any other retry.
+                let delay = match RateLimited::find(&error) {
+                    Some(limited) if limited.retry_after <= policy.max_delay => limited.retry_after,
+                    Some(_) => return Err(error),
+                    None if retryable(&error) => policy.delay(attempt),
+                    None => return Err(error),
+                };
+                warn!(?error, attempt, ?delay, "Retrying request");
+                sleep(delay).await;
+                attempt += 1;
+            }
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+#[instrument(skip(client, policy, headers), fields(url = url.as_str()))]
+pub(crate) async fn fetch(
+    client: &Client,
+    url: Url,
+    policy: RetryPolicy,
+    headers: &HeaderMap,
+) -> Result<String> {
+    match fetch_with(client, url, policy, headers, &Validators::default()).await? {
+        Fetched::Modified(text, _) => Ok(text),
+        Fetched::NotModified(_) => bail!("Unexpected 304 Not Modified"),
+    }
+}
+
+/// Like `fetch`, but goes through the HTTP cache when one is configured: fresh pages aren't
+/// requested at all, and stale ones are revalidated with their stored validators.
+#[instrument(skip(client, policy, headers), fields(url = url.as_str()))]
+pub(crate) async fn fetch_cached(
+    client: &Client,
+    url: Url,
+    policy: RetryPolicy,
+    headers: &HeaderMap,
+) -> Result<String> {
+    let cache = match cache::get() {
+        Some(cache) => cache,
+        None => return fetch(client, url, policy, headers).await,
+    };
+    let cached = cache.get(&url);
+    if let Some(cached) = cached.as_ref().filter(|cached| cached.fresh()) {
+        trace!("Using cached page");
+        return Ok(cached.body.clone());
+    }
+    let validators = cached
+        .as_ref()
+        .map(|cached| Validators {
+            etag: cached.etag(),
+            last_modified: cached.last_modified(),
+        })
+        .unwrap_or_default();
+    match (fetch_with(client, url.clone(), policy, headers, &validators).await?, cached) {
+        (Fetched::Modified(text, response), _) => {
+            cache.put(&url, &response, text.clone());
+            Ok(text)
+        }
+        (Fetched::NotModified(response), Some(cached)) => {
+            trace!("Cached page not modified");
+            cache.revalidated(&url, &cached, &response);
+            Ok(cached.body)
+        }
+        (Fetched::NotModified(_), None) => bail!("Unexpected 304 Not Modified"),
+    }
+}
+
+#[instrument(skip(client, policy, headers), fields(url = url.as_str()))]
+pub(crate) async fn fetch_if_modified(
+    client: &Client,
+    url: Url,
+    policy: RetryPolicy,
+    headers: &HeaderMap,
+) -> Result<Option<(String, PendingValidators)>> {
+    let validators = VALIDATORS
+        .lock()
+        .await
+        .get(&url)
+        .cloned()
+        .unwrap_or_default();
+    match fetch_with(client, url.clone(), policy, headers, &validators).await? {
+        Fetched::Modified(text, response) => Ok(Some((
+            text,
+            PendingValidators {
+                url,
+                validators: Validators::new(&response),
+            },
+        ))),
+        Fetched::NotModified(_) => {
+            trace!("Not modified");
+            Ok(None)
+        }
+    }
+}
+
+#[instrument(skip(client), fields(url = url.as_str()))]
+pub(crate) async fn probe(client: &Client, url: &Url) -> Result<(Option<String>, Option<u64>)> {
+    politeness::wait(client, url).await?;
+    let response = client.head(url.clone()).send().await?.error_for_status()?;
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value: &HeaderValue| value.to_str().ok())
+            .map(str::to_owned)
+    };
+    Ok((
+        header(CONTENT_TYPE),
+        header(CONTENT_LENGTH).and_then(|length| length.parse().ok()),
+    ))
+}
+
+/// Downloads a file whole, with its content type, refusing anything over `limit` bytes.
+#[instrument(skip(client), fields(url = url.as_str()))]
+pub(crate) async fn download(client: &Client, url: &Url, limit: usize) -> Result<(Option<String>, Vec<u8>)> {
+    politeness::wait(client, url).await?;
+    let mut response = client.get(url.clone()).send().await?.error_for_status()?;
+    let mime_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned);
+    ensure!(
+        response.content_length().is_none_or(|length| length <= limit as u64),
+        "Response is over {} bytes",
+        limit
+    );
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        body.extend_from_slice(&chunk);
+        ensure!(body.len() <= limit, "Response is over {} bytes", limit);
+    }
+    Ok((mime_type, body))
+}
@@ -0,0 +1,62 @@
+use crate::Article;
+use regex::Regex;
+use serde::Deserialize;
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Field {
+    Headline,
+    /// The body's HTML, tags and all.
+    Body,
+    Description,
+    Author,
+    /// Each category separately.
+    Categories,
+}
+
+/// A find and replace on one field of every article a scraper extracts. Replacements can refer
+/// to groups as `$1` or `$name`.
+#[derive(Debug, Clone)]
+pub struct Rewrite {
+    pub field: Field,
+    pub pattern: Regex,
+    pub replacement: Cow<'static, str>,
+}
+
+impl Rewrite {
+    fn replace(&self, text: &str) -> String {
+        self.pattern
+            .replace_all(text, self.replacement.as_ref())
+            .into_owned()
+    }
+}
+
+/// Applies each rewrite in turn. Text fields other than the body are trimmed afterwards, and
+/// dropped if nothing is left of them.
+pub(crate) fn apply(rewrites: &[Rewrite], mut article: Article) -> Article {
+    if rewrites.is_empty() {
+        return article;
+    }
+    for rewrite in rewrites {
+        match rewrite.field {
+            Field::Headline => article.headline = rewrite.replace(&article.headline),
+            Field::Body => article.body = rewrite.replace(&article.body),
+            Field::Description => {
+                article.description = article.description.map(|text| rewrite.replace(&text))
+            }
+            Field::Author => article.author = article.author.map(|text| rewrite.replace(&text)),
+            Field::Categories => {
+                for category in &mut article.categories {
+                    *category = rewrite.replace(category);
+                }
+            }
+        }
+    }
+    let trim = |text: String| Some(text.trim().to_owned()).filter(|text| !text.is_empty());
+    article.headline = article.headline.trim().to_owned();
+    article.description = article.description.and_then(trim);
+    article.author = article.author.and_then(trim);
+    article.categories = article.categories.into_iter().filter_map(trim).collect();
+    article
+}
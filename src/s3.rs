@@ -0,0 +1,146 @@
+use anyhow::*;
+use bytes::Bytes;
+use chrono::Utc;
+use reqwest::{Client, Url};
+use ring::{
+    digest::{digest, SHA256},
+    hmac,
+};
+use std::fmt::Write;
+use tracing::{instrument, trace};
+
+#[derive(Debug, Clone)]
+pub struct S3 {
+    pub bucket: String,
+    /// Prepended to every object key, e.g. `feeds/`.
+    pub prefix: String,
+    pub region: String,
+    /// An S3-compatible endpoint, addressed path-style. AWS is addressed virtual-host-style
+    /// when unset.
+    pub endpoint: Option<Url>,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        write!(hex, "{:02x}", byte).unwrap();
+        hex
+    })
+}
+
+fn sha256(data: &[u8]) -> String {
+    hex(digest(&SHA256, data).as_ref())
+}
+
+fn hmac(key: &[u8], data: &str) -> hmac::Tag {
+    hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), data.as_bytes())
+}
+
+// SigV4 requires every byte outside the unreserved set to be percent-encoded.
+fn encode(key: &str) -> String {
+    key.bytes().fold(String::new(), |mut encoded, byte| {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => write!(encoded, "%{:02X}", byte).unwrap(),
+        }
+        encoded
+    })
+}
+
+impl S3 {
+    fn url(&self, key: &str) -> Result<Url> {
+        let key = encode(&format!("{}{}", self.prefix, key));
+        let url = match &self.endpoint {
+            Some(endpoint) => format!(
+                "{}/{}/{}",
+                endpoint.as_str().trim_end_matches('/'),
+                encode(&self.bucket),
+                key
+            ),
+            None => format!(
+                "https://{}.s3.{}.amazonaws.com/{}",
+                self.bucket, self.region, key
+            ),
+        };
+        Ok(url.parse()?)
+    }
+
+    #[instrument(skip(self, client, body), fields(bucket = self.bucket.as_str()))]
+    pub async fn put(
+        &self,
+        client: &Client,
+        key: &str,
+        content_type: &str,
+        body: Bytes,
+    ) -> Result<()> {
+        let url = self.url(key)?;
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().context("No host")?, port),
+            None => url.host_str().context("No host")?.to_owned(),
+        };
+        let now = Utc::now();
+        let date = now.format("%Y%m%d").to_string();
+        let timestamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let payload = sha256(&body);
+        let mut headers = vec![
+            ("content-type", content_type.to_owned()),
+            ("host", host),
+            ("x-amz-content-sha256", payload.clone()),
+            ("x-amz-date", timestamp.clone()),
+        ];
+        if let Some(token) = &self.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        let signed = headers
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical = format!(
+            "PUT\n{}\n\n{}\n{}\n{}",
+            url.path(),
+            headers
+                .iter()
+                .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+                .collect::<String>(),
+            signed,
+            payload
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            timestamp,
+            scope,
+            sha256(canonical.as_bytes())
+        );
+        let key = [self.region.as_str(), "s3", "aws4_request"].iter().fold(
+            hmac(format!("AWS4{}", self.secret_access_key).as_bytes(), &date),
+            |key, part| hmac(key.as_ref(), part),
+        );
+        let signature = hex(hmac(key.as_ref(), &string_to_sign).as_ref());
+        let mut request = client.put(url).header(
+            "authorization",
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{},SignedHeaders={},Signature={}",
+                self.access_key_id, scope, signed, signature
+            ),
+        );
+        for (name, value) in headers {
+            if name != "host" {
+                request = request.header(name, value);
+            }
+        }
+        let response = request.body(body).send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            bail!("S3 upload failed with status {}: {}", status, text.trim());
+        }
+        trace!("Uploaded object");
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+use chrono::{DateTime, Utc};
+use fnv::FnvHasher;
+use rand::Rng;
+use std::{hash::Hasher, time::Duration};
+
+/// Spreads a source's scrapes out from everything else's. Scrapes fall at `offset` past each
+/// multiple of the interval since the Unix epoch, so the slot survives restarts, then wait a
+/// random extra share of the interval up to `jitter`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stagger {
+    /// Derived from the source's name when unset, which spreads sources without configuring any.
+    pub offset: Option<Duration>,
+    pub jitter: f64,
+}
+
+impl Stagger {
+    pub const DEFAULT_JITTER: f64 = 0.05;
+    pub const DEFAULT: Stagger = Stagger {
+        offset: None,
+        jitter: Stagger::DEFAULT_JITTER,
+    };
+
+    fn offset(&self, name: &str, period: Duration) -> u128 {
+        let period = period.as_millis().max(1);
+        match self.offset {
+            Some(offset) => offset.as_millis() % period,
+            None => {
+                let mut hasher = FnvHasher::default();
+                hasher.write(name.as_bytes());
+                hasher.finish() as u128 % period
+            }
+        }
+    }
+
+    /// How long to wait from `now` until the next scrape in the source's slot, at least half a
+    /// period away so a scrape just before a slot doesn't run twice in a row.
+    pub fn delay(&self, name: &str, period: Duration, now: DateTime<Utc>) -> Duration {
+        let millis = period.as_millis().max(1);
+        let now = now.timestamp_millis().max(0) as u128;
+        let offset = self.offset(name, period);
+        let mut next = (now + millis - offset) / millis * millis + offset;
+        if next - now < millis / 2 {
+            next += millis;
+        }
+        Duration::from_millis((next - now) as u64) + self.jitter(period)
+    }
+
+    /// A random share of the period, up to the jitter.
+    pub fn jitter(&self, period: Duration) -> Duration {
+        match self.jitter > 0.0 {
+            true => period.mul_f64(rand::thread_rng().gen_range(0.0..self.jitter.min(1.0))),
+            false => Duration::ZERO,
+        }
+    }
+}
+
+impl Default for Stagger {
+    fn default() -> Self {
+        Stagger::DEFAULT
+    }
+}
+
+/// Bounds for an interval that follows how often a source publishes, the way feed readers check
+/// busy feeds often and idle ones rarely.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Adaptive {
+    pub min_interval: Duration,
+    pub max_interval: Duration,
+}
+
+impl Adaptive {
+    /// How many of the latest articles the posting rate is taken from.
+    pub const SAMPLE: usize = 10;
+
+    /// Half the typical gap between the latest articles, so most are picked up well before the
+    /// next one; or half the time since the last one when that's longer, which backs off as a
+    /// source goes quiet overnight.
+    pub fn interval(
+        &self,
+        dates: impl IntoIterator<Item = DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Duration {
+        let mut dates = dates.into_iter().filter(|date| *date <= now).collect::<Vec<_>>();
+        dates.sort_unstable_by(|a, b| b.cmp(a));
+        dates.dedup();
+        dates.truncate(Adaptive::SAMPLE);
+        let latest = match dates.first() {
+            Some(latest) => *latest,
+            None => return self.max_interval,
+        };
+        let since = now - latest;
+        let gap = match dates.last() {
+            Some(earliest) if dates.len() > 1 => (latest - *earliest) / (dates.len() as i32 - 1),
+            _ => since,
+        };
+        let wait = gap.max(since).to_std().unwrap_or_default() / 2;
+        wait.clamp(self.min_interval, self.max_interval)
+    }
+}
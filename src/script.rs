@@ -0,0 +1,75 @@
+//! Rhai hooks for scrapers that need a little custom logic, such as cleaning up headlines or
+//! munging dates, without recompiling.
+
+use anyhow::*;
+use rhai::{Engine, Scope, AST};
+use std::sync::LazyLock;
+use tracing::trace;
+
+const HOOKS: [&str; 3] = ["transform_headline", "transform_body", "parse_date"];
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+static ENGINE: LazyLock<Engine> = LazyLock::new(|| {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.on_print(|text| trace!(text, "Script printed"));
+    engine.on_debug(|text, _, _| trace!(text, "Script printed"));
+    engine
+});
+
+/// A script defining any of the hooks, which each take and return a string:
+///
+/// - `transform_headline(headline)` replaces the article's headline.
+/// - `transform_body(body)` replaces the body's HTML.
+/// - `parse_date(date)` turns the date's text into something the scraper's date formats, RFC 3339
+///   or RFC 2822 can parse.
+///
+/// Hooks run before rewrites, and each call is limited in how much work it can do.
+#[derive(Debug)]
+pub struct Script {
+    ast: AST,
+}
+
+impl Script {
+    pub fn new(source: &str) -> Result<Script> {
+        let ast = ENGINE
+            .compile(source)
+            .map_err(|error| anyhow!("{}", error))
+            .context("Failed to compile script")?;
+        let hooks = ast
+            .iter_functions()
+            .filter(|function| HOOKS.contains(&function.name))
+            .collect::<Vec<_>>();
+        ensure!(
+            !hooks.is_empty(),
+            "Script defines none of the hooks {}",
+            HOOKS.join(", ")
+        );
+        if let Some(hook) = hooks.iter().find(|hook| hook.params.len() != 1) {
+            bail!("Script hook {} should take one argument", hook.name);
+        }
+        Ok(Script { ast })
+    }
+
+    fn call(&self, hook: &str, text: String) -> Result<String> {
+        if !self.ast.iter_functions().any(|function| function.name == hook) {
+            return Ok(text);
+        }
+        ENGINE
+            .call_fn(&mut Scope::new(), &self.ast, hook, (text,))
+            .map_err(|error| anyhow!("{}", error))
+            .with_context(|| format!("Script hook {} failed", hook))
+    }
+
+    pub fn headline(&self, headline: String) -> Result<String> {
+        self.call("transform_headline", headline)
+    }
+
+    pub fn body(&self, body: String) -> Result<String> {
+        self.call("transform_body", body)
+    }
+
+    pub fn date(&self, date: String) -> Result<String> {
+        self.call("parse_date", date)
+    }
+}
@@ -0,0 +1,124 @@
+use anyhow::*;
+use std::{fmt, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token<'a> {
+    Word(&'a str),
+    Phrase(&'a str),
+    Operator(&'a str),
+    Open,
+    Close,
+}
+
+/// A search query compiled to FTS5 syntax: words, `"phrases"`, `prefix*`, `AND`, `OR`, `NOT`
+/// and parentheses. Adjacent terms must all match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    source: String,
+    compiled: String,
+}
+
+fn tokens(query: &str) -> Result<Vec<Token<'_>>> {
+    let mut tokens = Vec::new();
+    let mut rest = query.trim_start();
+    while !rest.is_empty() {
+        let (token, len) = if let Some(after) = rest.strip_prefix('"') {
+            let end = after
+                .find('"')
+                .with_context(|| format!("Unclosed quote in search query {:?}", query))?;
+            (Token::Phrase(&after[..end]), end + 2)
+        } else if rest.starts_with('(') {
+            (Token::Open, 1)
+        } else if rest.starts_with(')') {
+            (Token::Close, 1)
+        } else {
+            let end = rest
+                .find(|c: char| c.is_whitespace() || matches!(c, '"' | '(' | ')'))
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            match word {
+                "AND" | "OR" | "NOT" => (Token::Operator(word), end),
+                _ => (Token::Word(word), end),
+            }
+        };
+        tokens.push(token);
+        rest = rest[len..].trim_start();
+    }
+    Ok(tokens)
+}
+
+fn quote(term: &str) -> String {
+    format!("\"{}\"", term)
+}
+
+impl FromStr for Query {
+    type Err = Error;
+
+    fn from_str(query: &str) -> Result<Query> {
+        let mut compiled = Vec::new();
+        let mut depth = 0usize;
+        // Whether the next token has to be a term or an opening parenthesis.
+        let mut operand = true;
+        for token in tokens(query)? {
+            match token {
+                Token::Word(word) => match word.strip_suffix('*') {
+                    Some(prefix) if !prefix.is_empty() => {
+                        compiled.push(format!("{}*", quote(prefix)))
+                    }
+                    _ => compiled.push(quote(word)),
+                },
+                Token::Phrase(phrase) => compiled.push(quote(phrase)),
+                Token::Operator(operator) => {
+                    ensure!(
+                        !operand,
+                        "Misplaced {} in search query {:?}",
+                        operator,
+                        query
+                    );
+                    compiled.push(operator.to_owned());
+                    operand = true;
+                    continue;
+                }
+                Token::Open => {
+                    depth += 1;
+                    compiled.push("(".to_owned());
+                    operand = true;
+                    continue;
+                }
+                Token::Close => {
+                    ensure!(
+                        depth > 0 && !operand,
+                        "Unbalanced parentheses in search query {:?}",
+                        query
+                    );
+                    depth -= 1;
+                    compiled.push(")".to_owned());
+                }
+            }
+            operand = false;
+        }
+        ensure!(!compiled.is_empty(), "Empty search query");
+        ensure!(!operand, "Incomplete search query {:?}", query);
+        ensure!(
+            depth == 0,
+            "Unbalanced parentheses in search query {:?}",
+            query
+        );
+        Ok(Query {
+            source: query.trim().to_owned(),
+            compiled: compiled.join(" "),
+        })
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl Query {
+    pub fn compiled(&self) -> &str {
+        &self.compiled
+    }
+}
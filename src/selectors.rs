@@ -0,0 +1,67 @@
+use anyhow::*;
+use nipper::{Matcher, Selection};
+
+#[derive(Debug, Clone)]
+pub enum Selectors {
+    Static(&'static [&'static str]),
+    Owned(Vec<String>),
+}
+
+impl Selectors {
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        let selectors: Vec<&str> = match self {
+            Selectors::Static(selectors) => selectors.to_vec(),
+            Selectors::Owned(selectors) => selectors.iter().map(String::as_str).collect(),
+        };
+        selectors.into_iter()
+    }
+
+    pub(crate) fn validate(&self, name: &str) -> Result<()> {
+        ensure!(
+            self.iter().next().is_some(),
+            "Scraper requires at least one {}",
+            name
+        );
+        for selector in self.iter() {
+            Matcher::new(selector)
+                .map_err(|e| anyhow!("Invalid {} {:?}: {:?}", name, selector, e))?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn select<'a>(&self, select: impl Fn(&str) -> Selection<'a>) -> Selection<'a> {
+        let mut selection = None;
+        for selector in self.iter() {
+            let matched = select(selector);
+            if matched.exists() {
+                return matched;
+            }
+            selection = Some(matched);
+        }
+        selection.expect("Expected at least one selector")
+    }
+}
+
+impl From<&'static str> for Selectors {
+    fn from(selector: &'static str) -> Self {
+        Selectors::Owned(vec![selector.to_owned()])
+    }
+}
+
+impl From<String> for Selectors {
+    fn from(selector: String) -> Self {
+        Selectors::Owned(vec![selector])
+    }
+}
+
+impl From<Vec<String>> for Selectors {
+    fn from(selectors: Vec<String>) -> Self {
+        Selectors::Owned(selectors)
+    }
+}
+
+impl From<&'static [&'static str]> for Selectors {
+    fn from(selectors: &'static [&'static str]) -> Self {
+        Selectors::Static(selectors)
+    }
+}
@@ -0,0 +1,1509 @@
+use crate::{
+    admin::{self, Admin, Edit},
+    registry::Registry,
+};
+use anyhow::*;
+use async_trait::async_trait;
+use axum::{
+    body::{box_body, BoxBody},
+    extract::{
+        connect_info::{ConnectInfo, Connected},
+        FromRequest, Path, Query, RequestParts,
+    },
+    handler::{get, post, put},
+    http::Response,
+    http::{
+        header::{
+            HeaderName, ACCEPT, ACCEPT_ENCODING, AUTHORIZATION, CACHE_CONTROL, CONTENT_ENCODING,
+            CONTENT_SECURITY_POLICY, CONTENT_TYPE, ETAG, HOST, IF_MODIFIED_SINCE, IF_NONE_MATCH,
+            LAST_MODIFIED, RETRY_AFTER, VARY, WWW_AUTHENTICATE, X_CONTENT_TYPE_OPTIONS,
+        },
+        HeaderMap, StatusCode, Uri,
+    },
+    response::{Headers, Html, IntoResponse, Json},
+    AddExtensionLayer, Router,
+};
+use bytes::Bytes;
+use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+use flate2::{write::GzEncoder, Compression};
+use futures::stream::{self, StreamExt};
+use hyper::server::{accept, conn::AddrStream};
+use news_rss::{
+    activitypub,
+    auth::{self, Auth},
+    feed, images, metrics,
+    render::{self, Channel, Format, Paging, Rendered},
+    status,
+    store::Store,
+    throttle, tls, Article, Feed, Query as SearchQuery, Scraper,
+};
+use reqwest::Client;
+use ring::constant_time::verify_slices_are_equal;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    convert::Infallible,
+    fs,
+    hash::{Hash, Hasher},
+    io::{self, Write},
+    net::{IpAddr, SocketAddr},
+    os::unix::fs::FileTypeExt,
+    path::{Path as StdPath, PathBuf},
+    str::FromStr,
+    sync::{Arc, LazyLock, Mutex as StdMutex},
+    time::{Duration, Instant},
+};
+use tokio::{
+    net::{TcpListener, TcpStream, UnixListener, UnixStream},
+    sync::{watch, Mutex, Notify},
+    time::timeout,
+};
+use tokio_rustls::server::TlsStream;
+use toml::Table;
+use tracing::{instrument, span, trace, warn, Instrument, Level};
+
+const READY_INTERVALS: u32 = 3;
+const ARCHIVE_PAGE_SIZE: usize = 50;
+const SEARCH_LIMIT: usize = 50;
+const SEARCH_CACHE_SIZE: usize = 256;
+const COMPRESSED_CACHE_SIZE: usize = 256;
+// Below this, the gzip framing eats most of the saving.
+const COMPRESS_THRESHOLD: usize = 1024;
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_HANDSHAKES: usize = 64;
+const CERTIFICATE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+fn record(handler: &str, response: impl IntoResponse) -> Response<BoxBody> {
+    let response = response.into_response().map(box_body);
+    metrics::HTTP_REQUESTS
+        .with_label_values(&[handler, response.status().as_str()])
+        .inc();
+    response
+}
+
+fn not_modified(headers: &HeaderMap, etag: &str, updated: DateTime<Utc>) -> bool {
+    if let Some(if_none_match) = headers.get(IF_NONE_MATCH) {
+        return if_none_match.to_str().is_ok_and(|if_none_match| {
+            if_none_match
+                .split(',')
+                .any(|tag| tag.trim() == etag || tag.trim() == "*")
+        });
+    }
+    headers
+        .get(IF_MODIFIED_SINCE)
+        .and_then(|since| since.to_str().ok())
+        .and_then(|since| DateTime::parse_from_rfc2822(since).ok())
+        .is_some_and(|since| updated.timestamp() <= since.timestamp())
+}
+
+/// Where a connection came from; Unix socket peers have no address.
+#[derive(Debug, Clone, Copy)]
+struct Peer(Option<IpAddr>);
+
+impl Connected<&AddrStream> for Peer {
+    type ConnectInfo = Peer;
+
+    fn connect_info(stream: &AddrStream) -> Peer {
+        Peer(Some(stream.remote_addr().ip()))
+    }
+}
+
+impl Connected<&TlsStream<TcpStream>> for Peer {
+    type ConnectInfo = Peer;
+
+    fn connect_info(stream: &TlsStream<TcpStream>) -> Peer {
+        Peer(
+            stream
+                .get_ref()
+                .0
+                .peer_addr()
+                .ok()
+                .map(|address| address.ip()),
+        )
+    }
+}
+
+impl Connected<&UnixStream> for Peer {
+    type ConnectInfo = Peer;
+
+    fn connect_info(_: &UnixStream) -> Peer {
+        Peer(None)
+    }
+}
+
+/// Rejects requests from clients over the rate limit, when one is configured.
+struct Throttled;
+
+#[async_trait]
+impl<B: Send> FromRequest<B> for Throttled {
+    type Rejection = Response<BoxBody>;
+
+    async fn from_request(request: &mut RequestParts<B>) -> Result<Throttled, Response<BoxBody>> {
+        let limiter = match throttle::get() {
+            Some(limiter) => limiter,
+            None => return Ok(Throttled),
+        };
+        let peer = request
+            .extensions()
+            .and_then(|extensions| extensions.get::<ConnectInfo<Peer>>())
+            .and_then(|ConnectInfo(Peer(peer))| *peer);
+        let forwarded_for = request.headers().map(|headers| {
+            headers
+                .get_all("x-forwarded-for")
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .collect::<Vec<_>>()
+                .join(",")
+        });
+        let client = match limiter.throttle().client(peer, forwarded_for.as_deref()) {
+            Some(client) => client,
+            None => return Ok(Throttled),
+        };
+        match limiter.check(client, Instant::now()) {
+            Result::Ok(()) => Ok(Throttled),
+            Err(wait) => {
+                trace!(%client, "Throttled request");
+                Err(record(
+                    "throttled",
+                    (
+                        StatusCode::TOO_MANY_REQUESTS,
+                        Headers(vec![(RETRY_AFTER, wait.as_secs_f64().ceil().to_string())]),
+                        "Too many requests\n",
+                    ),
+                ))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Credentials {
+    token: Option<String>,
+    feed: Option<String>,
+}
+
+/// Rejects requests for protected feeds without valid credentials, when authentication is
+/// configured. The feed is read from the path, or the `feed` parameter for searches, and checked
+/// against the virtual feeds of the registry current at the time.
+struct Authorized;
+
+#[async_trait]
+impl<B: Send> FromRequest<B> for Authorized {
+    type Rejection = Response<BoxBody>;
+
+    async fn from_request(request: &mut RequestParts<B>) -> Result<Authorized, Response<BoxBody>> {
+        let auth = match auth::get() {
+            Some(auth) => auth,
+            None => return Ok(Authorized),
+        };
+        let credentials = Query::<Credentials>::from_request(request)
+            .await
+            .map(|Query(credentials)| credentials)
+            .unwrap_or_default();
+        let segments = request
+            .uri()
+            .path()
+            .trim_start_matches('/')
+            .split('/')
+            .map(|segment| Format::split(segment).map_or(segment, |(slug, _)| slug))
+            .collect::<Vec<_>>();
+        let feed = match segments.as_slice() {
+            ["search"] => credentials.feed.as_deref(),
+            ["archive", file] => Some(*file),
+            [feed, ..] => Some(*feed),
+            [] => None,
+        };
+        let registry = request
+            .extensions()
+            .and_then(|extensions| extensions.get::<&'static watch::Receiver<Arc<Registry>>>())
+            .map(|registry| current(registry));
+        let virtual_feeds = registry.as_ref().map_or(&[][..], |registry| &registry.virtual_feeds);
+        if !auth.protects(feed, virtual_feeds) {
+            return Ok(Authorized);
+        }
+        if credentialed(request, auth, credentials.token.as_deref()) {
+            return Ok(Authorized);
+        }
+        trace!(?feed, "Missing or invalid credentials");
+        let challenge = Some(r#"Basic realm="news-rss", charset="UTF-8""#)
+            .filter(|_| !auth.users.is_empty())
+            .map(|challenge| (WWW_AUTHENTICATE, challenge));
+        Err(record(
+            "unauthorized",
+            (
+                StatusCode::UNAUTHORIZED,
+                Headers(challenge.into_iter().collect::<Vec<_>>()),
+                "Unauthorized\n",
+            ),
+        ))
+    }
+}
+
+/// Whether a request carries the admin token, or credentials `auth` accepts.
+fn credentialed<B>(request: &RequestParts<B>, auth: &Auth, token: Option<&str>) -> bool {
+    let authorization = request
+        .headers()
+        .and_then(|headers| headers.get(AUTHORIZATION))
+        .and_then(|header| header.to_str().ok());
+    let admin = authorization
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .zip(admin::get())
+        .is_some_and(|(token, admin)| admin.authorized(Some(token)));
+    admin || auth.authorized(authorization, token)
+}
+
+/// Whether the request may see every feed, protected or not, in listings of them. Without
+/// authentication configured, every request can.
+struct Credentialed(bool);
+
+#[async_trait]
+impl<B: Send> FromRequest<B> for Credentialed {
+    type Rejection = Infallible;
+
+    async fn from_request(request: &mut RequestParts<B>) -> Result<Credentialed, Infallible> {
+        let auth = match auth::get() {
+            Some(auth) => auth,
+            None => return Ok(Credentialed(true)),
+        };
+        let credentials = Query::<Credentials>::from_request(request)
+            .await
+            .map(|Query(credentials)| credentials)
+            .unwrap_or_default();
+        Ok(Credentialed(credentialed(
+            request,
+            auth,
+            credentials.token.as_deref(),
+        )))
+    }
+}
+
+impl Credentialed {
+    fn lists(&self, registry: &Registry, feed: &str) -> bool {
+        self.0
+            || !auth::get().is_some_and(|auth| auth.protects(Some(feed), &registry.virtual_feeds))
+    }
+}
+
+fn current(registry: &watch::Receiver<Arc<Registry>>) -> Arc<Registry> {
+    registry.borrow().clone()
+}
+
+/// The format a path asks for by extension, or, without one, that the `Accept` header prefers.
+/// Also returns whether it was negotiated, in which case responses must vary on `Accept`.
+fn split<'a>(file: &'a str, headers: &HeaderMap) -> Result<(&'a str, Format, bool), StatusCode> {
+    if !file.contains('.') {
+        return Ok((file, negotiate(headers), true));
+    }
+    Format::split(file)
+        .map(|(slug, format)| (slug, format, false))
+        .ok_or_else(|| {
+            trace!("Unknown feed format");
+            StatusCode::NOT_FOUND
+        })
+}
+
+fn negotiate(headers: &HeaderMap) -> Format {
+    let accept = headers
+        .get_all(ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect::<Vec<_>>()
+        .join(",");
+    Format::negotiate(&accept)
+}
+
+fn find<'a>(feeds: &'a HashMap<String, Feed>, slug: &str) -> Result<&'a Feed, StatusCode> {
+    feeds
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(slug))
+        .map(|(_, feed)| feed)
+        .ok_or_else(|| {
+            trace!("Feed not found");
+            StatusCode::NOT_FOUND
+        })
+}
+
+#[derive(Debug, Default, Hash, Deserialize)]
+struct Filter {
+    include: Option<String>,
+    exclude: Option<String>,
+    since: Option<DateTime<FixedOffset>>,
+    until: Option<DateTime<FixedOffset>>,
+    limit: Option<usize>,
+}
+
+fn keywords(keywords: &Option<String>) -> Vec<String> {
+    keywords
+        .iter()
+        .flat_map(|keywords| keywords.split(','))
+        .map(|keyword| keyword.trim().to_lowercase())
+        .filter(|keyword| !keyword.is_empty())
+        .collect()
+}
+
+impl Filter {
+    fn is_empty(&self) -> bool {
+        keywords(&self.include).is_empty()
+            && keywords(&self.exclude).is_empty()
+            && self.since.is_none()
+            && self.until.is_none()
+            && self.limit.is_none()
+    }
+
+    fn etag(&self) -> String {
+        if self.is_empty() {
+            return String::new();
+        }
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        format!("-{:016x}", hasher.finish())
+    }
+
+    fn apply<'a>(&self, articles: impl IntoIterator<Item = &'a Article>) -> Vec<Article> {
+        let include = keywords(&self.include);
+        let exclude = keywords(&self.exclude);
+        articles
+            .into_iter()
+            .filter(|article| {
+                let text = format!("{}\n{}", article.headline, article.body).to_lowercase();
+                let matches = |keyword: &String| text.contains(keyword.as_str());
+                (include.is_empty() || include.iter().any(matches)) && !exclude.iter().any(matches)
+            })
+            .filter(|article| self.since.is_none_or(|since| article.date >= since))
+            .filter(|article| self.until.is_none_or(|until| article.date <= until))
+            .take(self.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect()
+    }
+}
+
+async fn feed(
+    feeds: &Mutex<HashMap<String, Feed>>,
+    file: &str,
+    filter: &Filter,
+    headers: &HeaderMap,
+) -> Result<impl IntoResponse, StatusCode> {
+    trace!("Entered feed handler");
+    let (slug, format, negotiated) = split(file, headers)?;
+    let (channel, hash, updated, body, filtered) = {
+        let feeds = feeds.lock().await;
+        let feed = find(&feeds, slug)?;
+        (
+            feed.channel.clone(),
+            feed.hash,
+            feed.updated,
+            feed.rendered.get(format),
+            (!filter.is_empty()).then(|| filter.apply(&feed.articles)),
+        )
+    };
+    let etag = format!("\"{:016x}{}-{}\"", hash, filter.etag(), format.extension());
+    Ok(respond(
+        headers,
+        format,
+        negotiated,
+        etag,
+        updated,
+        || match filtered {
+            Some(articles) => format.render(&channel, &articles).into(),
+            None => body,
+        },
+    ))
+}
+
+async fn section(
+    feeds: &Mutex<HashMap<String, Feed>>,
+    slug: &str,
+    file: &str,
+    filter: &Filter,
+    headers: &HeaderMap,
+) -> Result<impl IntoResponse, StatusCode> {
+    trace!("Entered section handler");
+    let (section, format, negotiated) = split(file, headers)?;
+    let (channel, hash, updated, articles) = {
+        let feeds = feeds.lock().await;
+        let feed = find(&feeds, slug)?;
+        (
+            feed.channel.clone(),
+            feed.hash,
+            feed.updated,
+            feed.section(section),
+        )
+    };
+    if articles.is_empty() {
+        trace!("Section not found");
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let articles = filter.apply(&articles);
+    let etag = format!(
+        "\"{:016x}{}-{}-{}\"",
+        hash,
+        filter.etag(),
+        section.to_lowercase(),
+        format.extension()
+    );
+    let channel = Channel {
+        title: format!("{} - {}", channel.title, section),
+        slug: format!("{}/{}", channel.slug, section.to_lowercase()),
+        self_link: channel
+            .self_link
+            .map(|link| format!("{}/{}", link, section.to_lowercase())),
+        archive: None,
+        hub: None,
+        ..channel
+    };
+    Ok(respond(headers, format, negotiated, etag, updated, || {
+        format.render(&channel, &articles).into()
+    }))
+}
+
+/// Gzipped bodies by ETag, so polling readers don't pay for compressing the same feed each time.
+static COMPRESSED: LazyLock<StdMutex<HashMap<String, Bytes>>> = LazyLock::new(Default::default);
+
+fn compressed(etag: &str, body: Bytes) -> Bytes {
+    if let Some(body) = COMPRESSED.lock().unwrap().get(etag) {
+        return body.clone();
+    }
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(&body)
+        .expect("Expected writing to memory to succeed");
+    let body = Bytes::from(
+        encoder
+            .finish()
+            .expect("Expected writing to memory to succeed"),
+    );
+    let mut cache = COMPRESSED.lock().unwrap();
+    if cache.len() >= COMPRESSED_CACHE_SIZE {
+        trace!("Clearing compressed body cache");
+        cache.clear();
+    }
+    cache.insert(etag.to_owned(), body.clone());
+    body
+}
+
+fn respond(
+    headers: &HeaderMap,
+    format: Format,
+    negotiated: bool,
+    etag: String,
+    updated: DateTime<Utc>,
+    body: impl FnOnce() -> Bytes,
+) -> (StatusCode, Headers<Vec<(HeaderName, String)>>, Bytes) {
+    let gzip = headers
+        .get_all(ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .any(render::accepts_gzip);
+    // The encoded body is a different representation, so it needs its own strong validator.
+    let etag = match gzip {
+        true => format!("{}-gzip\"", etag.trim_end_matches('"')),
+        false => etag,
+    };
+    let mut response_headers = vec![
+        (CONTENT_TYPE, format.content_type().to_owned()),
+        (ETAG, etag.clone()),
+        (
+            LAST_MODIFIED,
+            updated.format("%a, %d %b %Y %H:%M:%S GMT").to_string(),
+        ),
+        (
+            VARY,
+            match negotiated {
+                true => "Accept, Accept-Encoding",
+                false => "Accept-Encoding",
+            }
+            .to_owned(),
+        ),
+    ];
+    if not_modified(headers, &etag, updated) {
+        trace!("Feed not modified");
+        return (
+            StatusCode::NOT_MODIFIED,
+            Headers(response_headers),
+            Bytes::new(),
+        );
+    }
+    let body = body();
+    if !gzip || body.len() < COMPRESS_THRESHOLD {
+        return (StatusCode::OK, Headers(response_headers), body);
+    }
+    response_headers.push((CONTENT_ENCODING, "gzip".to_owned()));
+    (
+        StatusCode::OK,
+        Headers(response_headers),
+        compressed(&etag, body),
+    )
+}
+
+/// The configured public URL, or else where the request says it was sent, which a client can
+/// set to anything.
+fn origin(registry: &Registry, headers: &HeaderMap) -> String {
+    if let Some(public_url) = &registry.public_url {
+        return public_url.clone();
+    }
+    let host = headers
+        .get(HOST)
+        .and_then(|host| host.to_str().ok())
+        .unwrap_or("localhost");
+    let scheme = headers
+        .get("x-forwarded-proto")
+        .and_then(|scheme| scheme.to_str().ok())
+        .unwrap_or("http");
+    format!("{}://{}", scheme, host)
+}
+
+fn opml(registry: &Registry, headers: &HeaderMap, credentialed: Credentialed) -> impl IntoResponse {
+    trace!("Entered OPML handler");
+    let mut names = registry.names();
+    names.retain(|name| credentialed.lists(registry, name));
+    (
+        Headers(vec![(CONTENT_TYPE, "text/x-opml")]),
+        render::opml(&origin(registry, headers), &names),
+    )
+}
+
+#[derive(Debug, Deserialize)]
+struct Page {
+    page: Option<usize>,
+}
+
+/// A cached article image, or its thumbnail. The key is a hash of the origin URL, so the content
+/// only changes in the rare case two URLs collide.
+fn image(key: &str, thumbnail: bool, headers: &HeaderMap) -> Result<impl IntoResponse, StatusCode> {
+    trace!("Entered image handler");
+    let images = images::get().ok_or(StatusCode::NOT_FOUND)?;
+    let etag = match thumbnail {
+        true => format!("\"{}-thumbnail\"", key),
+        false => format!("\"{}\"", key),
+    };
+    let mut response_headers = vec![
+        (ETAG, etag.clone()),
+        (CACHE_CONTROL, "public, max-age=604800".to_owned()),
+        (X_CONTENT_TYPE_OPTIONS, "nosniff".to_owned()),
+        // Served from our own origin, so an SVG mustn't be able to run scripts.
+        (CONTENT_SECURITY_POLICY, "default-src 'none'".to_owned()),
+    ];
+    let matched = headers
+        .get(IF_NONE_MATCH)
+        .and_then(|if_none_match| if_none_match.to_str().ok())
+        .is_some_and(|if_none_match| if_none_match.split(',').any(|tag| tag.trim() == etag));
+    if matched && images.contains_key(key) {
+        return Ok((StatusCode::NOT_MODIFIED, Headers(response_headers), Bytes::new()));
+    }
+    let image = match thumbnail {
+        true => images.load_thumbnail(key),
+        false => images.load(key),
+    };
+    let image = image.ok_or_else(|| {
+        trace!("Image not found");
+        StatusCode::NOT_FOUND
+    })?;
+    response_headers.push((CONTENT_TYPE, image.mime_type));
+    Ok((StatusCode::OK, Headers(response_headers), Bytes::from(image.body)))
+}
+
+async fn archive(
+    registry: &Registry,
+    feeds: &Mutex<HashMap<String, Feed>>,
+    store: Option<&'static Store>,
+    file: &str,
+    page: Option<usize>,
+    headers: &HeaderMap,
+) -> Result<impl IntoResponse, StatusCode> {
+    trace!("Entered archive handler");
+    let store = store.ok_or(StatusCode::NOT_FOUND)?;
+    let (slug, format, negotiated) = split(file, headers)?;
+    let (name, channel, updated) = {
+        let feeds = feeds.lock().await;
+        let feed = find(&feeds, slug)?;
+        (feed.name.clone(), feed.channel.clone(), feed.updated)
+    };
+    // Full pages are archived for good, so only the last page, which may still be empty, fills
+    // up. Without a page, that newest one is served.
+    let loaded = tokio::task::spawn_blocking(move || -> Result<_> {
+        let pages = store.count(&name)? / ARCHIVE_PAGE_SIZE + 1;
+        let page = page.unwrap_or(pages);
+        if page == 0 || page > pages {
+            return Ok((page, pages, None));
+        }
+        let mut articles =
+            store.history(&name, (page - 1) * ARCHIVE_PAGE_SIZE, ARCHIVE_PAGE_SIZE)?;
+        feed::sort(&mut articles);
+        Ok((page, pages, Some(articles)))
+    })
+    .await;
+    let (page, pages, articles) = loaded
+        .map_err(Error::from)
+        .and_then(|loaded| loaded)
+        .map_err(|error| {
+            warn!(?error, "Failed to load archive");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    let articles = articles.ok_or_else(|| {
+        trace!(page, pages, "Archive page not found");
+        StatusCode::NOT_FOUND
+    })?;
+    let (base, slug) = match channel
+        .self_link
+        .as_deref()
+        .and_then(|link| link.rsplit_once('/'))
+    {
+        Some((base, slug)) => (base.to_owned(), slug.to_owned()),
+        None => (origin(registry, headers), slug.to_lowercase()),
+    };
+    let channel = Channel {
+        paging: Some(Paging {
+            archive: format!("{}/archive/{}", base, slug),
+            current: format!("{}/{}", base, slug),
+            page,
+            pages,
+        }),
+        max_items: None,
+        hub: None,
+        ..channel
+    };
+    let mut hasher = DefaultHasher::new();
+    for article in &articles {
+        article.id().hash(&mut hasher);
+        article
+            .updated
+            .map(|updated| updated.timestamp())
+            .hash(&mut hasher);
+    }
+    let etag = format!(
+        "\"{:016x}-archive-{}-{}-{}\"",
+        hasher.finish(),
+        page,
+        pages,
+        format.extension()
+    );
+    Ok(respond(headers, format, negotiated, etag, updated, || {
+        format.render(&channel, &articles).into()
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+struct Search {
+    q: String,
+    feed: Option<String>,
+    format: Option<String>,
+    limit: Option<usize>,
+}
+
+#[derive(Debug)]
+struct CachedSearch {
+    query: SearchQuery,
+    updated: DateTime<Utc>,
+    hash: u64,
+    rendered: Rendered,
+}
+
+type SearchKey = (String, Option<String>, usize);
+
+static SEARCHES: LazyLock<Mutex<HashMap<SearchKey, CachedSearch>>> =
+    LazyLock::new(Default::default);
+
+async fn search(
+    feeds: &Mutex<HashMap<String, Feed>>,
+    store: Option<&Store>,
+    search: &Search,
+    format: Format,
+    negotiated: bool,
+    headers: &HeaderMap,
+) -> Result<impl IntoResponse, StatusCode> {
+    trace!("Entered search handler");
+    let store = store.ok_or(StatusCode::NOT_FOUND)?;
+    let (name, updated) = {
+        let feeds = feeds.lock().await;
+        match search.feed.as_deref() {
+            Some(slug) => {
+                let feed = find(&feeds, slug)?;
+                (Some(feed.name.clone()), feed.updated)
+            }
+            None => (
+                None,
+                feeds
+                    .values()
+                    .map(|feed| feed.updated)
+                    .max()
+                    .unwrap_or_else(Utc::now),
+            ),
+        }
+    };
+    let limit = search.limit.unwrap_or(SEARCH_LIMIT).min(SEARCH_LIMIT);
+    let key = (search.q.trim().to_owned(), name, limit);
+    let cached = SEARCHES.lock().await.remove(&key);
+    let cached = match cached {
+        Some(cached) if cached.updated == updated => cached,
+        cached => {
+            let query = match cached {
+                Some(cached) => cached.query,
+                None => search.q.parse::<SearchQuery>().map_err(|error| {
+                    trace!(?error, "Invalid search query");
+                    StatusCode::BAD_REQUEST
+                })?,
+            };
+            let articles = store
+                .search(&query, key.1.as_deref(), limit)
+                .map_err(|error| {
+                    warn!(?error, "Failed to search articles");
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+            let title = match &key.1 {
+                Some(name) => format!("{} - Search: {}", name, query),
+                None => format!("Search: {}", query),
+            };
+            let slug = match &key.1 {
+                Some(name) => format!("{}/search/{}", name.to_lowercase(), query),
+                None => format!("search/{}", query),
+            };
+            let channel = Channel {
+                description: Some(format!("Articles matching {:?}", query.to_string())),
+                slug,
+                ..Channel::new(title)
+            };
+            let mut hasher = DefaultHasher::new();
+            for article in &articles {
+                article.id().hash(&mut hasher);
+                article
+                    .updated
+                    .map(|updated| updated.timestamp())
+                    .hash(&mut hasher);
+            }
+            CachedSearch {
+                rendered: Rendered::new(&channel, &articles),
+                hash: hasher.finish(),
+                query,
+                updated,
+            }
+        }
+    };
+    let etag = format!("\"{:016x}-search-{}\"", cached.hash, format.extension());
+    let body = cached.rendered.get(format);
+    {
+        let mut searches = SEARCHES.lock().await;
+        if searches.len() >= SEARCH_CACHE_SIZE {
+            trace!("Clearing search cache");
+            searches.clear();
+        }
+        searches.insert(key, cached);
+    }
+    Ok(respond(headers, format, negotiated, etag, updated, || body))
+}
+
+async fn index(
+    feeds: &Mutex<HashMap<String, Feed>>,
+    registry: &Registry,
+    credentialed: Credentialed,
+) -> impl IntoResponse {
+    trace!("Entered index handler");
+    let names = registry.names();
+    let feeds = feeds.lock().await;
+    let feeds = names
+        .into_iter()
+        .filter(|name| credentialed.lists(registry, name))
+        .map(|name| (name, feeds.get(name)))
+        .collect::<Vec<_>>();
+    Html(render::index(&feeds))
+}
+
+/// Protected feeds still count towards readiness, but are only named to credentialed requests.
+fn ready(registry: &Registry, credentialed: Credentialed) -> (StatusCode, String) {
+    trace!("Entered readiness handler");
+    let now = Utc::now().timestamp_millis() as f64 / 1000.0;
+    let stale = registry
+        .sources
+        .iter()
+        .filter(|source| {
+            metrics::last_success(source.name())
+                .is_none_or(|last| now - last > (source.interval() * READY_INTERVALS).as_secs_f64())
+        })
+        .map(|source| source.name())
+        .collect::<Vec<_>>();
+    if stale.is_empty() {
+        return (StatusCode::OK, "ready\n".to_owned());
+    }
+    trace!(?stale, "Feeds not ready");
+    let named = stale
+        .into_iter()
+        .filter(|name| credentialed.lists(registry, name))
+        .collect::<Vec<_>>();
+    let body = if named.is_empty() {
+        "not ready\n".to_owned()
+    } else {
+        format!("stale: {}\n", named.join(", "))
+    };
+    (StatusCode::SERVICE_UNAVAILABLE, body)
+}
+
+#[derive(Debug, Serialize)]
+struct LastError {
+    at: DateTime<Utc>,
+    message: String,
+}
+
+#[derive(Debug, Serialize)]
+struct FeedStatus {
+    name: String,
+    articles: usize,
+    last_success: Option<DateTime<Utc>>,
+    last_error: Option<LastError>,
+    next_scrape: Option<DateTime<Utc>>,
+    average_duration_seconds: Option<f64>,
+    consecutive_failures: u32,
+    circuit_open: bool,
+}
+
+async fn status(
+    registry: &Registry,
+    feeds: &Mutex<HashMap<String, Feed>>,
+    credentialed: Credentialed,
+) -> Json<Vec<FeedStatus>> {
+    trace!("Entered status handler");
+    let feeds = feeds.lock().await;
+    Json(
+        registry
+            .sources
+            .iter()
+            .filter(|source| credentialed.lists(registry, source.name()))
+            .map(|source| {
+                let name = source.name();
+                let status = status::get(name);
+                FeedStatus {
+                    name: name.to_owned(),
+                    articles: feeds.get(name).map_or(0, |feed| feed.articles.len()),
+                    last_success: metrics::last_success(name)
+                        .and_then(|last| Utc.timestamp_millis_opt((last * 1000.0) as i64).single()),
+                    last_error: status
+                        .last_error
+                        .map(|(at, message)| LastError { at, message }),
+                    next_scrape: status.next_scrape,
+                    average_duration_seconds: metrics::average_duration(name),
+                    consecutive_failures: metrics::consecutive_failures(name),
+                    circuit_open: metrics::circuit_open(name),
+                }
+            })
+            .collect(),
+    )
+}
+
+async fn refresh_feed(
+    refresh: &HashMap<String, Arc<Notify>>,
+    refresh_token: Option<&str>,
+    slug: &str,
+    headers: &HeaderMap,
+) -> Result<StatusCode, StatusCode> {
+    trace!("Entered refresh handler");
+    let refresh_token = refresh_token.ok_or(StatusCode::FORBIDDEN)?;
+    let authorized = bearer(headers).is_some_and(|token| {
+        verify_slices_are_equal(token.as_bytes(), refresh_token.as_bytes()).is_ok()
+    });
+    if !authorized {
+        trace!("Invalid refresh token");
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    let (_, notify) = refresh
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(slug))
+        .ok_or(StatusCode::NOT_FOUND)?;
+    notify.notify_one();
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[derive(Debug, Deserialize)]
+struct Resource {
+    resource: String,
+}
+
+fn bearer(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))
+}
+
+fn admin(headers: &HeaderMap) -> Result<&'static Admin, StatusCode> {
+    let admin = admin::get().ok_or(StatusCode::FORBIDDEN)?;
+    if !admin.authorized(bearer(headers)) {
+        trace!("Invalid admin token");
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    Ok(admin)
+}
+
+async fn list_scrapers(headers: &HeaderMap) -> Result<impl IntoResponse, (StatusCode, String)> {
+    trace!("Entered scraper list handler");
+    let admin = self::admin(headers).map_err(|status| (status, String::new()))?;
+    admin.scrapers().await.map(Json).map_err(|error| {
+        warn!(?error, "Failed to read scrapers");
+        (StatusCode::INTERNAL_SERVER_ERROR, String::new())
+    })
+}
+
+/// Adds a scraper when `name` is `None`, and removes it when there is no body.
+async fn edit_scraper(
+    headers: &HeaderMap,
+    name: Option<&str>,
+    body: Option<&[u8]>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    trace!("Entered scraper edit handler");
+    let admin = self::admin(headers).map_err(|status| (status, String::new()))?;
+    let scraper = body
+        .map(serde_json::from_slice::<Table>)
+        .transpose()
+        .map_err(|error| (StatusCode::BAD_REQUEST, format!("{}\n", error)))?;
+    let adding = name.is_none();
+    match admin.edit(name, scraper).await {
+        Result::Ok(Edit::Done) if adding => Ok(StatusCode::CREATED),
+        Result::Ok(Edit::Done) => Ok(StatusCode::NO_CONTENT),
+        Result::Ok(Edit::NotFound) => Err((StatusCode::NOT_FOUND, String::new())),
+        Result::Ok(Edit::Conflict) => Err((
+            StatusCode::CONFLICT,
+            "A feed with that name already exists\n".to_owned(),
+        )),
+        Err(error) => {
+            trace!(?error, "Rejected scraper edit");
+            Err((StatusCode::UNPROCESSABLE_ENTITY, format!("{:#}\n", error)))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ScraperTest {
+    scraper: Table,
+    location: Option<String>,
+}
+
+/// Runs a scraper's selectors against its listing page, or another page, without saving it.
+async fn test_scraper(
+    client: &Client,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    trace!("Entered scraper test handler");
+    admin(headers).map_err(|status| (status, String::new()))?;
+    let test = serde_json::from_slice::<ScraperTest>(body)
+        .map_err(|error| (StatusCode::BAD_REQUEST, format!("{}\n", error)))?;
+    let scraper = Scraper::from_table(test.scraper)
+        .map_err(|error| (StatusCode::UNPROCESSABLE_ENTITY, format!("{:#}\n", error)))?;
+    let reports = match test.location.filter(|location| !location.is_empty()) {
+        // Anything else would be read as a local path.
+        Some(location) if !location.starts_with("http://") && !location.starts_with("https://") => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Expected an http or https URL\n".to_owned(),
+            ))
+        }
+        Some(location) => scraper.debug_selectors(client, &location).await,
+        None => scraper.debug_listing(client).await,
+    };
+    reports.map(Json).map_err(|error| {
+        trace!(?error, "Scraper test failed");
+        (StatusCode::BAD_GATEWAY, format!("{:#}\n", error))
+    })
+}
+
+fn activity_json(document: serde_json::Value) -> impl IntoResponse {
+    (
+        Headers(vec![(CONTENT_TYPE, activitypub::ACTIVITY_JSON)]),
+        document.to_string(),
+    )
+}
+
+fn actor_feed<'a>(
+    feeds: &'a HashMap<String, Feed>,
+    registry: &Registry,
+    username: &str,
+) -> Result<(&'a str, &'a Feed), StatusCode> {
+    activitypub::actor_feed(feeds, username, &registry.virtual_feeds).ok_or_else(|| {
+        trace!("Actor not found");
+        StatusCode::NOT_FOUND
+    })
+}
+
+async fn webfinger(
+    feeds: &Mutex<HashMap<String, Feed>>,
+    registry: &Registry,
+    resource: &str,
+) -> Result<impl IntoResponse, StatusCode> {
+    trace!("Entered webfinger handler");
+    let activitypub = activitypub::get().ok_or(StatusCode::NOT_FOUND)?;
+    let username = activitypub
+        .resource(resource)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    actor_feed(&*feeds.lock().await, registry, &username)?;
+    Ok((
+        Headers(vec![(CONTENT_TYPE, "application/jrd+json")]),
+        activitypub.webfinger(&username).to_string(),
+    ))
+}
+
+async fn actor(
+    feeds: &Mutex<HashMap<String, Feed>>,
+    registry: &Registry,
+    store: Option<&Store>,
+    username: &str,
+    document: &str,
+) -> Result<impl IntoResponse, StatusCode> {
+    trace!("Entered actor handler");
+    let activitypub = activitypub::get().ok_or(StatusCode::NOT_FOUND)?;
+    let feeds = feeds.lock().await;
+    let (name, feed) = actor_feed(&feeds, registry, username)?;
+    Ok(activity_json(match document {
+        "actor" => activitypub.actor(username, &feed.channel),
+        "outbox" => activitypub.outbox(username, &feed.articles),
+        "followers" => {
+            let followers = store
+                .map(|store| store.followers(name))
+                .transpose()
+                .map_err(|error| {
+                    warn!(?error, "Failed to load followers");
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+            activitypub.followers(username, followers.map_or(0, |followers| followers.len()))
+        }
+        _ => return Err(StatusCode::NOT_FOUND),
+    }))
+}
+
+/// Receives an activity for the actor of the named feed.
+async fn inbox(
+    client: &Client,
+    store: Option<&Store>,
+    name: &str,
+    uri: &Uri,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<StatusCode, StatusCode> {
+    trace!("Entered inbox handler");
+    let activitypub = activitypub::get().ok_or(StatusCode::NOT_FOUND)?;
+    let store = store.ok_or(StatusCode::NOT_FOUND)?;
+    let target = format!(
+        "post {}",
+        uri.path_and_query()
+            .map_or(uri.path(), |path| path.as_str())
+    );
+    activitypub
+        .receive(client, store, name, &target, headers, body)
+        .await
+        .map_err(|error| {
+            warn!(?error, "Rejected activity");
+            StatusCode::UNAUTHORIZED
+        })?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[instrument(skip(client, registry, feeds, store, refresh_token, shutdown))]
+pub async fn server(
+    listen: Listen,
+    client: Client,
+    registry: &'static watch::Receiver<Arc<Registry>>,
+    feeds: &'static Mutex<HashMap<String, Feed>>,
+    store: Option<&'static Store>,
+    refresh_token: Option<&'static str>,
+    mut shutdown: watch::Receiver<bool>,
+) -> Result<()> {
+    let tester = client.clone();
+    let app = Router::new()
+        .route(
+            "/:file",
+            get(
+                move |_: Throttled,
+                      _: Authorized,
+                      Path(file): Path<String>,
+                      Query(filter): Query<Filter>,
+                      query: Option<Query<Search>>,
+                      headers: HeaderMap| {
+                    let span = span!(Level::TRACE, "feed-handler", file = file.as_str());
+                    async move {
+                        match (Format::split(&file), query) {
+                            (Some(("search", format)), Some(Query(query))) => record(
+                                "search",
+                                search(feeds, store, &query, format, false, &headers).await,
+                            ),
+                            _ => record("feed", feed(feeds, &file, &filter, &headers).await),
+                        }
+                    }
+                    .instrument(span)
+                },
+            ),
+        )
+        .route(
+            "/:feed/:file",
+            get(
+                move |_: Throttled,
+                      _: Authorized,
+                      Path((slug, file)): Path<(String, String)>,
+                      Query(filter): Query<Filter>,
+                      headers: HeaderMap| {
+                    let span = span!(
+                        Level::TRACE,
+                        "section-handler",
+                        slug = slug.as_str(),
+                        file = file.as_str()
+                    );
+                    async move {
+                        record(
+                            "section",
+                            section(feeds, &slug, &file, &filter, &headers).await,
+                        )
+                    }
+                    .instrument(span)
+                },
+            ),
+        )
+        .route(
+            "/archive/:file",
+            get(
+                move |_: Throttled,
+                      _: Authorized,
+                      Path(file): Path<String>,
+                      Query(page): Query<Page>,
+                      headers: HeaderMap| {
+                    let span = span!(Level::TRACE, "archive-handler", file = file.as_str());
+                    async move {
+                        record(
+                            "archive",
+                            archive(&current(registry), feeds, store, &file, page.page, &headers)
+                                .await,
+                        )
+                    }
+                    .instrument(span)
+                },
+            ),
+        )
+        .route(
+            "/search",
+            get(
+                move |_: Throttled,
+                      _: Authorized,
+                      Query(query): Query<Search>,
+                      headers: HeaderMap| {
+                    let span = span!(Level::TRACE, "search-handler", q = query.q.as_str());
+                    async move {
+                        let format = match query.format.as_deref() {
+                            Some(extension) => {
+                                Format::from_extension(extension).map(|format| (format, false))
+                            }
+                            None => Some((negotiate(&headers), true)),
+                        };
+                        match format {
+                            Some((format, negotiated)) => record(
+                                "search",
+                                search(feeds, store, &query, format, negotiated, &headers).await,
+                            ),
+                            None => record("search", StatusCode::NOT_FOUND),
+                        }
+                    }
+                    .instrument(span)
+                },
+            ),
+        )
+        // Boxing every few routes keeps the router's type, and so compile times, in check.
+        .boxed()
+        .route(
+            "/refresh/:feed",
+            post(move |Path(slug): Path<String>, headers: HeaderMap| {
+                let span = span!(Level::TRACE, "refresh-handler", slug = slug.as_str());
+                let registry = current(registry);
+                async move {
+                    record(
+                        "refresh",
+                        refresh_feed(&registry.refresh, refresh_token, &slug, &headers).await,
+                    )
+                }
+                .instrument(span)
+            }),
+        )
+        .route(
+            "/api/scrapers",
+            get(move |headers: HeaderMap| async move {
+                record("scrapers", list_scrapers(&headers).await)
+            })
+            .post(move |headers: HeaderMap, body: Bytes| async move {
+                record("scrapers", edit_scraper(&headers, None, Some(&body)).await)
+            }),
+        )
+        .route(
+            "/api/scrapers/:name",
+            put(
+                move |Path(name): Path<String>, headers: HeaderMap, body: Bytes| async move {
+                    record(
+                        "scrapers",
+                        edit_scraper(&headers, Some(&name), Some(&body)).await,
+                    )
+                },
+            )
+            .delete(
+                move |Path(name): Path<String>, headers: HeaderMap| async move {
+                    record("scrapers", edit_scraper(&headers, Some(&name), None).await)
+                },
+            ),
+        )
+        .route(
+            "/api/scraper-test",
+            post(move |headers: HeaderMap, body: Bytes| {
+                let client = tester.clone();
+                async move { record("scrapers", test_scraper(&client, &headers, &body).await) }
+            }),
+        )
+        .boxed()
+        .route(
+            "/admin",
+            get(|| async {
+                match admin::get() {
+                    Some(_) => record("admin", Html(admin::PAGE)),
+                    None => record("admin", StatusCode::NOT_FOUND),
+                }
+            }),
+        )
+        .route(
+            "/",
+            get(move |credentialed: Credentialed| {
+                let registry = current(registry);
+                async move { record("index", index(feeds, &registry, credentialed).await) }
+            }),
+        )
+        .route(
+            "/opml",
+            get(move |credentialed: Credentialed, headers: HeaderMap| async move {
+                record("opml", opml(&current(registry), &headers, credentialed))
+            }),
+        )
+        .route(
+            "/img/:key",
+            get(move |Path(key): Path<String>, headers: HeaderMap| async move {
+                record("image", image(&key, false, &headers))
+            }),
+        )
+        .route(
+            "/img/:key/thumbnail",
+            get(move |Path(key): Path<String>, headers: HeaderMap| async move {
+                record("thumbnail", image(&key, true, &headers))
+            }),
+        )
+        .route(
+            render::STYLESHEET_PATH,
+            get(|| async {
+                record(
+                    "stylesheet",
+                    (
+                        Headers(vec![(CONTENT_TYPE, "text/xsl")]),
+                        render::STYLESHEET,
+                    ),
+                )
+            }),
+        )
+        .boxed()
+        .route("/healthz", get(|| async { record("healthz", "ok\n") }))
+        .route(
+            "/readyz",
+            get(move |credentialed: Credentialed| async move {
+                record("readyz", ready(&current(registry), credentialed))
+            }),
+        )
+        .route(
+            "/status",
+            get(move |credentialed: Credentialed| {
+                let registry = current(registry);
+                async move { record("status", status(&registry, feeds, credentialed).await) }
+            }),
+        )
+        .route(
+            "/metrics",
+            get(|| async {
+                record(
+                    "metrics",
+                    (
+                        Headers(vec![(CONTENT_TYPE, "text/plain; version=0.0.4")]),
+                        metrics::render(),
+                    ),
+                )
+            }),
+        )
+        .route(
+            "/.well-known/webfinger",
+            get(move |Query(resource): Query<Resource>| {
+                let registry = current(registry);
+                async move {
+                    record(
+                        "webfinger",
+                        webfinger(feeds, &registry, &resource.resource).await,
+                    )
+                }
+            }),
+        )
+        .route(
+            "/ap/:name",
+            get(move |Path(name): Path<String>| {
+                let registry = current(registry);
+                async move { record("actor", actor(feeds, &registry, store, &name, "actor").await) }
+            }),
+        )
+        .route(
+            "/ap/:name/:document",
+            get(move |Path((name, document)): Path<(String, String)>| {
+                let registry = current(registry);
+                async move {
+                    record(
+                        "actor",
+                        actor(feeds, &registry, store, &name, &document).await,
+                    )
+                }
+            })
+            .post(
+                move |Path((name, document)): Path<(String, String)>,
+                      uri: Uri,
+                      headers: HeaderMap,
+                      body: Bytes| {
+                    let span = span!(Level::TRACE, "inbox-handler", name = name.as_str());
+                    let registry = current(registry);
+                    async move {
+                        if document != "inbox" {
+                            return record("inbox", StatusCode::NOT_FOUND);
+                        }
+                        let feed = actor_feed(&*feeds.lock().await, &registry, &name)
+                            .map(|(feed, _)| feed.to_owned());
+                        record(
+                            "inbox",
+                            match feed {
+                                Ok(feed) => inbox(&client, store, &feed, &uri, &headers, &body).await,
+                                Err(status) => Err(status),
+                            },
+                        )
+                    }
+                    .instrument(span)
+                },
+            ),
+        )
+        .layer(AddExtensionLayer::new(registry));
+
+    let shutdown = async move {
+        while !*shutdown.borrow() {
+            if shutdown.changed().await.is_err() {
+                break;
+            }
+        }
+    };
+    match listen {
+        Listen::Tcp(address) => match tls::acceptor() {
+            Some(acceptor) => {
+                tokio::spawn(refresh_certificates());
+                let listener = TcpListener::bind(address)
+                    .await
+                    .with_context(|| format!("Failed to listen on {}", address))?;
+                let incoming = stream::unfold(listener, |listener| async move {
+                    let accepted = listener.accept().await;
+                    Some((accepted, listener))
+                })
+                .filter_map(|accepted| async move {
+                    match accepted {
+                        Result::Ok((stream, _)) => Some(stream),
+                        Err(error) => {
+                            // Most likely out of file descriptors, which takes a moment to ease.
+                            warn!(?error, "Failed to accept connection");
+                            tokio::time::sleep(Duration::from_secs(1)).await;
+                            None
+                        }
+                    }
+                })
+                .map(move |stream| timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)))
+                .buffer_unordered(MAX_HANDSHAKES)
+                .filter_map(|handshake| async move {
+                    match handshake {
+                        Result::Ok(Result::Ok(stream)) => Some(io::Result::Ok(stream)),
+                        Result::Ok(Err(error)) => {
+                            trace!(?error, "TLS handshake failed");
+                            None
+                        }
+                        Err(_) => {
+                            trace!("TLS handshake timed out");
+                            None
+                        }
+                    }
+                });
+                axum::Server::builder(accept::from_stream(incoming))
+                    .serve(app.into_make_service_with_connect_info::<Peer, &TlsStream<TcpStream>>())
+                    .with_graceful_shutdown(shutdown)
+                    .await?
+            }
+            None => {
+                axum::Server::bind(&address)
+                    .serve(app.into_make_service_with_connect_info::<Peer, &AddrStream>())
+                    .with_graceful_shutdown(shutdown)
+                    .await?
+            }
+        },
+        Listen::Unix(path) => {
+            let listener = bind_unix(&path)?;
+            let incoming = accept::from_stream(stream::poll_fn(move |cx| {
+                listener
+                    .poll_accept(cx)
+                    .map(|accepted| Some(accepted.map(|(stream, _)| stream)))
+            }));
+            let served = axum::Server::builder(incoming)
+                .serve(app.into_make_service_with_connect_info::<Peer, &UnixStream>())
+                .with_graceful_shutdown(shutdown)
+                .await;
+            fs::remove_file(&path).ok();
+            served?
+        }
+    }
+    Ok(())
+}
+
+// Renewals are picked up on SIGHUP too, but not every ACME client sends one.
+async fn refresh_certificates() {
+    let certificates = match tls::get() {
+        Some(certificates) => certificates,
+        None => return,
+    };
+    let mut interval = tokio::time::interval(CERTIFICATE_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(error) = certificates.refresh() {
+            warn!(
+                ?error,
+                "Failed to reload TLS certificate, keeping the current one"
+            );
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Listen {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl FromStr for Listen {
+    type Err = Error;
+
+    /// Accepts `unix:PATH`, `host:port`, or a bare port on every interface.
+    fn from_str(listen: &str) -> Result<Listen> {
+        if let Some(path) = listen.strip_prefix("unix:") {
+            ensure!(!path.is_empty(), "Expected a socket path after unix:");
+            return Ok(Listen::Unix(path.into()));
+        }
+        let (address, port) = listen.rsplit_once(':').unwrap_or(("0.0.0.0", listen));
+        let address = address.trim_start_matches('[').trim_end_matches(']');
+        Ok(Listen::Tcp(SocketAddr::new(
+            address
+                .parse()
+                .with_context(|| format!("Invalid listen address {:?}", listen))?,
+            port.parse()
+                .with_context(|| format!("Invalid listen port {:?}", listen))?,
+        )))
+    }
+}
+
+// Replaces a socket left behind by a previous run, but nothing else.
+fn bind_unix(path: &StdPath) -> Result<UnixListener> {
+    match fs::symlink_metadata(path) {
+        Result::Ok(metadata) if metadata.file_type().is_socket() => fs::remove_file(path)?,
+        Result::Ok(_) => bail!("{} exists and is not a socket", path.display()),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+        Err(error) => return Err(error.into()),
+    }
+    UnixListener::bind(path).with_context(|| format!("Failed to listen on {}", path.display()))
+}
@@ -0,0 +1,230 @@
+use anyhow::*;
+use chrono::Utc;
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufStream},
+    net::TcpStream,
+};
+use tokio_rustls::{
+    rustls::ClientConfig,
+    webpki::{DNSName, DNSNameRef},
+    TlsConnector,
+};
+use tracing::{instrument, trace};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Security {
+    /// TLS from the first byte, usually on port 465.
+    Tls,
+    /// Plain text upgraded with STARTTLS, usually on port 587.
+    #[default]
+    StartTls,
+    None,
+}
+
+impl Security {
+    pub fn default_port(self) -> u16 {
+        match self {
+            Security::Tls => 465,
+            Security::StartTls => 587,
+            Security::None => 25,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Smtp {
+    pub host: String,
+    pub port: u16,
+    pub security: Security,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub to: Vec<String>,
+    pub subject: String,
+    pub html: String,
+}
+
+trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+struct Connection {
+    stream: BufStream<Box<dyn Stream>>,
+}
+
+fn tls() -> TlsConnector {
+    let mut config = ClientConfig::new();
+    config
+        .root_store
+        .add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+    TlsConnector::from(Arc::new(config))
+}
+
+impl Connection {
+    async fn reply(&mut self) -> Result<(u16, String)> {
+        let mut text = String::new();
+        loop {
+            let mut line = String::new();
+            ensure!(
+                self.stream.read_line(&mut line).await? > 0,
+                "SMTP server closed the connection"
+            );
+            let line = line.trim_end();
+            let code = line
+                .get(..3)
+                .and_then(|code| code.parse().ok())
+                .with_context(|| format!("Invalid SMTP reply {:?}", line))?;
+            text.push_str(line.get(4..).unwrap_or_default());
+            text.push('\n');
+            if line.as_bytes().get(3) != Some(&b'-') {
+                trace!(code, "SMTP reply");
+                return Ok((code, text));
+            }
+        }
+    }
+
+    async fn expect(&mut self, class: u16) -> Result<String> {
+        let (code, text) = self.reply().await?;
+        ensure!(
+            code / 100 == class,
+            "Unexpected SMTP reply {}: {}",
+            code,
+            text.trim()
+        );
+        Ok(text)
+    }
+
+    async fn command(&mut self, command: &str, class: u16) -> Result<String> {
+        self.stream.write_all(command.as_bytes()).await?;
+        self.stream.write_all(b"\r\n").await?;
+        self.stream.flush().await?;
+        self.expect(class)
+            .await
+            .with_context(|| format!("SMTP command {} failed", verb(command)))
+    }
+}
+
+fn verb(command: &str) -> &str {
+    command.split(' ').next().unwrap_or(command)
+}
+
+fn header(text: &str) -> String {
+    if text.is_ascii() {
+        text.to_owned()
+    } else {
+        format!("=?utf-8?b?{}?=", base64::encode(text))
+    }
+}
+
+impl Message {
+    fn format(&self, from: &str) -> String {
+        let body = base64::encode(&self.html)
+            .as_bytes()
+            .chunks(76)
+            .map(|line| std::str::from_utf8(line).unwrap())
+            .collect::<Vec<_>>()
+            .join("\r\n");
+        format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMIME-Version: 1.0\r\nContent-Type: text/html; charset=utf-8\r\nContent-Transfer-Encoding: base64\r\n\r\n{}\r\n",
+            from,
+            self.to.join(", "),
+            header(&self.subject),
+            Utc::now().to_rfc2822(),
+            body
+        )
+    }
+}
+
+fn address(mailbox: &str) -> &str {
+    match (mailbox.rfind('<'), mailbox.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &mailbox[start + 1..end],
+        _ => mailbox.trim(),
+    }
+}
+
+impl Smtp {
+    fn name(&self) -> Result<DNSName> {
+        DNSNameRef::try_from_ascii_str(&self.host)
+            .map(DNSName::from)
+            .map_err(|_| anyhow!("TLS requires a DNS name for SMTP host {:?}", self.host))
+    }
+
+    async fn connect(&self) -> Result<Connection> {
+        let tcp = TcpStream::connect((self.host.as_str(), self.port))
+            .await
+            .with_context(|| format!("Failed to connect to {}:{}", self.host, self.port))?;
+        let stream: Box<dyn Stream> = match self.security {
+            Security::Tls => Box::new(tls().connect(self.name()?.as_ref(), tcp).await?),
+            Security::StartTls | Security::None => Box::new(tcp),
+        };
+        let mut connection = Connection {
+            stream: BufStream::new(stream),
+        };
+        connection.expect(2).await?;
+        let mut extensions = connection.command("EHLO news-rss", 2).await?;
+        if self.security == Security::StartTls {
+            ensure!(
+                extensions
+                    .lines()
+                    .any(|line| line.eq_ignore_ascii_case("STARTTLS")),
+                "SMTP server {} does not support STARTTLS",
+                self.host
+            );
+            connection.command("STARTTLS", 2).await?;
+            let tcp = connection.stream.into_inner();
+            connection = Connection {
+                stream: BufStream::new(Box::new(tls().connect(self.name()?.as_ref(), tcp).await?)),
+            };
+            extensions = connection.command("EHLO news-rss", 2).await?;
+        }
+        if let (Some(username), Some(password)) = (&self.username, &self.password) {
+            ensure!(
+                extensions.lines().any(|line| {
+                    let mut words = line.split_whitespace();
+                    words
+                        .next()
+                        .is_some_and(|word| word.eq_ignore_ascii_case("AUTH"))
+                        && words.any(|word| word.eq_ignore_ascii_case("PLAIN"))
+                }),
+                "SMTP server {} does not support AUTH PLAIN",
+                self.host
+            );
+            let credentials = base64::encode(format!("\0{}\0{}", username, password));
+            connection
+                .command(&format!("AUTH PLAIN {}", credentials), 2)
+                .await?;
+        }
+        Ok(connection)
+    }
+
+    #[instrument(skip(self, message), fields(host = self.host.as_str(), subject = message.subject.as_str()))]
+    pub async fn send(&self, message: &Message) -> Result<()> {
+        ensure!(!message.to.is_empty(), "Email has no recipients");
+        let mut connection = self.connect().await?;
+        connection
+            .command(&format!("MAIL FROM:<{}>", address(&self.from)), 2)
+            .await?;
+        for to in &message.to {
+            connection
+                .command(&format!("RCPT TO:<{}>", address(to)), 2)
+                .await?;
+        }
+        connection.command("DATA", 3).await?;
+        // The body is base64, so no line can start with a dot.
+        connection
+            .stream
+            .write_all(message.format(&self.from).as_bytes())
+            .await?;
+        connection.command(".", 2).await?;
+        connection.command("QUIT", 2).await.ok();
+        trace!("Sent email");
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{render::Channel, store::Store, Adaptive, Retention, Scraped, Scraper, Stagger};
+use anyhow::*;
+use async_trait::async_trait;
+use reqwest::Client;
+use std::{fmt::Debug, time::Duration};
+
+#[async_trait(?Send)]
+pub trait Source: Debug + Send + Sync {
+    fn name(&self) -> &str;
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(60 * 60)
+    }
+
+    fn retention(&self) -> Retention {
+        Retention::DEFAULT
+    }
+
+    fn stagger(&self) -> Stagger {
+        Stagger::DEFAULT
+    }
+
+    /// Bounds to adapt the interval within, if it should follow how often the source publishes.
+    fn adaptive(&self) -> Option<Adaptive> {
+        None
+    }
+
+    fn channel(&self) -> Channel {
+        Channel::new(self.name())
+    }
+
+    async fn fetch(&self, client: &Client, store: Option<&Store>) -> Result<Option<Scraped>>;
+}
+
+#[async_trait(?Send)]
+impl Source for Scraper {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    fn retention(&self) -> Retention {
+        self.retention
+    }
+
+    fn stagger(&self) -> Stagger {
+        self.stagger
+    }
+
+    fn adaptive(&self) -> Option<Adaptive> {
+        self.adaptive
+    }
+
+    fn channel(&self) -> Channel {
+        Channel {
+            description: self.description.as_deref().map(str::to_owned),
+            language: self.language.as_deref().map(str::to_owned),
+            link: Some(self.base_url.to_string()),
+            ttl: Some(self.interval),
+            bump_pub_date: self.bump_pub_date,
+            max_items: self.max_items,
+            ..Channel::new(self.name())
+        }
+    }
+
+    async fn fetch(&self, client: &Client, store: Option<&Store>) -> Result<Option<Scraped>> {
+        self.get_articles(client, store).await
+    }
+}
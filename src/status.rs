@@ -0,0 +1,44 @@
+use chrono::{DateTime, Utc};
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct Status {
+    pub last_error: Option<(DateTime<Utc>, String)>,
+    pub next_scrape: Option<DateTime<Utc>>,
+}
+
+static STATUS: LazyLock<Mutex<HashMap<String, Status>>> = LazyLock::new(Default::default);
+
+pub fn failed(feed: &str, error: &anyhow::Error) {
+    failed_at(feed, Utc::now(), error.to_string());
+}
+
+pub fn failed_at(feed: &str, at: DateTime<Utc>, message: String) {
+    STATUS
+        .lock()
+        .unwrap()
+        .entry(feed.to_owned())
+        .or_default()
+        .last_error = Some((at, message));
+}
+
+pub fn scheduled(feed: &str, at: DateTime<Utc>) {
+    STATUS
+        .lock()
+        .unwrap()
+        .entry(feed.to_owned())
+        .or_default()
+        .next_scrape = Some(at);
+}
+
+pub fn get(feed: &str) -> Status {
+    STATUS
+        .lock()
+        .unwrap()
+        .get(feed)
+        .cloned()
+        .unwrap_or_default()
+}
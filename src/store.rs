@@ -0,0 +1,499 @@
+use crate::{feed::content_hash, Article, Image, Query};
+use anyhow::*;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use nipper::Document;
+use reqwest::Url;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::{path::Path, sync::Mutex};
+use tracing::{instrument, trace};
+
+#[derive(Debug)]
+pub struct Store {
+    connection: Mutex<Connection>,
+}
+
+/// How a source's latest scrape went, kept so a restart carries on its schedule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LastScrape {
+    pub at: DateTime<Utc>,
+    pub succeeded: Option<DateTime<Utc>>,
+    /// Consecutive failures, which the circuit and backoff pick up from.
+    pub failures: u32,
+    pub error: Option<(DateTime<Utc>, String)>,
+}
+
+fn timestamp(text: &str) -> Result<DateTime<Utc>> {
+    Ok(DateTime::parse_from_rfc3339(text)?.with_timezone(&Utc))
+}
+
+fn last_scrape(row: &Row) -> Result<LastScrape> {
+    let scraped_at: String = row.get("scraped_at")?;
+    let succeeded_at: Option<String> = row.get("succeeded_at")?;
+    let failed_at: Option<String> = row.get("failed_at")?;
+    let error: Option<String> = row.get("error")?;
+    Ok(LastScrape {
+        at: timestamp(&scraped_at)?,
+        succeeded: succeeded_at.as_deref().map(timestamp).transpose()?,
+        failures: row.get("failures")?,
+        error: match (failed_at, error) {
+            (Some(at), Some(error)) => Some((timestamp(&at)?, error)),
+            _ => None,
+        },
+    })
+}
+
+fn article(row: &Row) -> Result<Article> {
+    let link: String = row.get("link")?;
+    let image: Option<String> = row.get("image")?;
+    let date: String = row.get("date")?;
+    let timezone: String = row.get("timezone")?;
+    let timezone = timezone.parse::<Tz>().map_err(|e| anyhow!(e))?;
+    let categories: Option<String> = row.get("categories")?;
+    let images: Option<String> = row.get("images")?;
+    let updated: Option<String> = row.get("updated")?;
+    Ok(Article {
+        headline: row.get("headline")?,
+        link: link.parse()?,
+        body: row.get("body")?,
+        description: row.get("description")?,
+        language: row.get("language")?,
+        image: image
+            .map(|image| -> Result<Image> {
+                Ok(Image {
+                    url: image.parse()?,
+                    mime_type: row.get("image_type")?,
+                    length: row
+                        .get::<_, Option<i64>>("image_length")?
+                        .map(|length| length as u64),
+                })
+            })
+            .transpose()?,
+        images: images
+            .map(|images| serde_json::from_str::<Vec<String>>(&images))
+            .transpose()?
+            .unwrap_or_default()
+            .into_iter()
+            .map(|url| -> Result<Image> {
+                Ok(Image {
+                    url: url.parse()?,
+                    mime_type: None,
+                    length: None,
+                })
+            })
+            .collect::<Result<_>>()?,
+        author: row.get("author")?,
+        categories: categories
+            .map(|categories| serde_json::from_str(&categories))
+            .transpose()?
+            .unwrap_or_default(),
+        date: DateTime::parse_from_rfc3339(&date)?.with_timezone(&timezone),
+        updated: updated
+            .map(|updated| DateTime::parse_from_rfc3339(&updated))
+            .transpose()?
+            .map(|updated| updated.with_timezone(&timezone)),
+        guid: row.get("guid")?,
+        alternates: Vec::new(),
+    })
+}
+
+const COLUMNS: &[(&str, &str)] = &[
+    ("author", "TEXT"),
+    ("categories", "TEXT"),
+    ("image_type", "TEXT"),
+    ("image_length", "INTEGER"),
+    ("images", "TEXT"),
+    ("guid", "TEXT"),
+    ("updated", "TEXT"),
+    ("content_hash", "INTEGER"),
+    ("description", "TEXT"),
+    ("language", "TEXT"),
+];
+
+fn text(html: &str) -> String {
+    Document::from(html).select("body").text().to_string()
+}
+
+fn index(connection: &Connection, feed: &str, article: &Article) -> Result<()> {
+    connection.execute(
+        "DELETE FROM articles_search WHERE feed = ?1 AND link = ?2",
+        params![feed, article.link.as_str()],
+    )?;
+    connection.execute(
+        "INSERT INTO articles_search (feed, link, headline, body) VALUES (?1, ?2, ?3, ?4)",
+        params![
+            feed,
+            article.link.as_str(),
+            article.headline,
+            text(&article.body)
+        ],
+    )?;
+    Ok(())
+}
+
+fn migrate_search(connection: &Connection) -> Result<()> {
+    let exists = connection
+        .query_row(
+            "SELECT 1 FROM sqlite_master WHERE name = 'articles_search'",
+            [],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some();
+    if exists {
+        return Ok(());
+    }
+    connection.execute_batch(
+        "CREATE VIRTUAL TABLE articles_search USING fts5(
+            feed UNINDEXED,
+            link UNINDEXED,
+            headline,
+            body,
+            tokenize = 'porter unicode61'
+        );",
+    )?;
+    let mut statement = connection.prepare("SELECT * FROM articles")?;
+    let articles = statement
+        .query_map([], |row| Ok((row.get::<_, String>("feed")?, article(row))))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    trace!(articles = articles.len(), "Indexing stored articles");
+    for (feed, article) in articles {
+        index(connection, &feed, &article?)?;
+    }
+    Ok(())
+}
+
+fn migrate(connection: &Connection) -> Result<()> {
+    let existing = connection
+        .prepare("SELECT name FROM pragma_table_info('articles')")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for (column, kind) in COLUMNS {
+        if !existing.iter().any(|name| name == column) {
+            connection.execute_batch(&format!(
+                "ALTER TABLE articles ADD COLUMN {} {};",
+                column, kind
+            ))?;
+        }
+    }
+    Ok(())
+}
+
+impl Store {
+    #[instrument(skip(path), fields(path = ?path.as_ref()))]
+    pub fn open(path: impl AsRef<Path>) -> Result<Store> {
+        let connection = Connection::open(path.as_ref())
+            .with_context(|| format!("Failed to open store {:?}", path.as_ref()))?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS articles (
+                feed TEXT NOT NULL,
+                link TEXT NOT NULL,
+                headline TEXT NOT NULL,
+                body TEXT NOT NULL,
+                image TEXT,
+                date TEXT NOT NULL,
+                timezone TEXT NOT NULL,
+                scraped_at TEXT NOT NULL,
+                PRIMARY KEY (feed, link)
+            );
+            CREATE TABLE IF NOT EXISTS posted (
+                target TEXT NOT NULL,
+                link TEXT NOT NULL,
+                posted_at TEXT NOT NULL,
+                PRIMARY KEY (target, link)
+            );
+            CREATE TABLE IF NOT EXISTS followers (
+                feed TEXT NOT NULL,
+                actor TEXT NOT NULL,
+                inbox TEXT NOT NULL,
+                followed_at TEXT NOT NULL,
+                PRIMARY KEY (feed, actor)
+            );
+            CREATE TABLE IF NOT EXISTS aliases (
+                feed TEXT NOT NULL,
+                alias TEXT NOT NULL,
+                link TEXT NOT NULL,
+                PRIMARY KEY (feed, alias)
+            );
+            CREATE TABLE IF NOT EXISTS scrapes (
+                feed TEXT PRIMARY KEY,
+                scraped_at TEXT NOT NULL,
+                succeeded_at TEXT,
+                failures INTEGER NOT NULL,
+                failed_at TEXT,
+                error TEXT
+            );",
+        )?;
+        migrate(&connection)?;
+        migrate_search(&connection)?;
+        Ok(Store {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    #[instrument(skip(self))]
+    pub fn load(&self, feed: &str) -> Result<Vec<Article>> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare(
+            "SELECT * FROM articles
+            WHERE feed = ?1 AND scraped_at = (SELECT MAX(scraped_at) FROM articles WHERE feed = ?1)
+            ORDER BY rowid",
+        )?;
+        let articles = statement
+            .query_map(params![feed], |row| Ok(article(row)))?
+            .map(|row| row?)
+            .collect();
+        articles
+    }
+
+    /// How many articles have been stored for `feed`.
+    #[instrument(skip(self))]
+    pub fn count(&self, feed: &str) -> Result<usize> {
+        let count: i64 = self.connection.lock().unwrap().query_row(
+            "SELECT COUNT(*) FROM articles WHERE feed = ?1",
+            params![feed],
+            |row| row.get(0),
+        )?;
+        Ok(count as usize)
+    }
+
+    /// Up to `limit` of the articles stored for `feed`, oldest first, after skipping the `offset`
+    /// oldest. Counting from the oldest keeps a range the same as newer articles are added.
+    #[instrument(skip(self))]
+    pub fn history(&self, feed: &str, offset: usize, limit: usize) -> Result<Vec<Article>> {
+        let connection = self.connection.lock().unwrap();
+        // Dates keep their own offsets, so they're only ordered once SQLite has normalised them.
+        let mut statement = connection.prepare(
+            "SELECT * FROM articles WHERE feed = ?1
+            ORDER BY julianday(date), COALESCE(guid, link)
+            LIMIT ?2 OFFSET ?3",
+        )?;
+        let articles = statement
+            .query_map(params![feed, limit as i64, offset as i64], |row| {
+                Ok(article(row))
+            })?
+            .map(|row| row?)
+            .collect();
+        articles
+    }
+
+    #[instrument(skip(self), fields(query = %query))]
+    pub fn search(&self, query: &Query, feed: Option<&str>, limit: usize) -> Result<Vec<Article>> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare(
+            "SELECT articles.* FROM articles_search
+            JOIN articles ON articles.feed = articles_search.feed AND articles.link = articles_search.link
+            WHERE articles_search MATCH ?1 AND (?2 IS NULL OR articles_search.feed = ?2)
+            ORDER BY rank
+            LIMIT ?3",
+        )?;
+        let articles = statement
+            .query_map(params![query.compiled(), feed, limit as i64], |row| {
+                Ok(article(row))
+            })?
+            .map(|row| row?)
+            .collect();
+        articles
+    }
+
+    /// The article stored under `link`, or under the link it's an alias of.
+    #[instrument(skip(self), fields(link = link.as_str()))]
+    pub fn get(&self, feed: &str, link: &Url) -> Result<Option<Article>> {
+        self.connection
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT * FROM articles WHERE feed = ?1 AND link =
+                COALESCE((SELECT link FROM aliases WHERE feed = ?1 AND alias = ?2), ?2)",
+                params![feed, link.as_str()],
+                |row| Ok(article(row)),
+            )
+            .optional()?
+            .transpose()
+    }
+
+    /// Remembers that `alias`, such as the link a listing gave, leads to the article stored under
+    /// `link`, such as the canonical link its page gave.
+    #[instrument(skip(self), fields(alias = alias.as_str(), link = link.as_str()))]
+    pub fn alias(&self, feed: &str, alias: &Url, link: &Url) -> Result<()> {
+        self.connection.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO aliases (feed, alias, link) VALUES (?1, ?2, ?3)",
+            params![feed, alias.as_str(), link.as_str()],
+        )?;
+        Ok(())
+    }
+
+    #[instrument(skip(self, articles))]
+    pub fn save(&self, feed: &str, articles: &[Article]) -> Result<()> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        let scraped_at = Utc::now().to_rfc3339();
+        for article in articles {
+            if let Some(guid) = &article.guid {
+                transaction.execute(
+                    "DELETE FROM articles_search WHERE feed = ?1 AND link IN
+                    (SELECT link FROM articles WHERE feed = ?1 AND guid = ?2 AND link != ?3)",
+                    params![feed, guid, article.link.as_str()],
+                )?;
+                transaction.execute(
+                    "DELETE FROM articles WHERE feed = ?1 AND guid = ?2 AND link != ?3",
+                    params![feed, guid, article.link.as_str()],
+                )?;
+            }
+            // SQLite integers are signed, so the hash is stored as its bit pattern.
+            let hash = content_hash(article) as i64;
+            // Unchanged articles only need marking as part of this scrape, which keeps their
+            // rows and search index entries from being rewritten every time.
+            let unchanged = transaction.execute(
+                "UPDATE articles SET scraped_at = ?3, updated = ?4
+                WHERE feed = ?1 AND link = ?2 AND content_hash = ?5",
+                params![
+                    feed,
+                    article.link.as_str(),
+                    scraped_at,
+                    article.updated.map(|updated| updated.to_rfc3339()),
+                    hash
+                ],
+            )?;
+            if unchanged > 0 {
+                continue;
+            }
+            transaction.execute(
+                "INSERT OR REPLACE INTO articles (feed, link, headline, body, image, date, timezone, scraped_at, author, categories, image_type, image_length, images, guid, updated, content_hash, description, language)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+                params![
+                    feed,
+                    article.link.as_str(),
+                    article.headline,
+                    article.body,
+                    article.image.as_ref().map(|image| image.url.as_str()),
+                    article.date.to_rfc3339(),
+                    article.date.timezone().name(),
+                    scraped_at,
+                    article.author,
+                    serde_json::to_string(&article.categories)?,
+                    article.image.as_ref().and_then(|image| image.mime_type.as_deref()),
+                    article
+                        .image
+                        .as_ref()
+                        .and_then(|image| image.length)
+                        .map(|length| length as i64),
+                    serde_json::to_string(
+                        &article
+                            .images
+                            .iter()
+                            .map(|image| image.url.as_str())
+                            .collect::<Vec<_>>()
+                    )?,
+                    article.guid,
+                    article.updated.map(|updated| updated.to_rfc3339()),
+                    hash,
+                    article.description,
+                    article.language,
+                ],
+            )?;
+            index(&transaction, feed, article)?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    #[instrument(skip(self), fields(link = link.as_str()))]
+    pub fn posted(&self, target: &str, link: &Url) -> Result<bool> {
+        Ok(self
+            .connection
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT 1 FROM posted WHERE target = ?1 AND link = ?2",
+                params![target, link.as_str()],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some())
+    }
+
+    #[instrument(skip(self), fields(link = link.as_str()))]
+    pub fn mark_posted(&self, target: &str, link: &Url) -> Result<()> {
+        self.connection.lock().unwrap().execute(
+            "INSERT OR IGNORE INTO posted (target, link, posted_at) VALUES (?1, ?2, ?3)",
+            params![target, link.as_str(), Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    #[instrument(skip(self, inbox), fields(inbox = inbox.as_str()))]
+    pub fn add_follower(&self, feed: &str, actor: &str, inbox: &Url) -> Result<()> {
+        self.connection.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO followers (feed, actor, inbox, followed_at) VALUES (?1, ?2, ?3, ?4)",
+            params![feed, actor, inbox.as_str(), Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    pub fn remove_follower(&self, feed: &str, actor: &str) -> Result<()> {
+        self.connection.lock().unwrap().execute(
+            "DELETE FROM followers WHERE feed = ?1 AND actor = ?2",
+            params![feed, actor],
+        )?;
+        Ok(())
+    }
+
+    /// Returns each follower's actor ID and the inbox to deliver to.
+    #[instrument(skip(self))]
+    pub fn followers(&self, feed: &str) -> Result<Vec<(String, Url)>> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection
+            .prepare("SELECT actor, inbox FROM followers WHERE feed = ?1 ORDER BY rowid")?;
+        let followers = statement
+            .query_map(params![feed], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .map(|row| {
+                let (actor, inbox) = row?;
+                Ok((actor, inbox.parse()?))
+            })
+            .collect();
+        followers
+    }
+
+    #[instrument(skip(self, scrape))]
+    pub fn record_scrape(&self, feed: &str, scrape: &LastScrape) -> Result<()> {
+        let (failed_at, error) = scrape.error.clone().unzip();
+        self.connection.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO scrapes (feed, scraped_at, succeeded_at, failures, failed_at, error)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                feed,
+                scrape.at.to_rfc3339(),
+                scrape.succeeded.map(|at| at.to_rfc3339()),
+                scrape.failures,
+                failed_at.map(|at| at.to_rfc3339()),
+                error,
+            ],
+        )?;
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    pub fn last_scrape(&self, feed: &str) -> Result<Option<LastScrape>> {
+        self.connection
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT scraped_at, succeeded_at, failures, failed_at, error FROM scrapes
+                WHERE feed = ?1",
+                params![feed],
+                |row| Ok(last_scrape(row)),
+            )
+            .optional()?
+            .transpose()
+    }
+
+    #[instrument(skip(self))]
+    pub fn flush(&self) -> Result<()> {
+        self.connection.lock().unwrap().cache_flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,204 @@
+use crate::{escape_html, store::Store, Article};
+use anyhow::*;
+use reqwest::{header::CONTENT_TYPE, Client, StatusCode, Url};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    collections::HashSet,
+    sync::{LazyLock, Mutex as SyncMutex, OnceLock},
+    time::Duration,
+};
+use tokio::{
+    sync::Mutex,
+    time::{sleep, sleep_until, Instant},
+};
+use tracing::{instrument, trace, warn};
+
+const CAPTION_LENGTH: usize = 1024;
+
+#[derive(Debug, Clone)]
+pub struct Telegram {
+    pub token: String,
+    pub chat_id: String,
+    pub api_url: Url,
+    /// Minimum time between posts, to stay inside the Bot API's per-chat limits.
+    pub interval: Duration,
+    /// Feeds to post; every feed when empty.
+    pub feeds: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Response {
+    ok: bool,
+    description: Option<String>,
+    parameters: Option<Parameters>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Parameters {
+    retry_after: Option<u64>,
+}
+
+static TELEGRAM: OnceLock<Telegram> = OnceLock::new();
+static LAST_POST: LazyLock<Mutex<Option<Instant>>> = LazyLock::new(Default::default);
+// Used instead of the store when running without one.
+static POSTED: LazyLock<SyncMutex<HashSet<String>>> = LazyLock::new(Default::default);
+
+pub fn configure(telegram: Telegram) {
+    TELEGRAM.set(telegram).ok();
+}
+
+fn caption(article: &Article) -> String {
+    let link = escape_html(article.link.as_str());
+    let room = CAPTION_LENGTH.saturating_sub(link.chars().count() + 1);
+    let headline = match article.headline.char_indices().nth(room.saturating_sub(1)) {
+        Some((index, _)) => format!("{}…", &article.headline[..index]),
+        None => article.headline.clone(),
+    };
+    format!("<b>{}</b>\n{}", escape_html(&headline), link)
+}
+
+impl Telegram {
+    fn target(&self) -> String {
+        format!("telegram:{}", self.chat_id)
+    }
+
+    fn posted(&self, store: Option<&Store>, article: &Article) -> Result<bool> {
+        match store {
+            Some(store) => store.posted(&self.target(), &article.link),
+            None => Ok(POSTED.lock().unwrap().contains(article.link.as_str())),
+        }
+    }
+
+    fn mark_posted(&self, store: Option<&Store>, article: &Article) -> Result<()> {
+        match store {
+            Some(store) => store.mark_posted(&self.target(), &article.link),
+            None => {
+                POSTED
+                    .lock()
+                    .unwrap()
+                    .insert(article.link.as_str().to_owned());
+                Ok(())
+            }
+        }
+    }
+
+    async fn call(&self, client: &Client, method: &str, body: &Value) -> Result<Response> {
+        let url = self
+            .api_url
+            .join(&format!("./bot{}/{}", self.token, method))?;
+        loop {
+            // Request errors include the URL, which contains the bot token.
+            let redact =
+                |error: reqwest::Error| anyhow!(error.to_string().replace(&self.token, "…"));
+            let response = client
+                .post(url.clone())
+                .header(CONTENT_TYPE, "application/json")
+                .body(body.to_string())
+                .send()
+                .await
+                .map_err(redact)?;
+            let status = response.status();
+            let text = response.text().await.map_err(redact)?;
+            let response: Response = serde_json::from_str(&text)
+                .with_context(|| format!("Invalid Bot API response with status {}", status))?;
+            let retry_after = response
+                .parameters
+                .as_ref()
+                .and_then(|parameters| parameters.retry_after);
+            match (status, retry_after) {
+                (StatusCode::TOO_MANY_REQUESTS, Some(retry_after)) => {
+                    trace!(retry_after, "Rate limited by the Bot API");
+                    sleep(Duration::from_secs(retry_after)).await;
+                }
+                _ => return Ok(response),
+            }
+        }
+    }
+
+    async fn send(&self, client: &Client, article: &Article) -> Result<()> {
+        if let Some(image) = &article.image {
+            let response = self
+                .call(
+                    client,
+                    "sendPhoto",
+                    &json!({
+                        "chat_id": self.chat_id,
+                        "photo": image.url.as_str(),
+                        "caption": caption(article),
+                        "parse_mode": "HTML",
+                    }),
+                )
+                .await?;
+            if response.ok {
+                return Ok(());
+            }
+            // Telegram refuses images it cannot fetch, so fall back to a plain message.
+            trace!(?response.description, "Failed to send photo");
+        }
+        let response = self
+            .call(
+                client,
+                "sendMessage",
+                &json!({
+                    "chat_id": self.chat_id,
+                    "text": caption(article),
+                    "parse_mode": "HTML",
+                }),
+            )
+            .await?;
+        ensure!(
+            response.ok,
+            "Bot API error: {}",
+            response.description.unwrap_or_default()
+        );
+        Ok(())
+    }
+}
+
+/// Posts articles that have not been posted to the channel before, oldest first.
+#[instrument(skip(client, store, articles), fields(articles = articles.len()))]
+pub async fn post(client: &Client, store: Option<&Store>, feed: &str, articles: &[Article]) {
+    let telegram = match TELEGRAM.get() {
+        Some(telegram) => telegram,
+        None => return,
+    };
+    if !telegram.feeds.is_empty()
+        && !telegram
+            .feeds
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(feed))
+    {
+        return;
+    }
+    for article in articles.iter().rev() {
+        match telegram.posted(store, article) {
+            Ok(false) => {}
+            Ok(true) => continue,
+            Err(error) => {
+                warn!(?error, "Failed to check posted articles");
+                continue;
+            }
+        }
+        let mut last = LAST_POST.lock().await;
+        if let Some(last) = *last {
+            sleep_until(last + telegram.interval).await;
+        }
+        let sent = telegram.send(client, article).await;
+        *last = Some(Instant::now());
+        drop(last);
+        match sent {
+            Ok(()) => {
+                trace!(link = article.link.as_str(), "Posted to Telegram");
+                if let Err(error) = telegram.mark_posted(store, article) {
+                    warn!(?error, "Failed to record posted article");
+                }
+            }
+            Err(error) => warn!(
+                ?error,
+                link = article.link.as_str(),
+                "Failed to post to Telegram"
+            ),
+        }
+    }
+}
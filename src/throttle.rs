@@ -0,0 +1,107 @@
+use ipnet::IpNet;
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+/// Clients whose buckets have refilled are forgotten once this many are tracked.
+const MAX_TRACKED: usize = 10_000;
+
+#[derive(Debug, Clone)]
+pub struct Throttle {
+    pub requests_per_minute: u32,
+    /// Proxies whose `X-Forwarded-For` is believed. Connections over a Unix socket always are.
+    pub trusted_proxies: Vec<IpNet>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+#[derive(Debug)]
+pub struct Limiter {
+    throttle: Throttle,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+static LIMITER: OnceLock<Limiter> = OnceLock::new();
+
+pub fn configure(throttle: Throttle) {
+    LIMITER.set(Limiter::new(throttle)).ok();
+}
+
+pub fn get() -> Option<&'static Limiter> {
+    LIMITER.get()
+}
+
+impl Throttle {
+    fn trusted(&self, address: IpAddr) -> bool {
+        self.trusted_proxies
+            .iter()
+            .any(|proxy| proxy.contains(&address))
+    }
+
+    /// The address a request came from: the peer, unless it is a trusted proxy, in which case
+    /// the nearest untrusted hop in `X-Forwarded-For`. Clients can prepend anything to that
+    /// header, so it is read right to left.
+    pub fn client(&self, peer: Option<IpAddr>, forwarded_for: Option<&str>) -> Option<IpAddr> {
+        if peer.is_some_and(|peer| !self.trusted(peer)) {
+            return peer;
+        }
+        let hops = forwarded_for
+            .into_iter()
+            .flat_map(|header| header.split(','))
+            .filter_map(|hop| hop.trim().parse::<IpAddr>().ok())
+            .collect::<Vec<_>>();
+        hops.iter()
+            .rev()
+            .copied()
+            .find(|hop| !self.trusted(*hop))
+            .or_else(|| hops.first().copied())
+            .or(peer)
+    }
+}
+
+impl Limiter {
+    pub fn new(throttle: Throttle) -> Limiter {
+        Limiter {
+            throttle,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn throttle(&self) -> &Throttle {
+        &self.throttle
+    }
+
+    /// Takes a request from the client's allowance, which refills evenly over each minute.
+    /// Returns how long to wait when it is used up.
+    pub fn check(&self, client: IpAddr, now: Instant) -> Result<(), Duration> {
+        let capacity = f64::from(self.throttle.requests_per_minute.max(1));
+        let rate = capacity / 60.0;
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED {
+            buckets.retain(|_, bucket| {
+                bucket.tokens + now.saturating_duration_since(bucket.updated).as_secs_f64() * rate
+                    < capacity
+            });
+        }
+        let bucket = buckets.entry(client).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(capacity);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / rate))
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::*;
+use std::{
+    fs::{self, File},
+    io::BufReader,
+    path::PathBuf,
+    sync::{Arc, OnceLock, RwLock},
+    time::SystemTime,
+};
+use tokio_rustls::{
+    rustls::{
+        internal::pemfile,
+        sign::{self, CertifiedKey},
+        ClientHello, NoClientAuth, ResolvesServerCert, ServerConfig,
+    },
+    TlsAcceptor,
+};
+use tracing::{info, instrument};
+
+#[derive(Debug, Clone)]
+pub struct Tls {
+    /// PEM certificate chain, leaf first, as written by certbot and most ACME clients.
+    pub certificate: PathBuf,
+    /// PEM private key, PKCS#8 or PKCS#1 RSA.
+    pub private_key: PathBuf,
+}
+
+struct Loaded {
+    modified: (SystemTime, SystemTime),
+    key: CertifiedKey,
+}
+
+/// The current certificate, swapped out when the files on disk change.
+pub struct Certificates {
+    tls: Tls,
+    loaded: RwLock<Loaded>,
+}
+
+static CERTIFICATES: OnceLock<Arc<Certificates>> = OnceLock::new();
+
+pub fn configure(certificates: Certificates) {
+    CERTIFICATES.set(Arc::new(certificates)).ok();
+}
+
+pub fn get() -> Option<&'static Arc<Certificates>> {
+    CERTIFICATES.get()
+}
+
+/// An acceptor that always presents the latest loaded certificate.
+pub fn acceptor() -> Option<TlsAcceptor> {
+    let certificates = get()?;
+    let mut config = ServerConfig::new(NoClientAuth::new());
+    config.cert_resolver = certificates.clone();
+    config.set_protocols(&[b"http/1.1".to_vec()]);
+    Some(Arc::new(config).into())
+}
+
+fn modified(tls: &Tls) -> Result<(SystemTime, SystemTime)> {
+    let modified = |path: &PathBuf| -> Result<SystemTime> {
+        fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .with_context(|| format!("Failed to read {}", path.display()))
+    };
+    Ok((modified(&tls.certificate)?, modified(&tls.private_key)?))
+}
+
+fn load(tls: &Tls) -> Result<CertifiedKey> {
+    let open = |path: &PathBuf| {
+        File::open(path)
+            .map(BufReader::new)
+            .with_context(|| format!("Failed to read {}", path.display()))
+    };
+    let chain = pemfile::certs(&mut open(&tls.certificate)?)
+        .ok()
+        .filter(|chain| !chain.is_empty())
+        .with_context(|| format!("No certificates in {}", tls.certificate.display()))?;
+    let key = pemfile::pkcs8_private_keys(&mut open(&tls.private_key)?)
+        .ok()
+        .filter(|keys| !keys.is_empty())
+        .or_else(|| pemfile::rsa_private_keys(&mut open(&tls.private_key).ok()?).ok())
+        .and_then(|keys| keys.into_iter().next())
+        .with_context(|| {
+            format!(
+                "No PKCS#8 or RSA private key in {}",
+                tls.private_key.display()
+            )
+        })?;
+    let key = sign::any_supported_type(&key)
+        .ok()
+        .with_context(|| format!("Unsupported private key in {}", tls.private_key.display()))?;
+    let key = CertifiedKey::new(chain, Arc::new(key));
+    key.cross_check_end_entity_cert(None)
+        .map_err(|error| anyhow!("{}", error))
+        .with_context(|| format!("Invalid certificate in {}", tls.certificate.display()))?;
+    Ok(key)
+}
+
+impl Certificates {
+    pub fn new(tls: Tls) -> Result<Certificates> {
+        let modified = modified(&tls)?;
+        let key = load(&tls)?;
+        Ok(Certificates {
+            tls,
+            loaded: RwLock::new(Loaded { modified, key }),
+        })
+    }
+
+    /// Reloads the certificate if either file has changed since it was last loaded, keeping the
+    /// current one if the new files are unusable. Returns whether it was replaced.
+    #[instrument(skip(self), fields(certificate = ?self.tls.certificate))]
+    pub fn refresh(&self) -> Result<bool> {
+        let modified = modified(&self.tls)?;
+        if self.loaded.read().unwrap().modified == modified {
+            return Ok(false);
+        }
+        let key = load(&self.tls)?;
+        *self.loaded.write().unwrap() = Loaded { modified, key };
+        info!("Reloaded TLS certificate");
+        Ok(true)
+    }
+}
+
+impl ResolvesServerCert for Certificates {
+    fn resolve(&self, _: ClientHello) -> Option<CertifiedKey> {
+        Some(self.loaded.read().unwrap().key.clone())
+    }
+}
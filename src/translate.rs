@@ -0,0 +1,235 @@
+use crate::Article;
+use anyhow::*;
+use fnv::FnvHasher;
+use reqwest::{
+    header::{AUTHORIZATION, CONTENT_TYPE},
+    Client, Url,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    hash::Hasher,
+    sync::{LazyLock, Mutex, OnceLock},
+};
+use tracing::{instrument, trace, warn};
+
+/// Translations are remembered so articles scraped again without a store aren't sent again; past
+/// this many they are forgotten.
+const REMEMBERED: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    DeepL,
+    LibreTranslate,
+}
+
+/// The translation API from `[translate]`, shared by every scraper that translates.
+#[derive(Debug, Clone)]
+pub struct Translator {
+    pub backend: Backend,
+    pub url: Url,
+    pub api_key: Option<String>,
+}
+
+/// Which parts of an article are translated. Bodies include descriptions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TranslatedFields {
+    Headline,
+    Body,
+    #[default]
+    All,
+}
+
+/// A scraper's articles are translated into `target`, a language tag such as `en` or `en-GB`.
+#[derive(Debug, Clone)]
+pub struct Translation {
+    pub target: Cow<'static, str>,
+    pub fields: TranslatedFields,
+}
+
+#[derive(Debug, Serialize)]
+struct DeepLRequest<'a> {
+    text: [&'a str; 1],
+    target_lang: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tag_handling: Option<&'static str>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeepLResponse {
+    translations: Vec<DeepLTranslation>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeepLTranslation {
+    text: String,
+}
+
+#[derive(Debug, Serialize)]
+struct LibreTranslateRequest<'a> {
+    q: &'a str,
+    source: &'static str,
+    target: &'a str,
+    format: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    api_key: Option<&'a str>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LibreTranslateResponse {
+    translated_text: String,
+}
+
+static TRANSLATOR: OnceLock<Translator> = OnceLock::new();
+static TRANSLATED: LazyLock<Mutex<HashMap<u64, String>>> = LazyLock::new(Default::default);
+
+pub fn configure(translator: Translator) {
+    TRANSLATOR.set(translator).ok();
+}
+
+pub fn get() -> Option<&'static Translator> {
+    TRANSLATOR.get()
+}
+
+impl Backend {
+    /// DeepL's free keys end in `:fx` and only work against the free API.
+    pub fn default_url(self, api_key: Option<&str>) -> &'static str {
+        match self {
+            Backend::DeepL if api_key.is_some_and(|key| key.ends_with(":fx")) => {
+                "https://api-free.deepl.com/"
+            }
+            Backend::DeepL => "https://api.deepl.com/",
+            Backend::LibreTranslate => "https://libretranslate.com/",
+        }
+    }
+}
+
+impl Translation {
+    /// The primary subtag of the target, as articles are tagged with it.
+    fn language(&self) -> String {
+        self.target
+            .split(['-', '_'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase()
+    }
+}
+
+fn key(translation: &Translation, html: bool, text: &str) -> u64 {
+    let mut hasher = FnvHasher::default();
+    hasher.write(translation.target.as_bytes());
+    hasher.write_u8(html as u8);
+    hasher.write(text.as_bytes());
+    hasher.finish()
+}
+
+impl Translator {
+    async fn request(&self, client: &Client, target: &str, html: bool, text: &str) -> Result<String> {
+        let request = match self.backend {
+            Backend::DeepL => client
+                .post(self.url.join("v2/translate")?)
+                .header(
+                    AUTHORIZATION,
+                    format!("DeepL-Auth-Key {}", self.api_key.as_deref().unwrap_or_default()),
+                )
+                .body(serde_json::to_string(&DeepLRequest {
+                    text: [text],
+                    target_lang: target.to_ascii_uppercase(),
+                    tag_handling: html.then_some("html"),
+                })?),
+            Backend::LibreTranslate => {
+                client
+                    .post(self.url.join("translate")?)
+                    .body(serde_json::to_string(&LibreTranslateRequest {
+                        q: text,
+                        source: "auto",
+                        target,
+                        format: if html { "html" } else { "text" },
+                        api_key: self.api_key.as_deref(),
+                    })?)
+            }
+        };
+        let response = request
+            .header(CONTENT_TYPE, "application/json")
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        match self.backend {
+            Backend::DeepL => serde_json::from_str::<DeepLResponse>(&response)?
+                .translations
+                .into_iter()
+                .next()
+                .map(|translation| translation.text)
+                .context("Expected DeepL to return a translation"),
+            Backend::LibreTranslate => {
+                Ok(serde_json::from_str::<LibreTranslateResponse>(&response)?.translated_text)
+            }
+        }
+    }
+
+    async fn translate(
+        &self,
+        client: &Client,
+        translation: &Translation,
+        html: bool,
+        text: &str,
+    ) -> Result<String> {
+        if text.trim().is_empty() {
+            return Ok(text.to_owned());
+        }
+        let key = key(translation, html, text);
+        if let Some(translated) = TRANSLATED.lock().unwrap().get(&key) {
+            return Ok(translated.clone());
+        }
+        let translated = self.request(client, &translation.target, html, text).await?;
+        let mut translations = TRANSLATED.lock().unwrap();
+        if translations.len() >= REMEMBERED {
+            translations.clear();
+        }
+        translations.insert(key, translated.clone());
+        Ok(translated)
+    }
+
+    /// Translates an article, leaving it as it was if the backend fails. Articles already detected
+    /// to be in the target language aren't sent.
+    #[instrument(skip(self, client, article), fields(link = article.link.as_str()))]
+    pub async fn article(&self, client: &Client, translation: &Translation, article: Article) -> Article {
+        let language = translation.language();
+        if article.language.as_deref() == Some(language.as_str()) {
+            return article;
+        }
+        let translated = async {
+            let mut translated = article.clone();
+            if translation.fields != TranslatedFields::Body {
+                translated.headline = self
+                    .translate(client, translation, false, &article.headline)
+                    .await?;
+            }
+            if translation.fields != TranslatedFields::Headline {
+                translated.body = self.translate(client, translation, true, &article.body).await?;
+                if let Some(description) = &article.description {
+                    translated.description =
+                        Some(self.translate(client, translation, false, description).await?);
+                }
+                translated.language = Some(language);
+            }
+            Ok(translated)
+        };
+        match translated.await {
+            Ok(translated) => {
+                trace!("Translated article");
+                translated
+            }
+            Err::<_, Error>(error) => {
+                warn!(?error, "Failed to translate article, keeping the original");
+                article
+            }
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use anyhow::*;
+use futures::future::join_all;
+use reqwest::{Client, Url};
+use std::sync::OnceLock;
+use tracing::{instrument, trace, warn};
+
+#[derive(Debug, Clone)]
+pub struct Hub {
+    pub url: Url,
+}
+
+static HUB: OnceLock<Hub> = OnceLock::new();
+
+pub fn configure(hub: Hub) {
+    HUB.set(hub).ok();
+}
+
+pub fn hub() -> Option<&'static Url> {
+    HUB.get().map(|hub| &hub.url)
+}
+
+/// Tells the hub that each topic URL has new content, so it can fetch and push it to subscribers.
+#[instrument(skip(client))]
+pub async fn publish(client: &Client, topics: &[String]) {
+    let hub = match hub() {
+        Some(hub) => hub,
+        None => return,
+    };
+    join_all(topics.iter().map(|topic| async move {
+        let published = async {
+            client
+                .post(hub.clone())
+                .form(&[("hub.mode", "publish"), ("hub.url", topic)])
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok(())
+        };
+        match published.await {
+            Ok(()) => trace!(topic = topic.as_str(), "Published to hub"),
+            Err::<_, Error>(error) => {
+                warn!(?error, topic = topic.as_str(), "Failed to publish to hub")
+            }
+        }
+    }))
+    .await;
+}
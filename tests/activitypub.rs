@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use chrono::TimeZone;
+use chrono_tz::Europe::Dublin;
+use news_rss::{
+    activitypub::{actor_feed, internal, username, ActivityPub},
+    auth::{self, Auth},
+    render::Channel,
+    Article, Feed,
+};
+
+fn activitypub() -> ActivityPub {
+    ActivityPub::new(
+        "https://news.example/",
+        include_str!("fixtures/activitypub/key.pem"),
+    )
+    .unwrap()
+}
+
+#[test]
+fn webfinger() {
+    let activitypub = activitypub();
+    assert_eq!(username("Irish Times"), "irish_times");
+    assert_eq!(
+        activitypub.resource("acct:rte@news.example").as_deref(),
+        Some("rte")
+    );
+    assert_eq!(
+        activitypub
+            .resource("https://news.example/ap/rte")
+            .as_deref(),
+        Some("rte")
+    );
+    assert_eq!(activitypub.resource("acct:rte@elsewhere.example"), None);
+    let jrd = activitypub.webfinger("rte");
+    assert_eq!(jrd["subject"], "acct:rte@news.example");
+    assert_eq!(jrd["links"][0]["href"], "https://news.example/ap/rte");
+}
+
+#[test]
+fn actor() {
+    let actor = activitypub().actor("rte", &Channel::new("RTE"));
+    assert_eq!(actor["id"], "https://news.example/ap/rte");
+    assert_eq!(actor["inbox"], "https://news.example/ap/rte/inbox");
+    assert_eq!(
+        actor["publicKey"]["id"],
+        "https://news.example/ap/rte#main-key"
+    );
+    assert!(actor["publicKey"]["publicKeyPem"]
+        .as_str()
+        .unwrap()
+        .starts_with("-----BEGIN PUBLIC KEY-----\nMIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEA"));
+}
+
+#[test]
+fn protected() {
+    auth::configure(Auth {
+        tokens: vec!["secret".to_owned()],
+        users: Vec::new(),
+        feeds: vec!["RTE".to_owned()],
+    });
+    let article = Article {
+        headline: "Headline".to_owned(),
+        link: "https://news.example/article".parse().unwrap(),
+        body: "<p>Body</p>".to_owned(),
+        description: None,
+        language: None,
+        image: None,
+        images: Vec::new(),
+        author: None,
+        categories: Vec::new(),
+        date: Dublin.with_ymd_and_hms(2024, 10, 1, 12, 0, 0).unwrap(),
+        updated: None,
+        guid: None,
+        alternates: Vec::new(),
+    };
+    let feeds: HashMap<String, Feed> = ["RTE", "Guardian"]
+        .iter()
+        .map(|&name| {
+            let feed = Feed::new(Channel::new(name), vec![article.clone()]);
+            (name.to_owned(), feed)
+        })
+        .collect();
+    assert!(actor_feed(&feeds, "rte", &[]).is_none());
+    let (name, feed) = actor_feed(&feeds, "guardian", &[]).unwrap();
+    assert_eq!(name, "Guardian");
+    let outbox = activitypub().outbox("guardian", &feed.articles);
+    assert_eq!(outbox["orderedItems"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn internal_addresses() {
+    for address in [
+        "127.0.0.1",
+        "10.1.2.3",
+        "192.168.0.1",
+        "169.254.169.254",
+        "0.0.0.0",
+        "::1",
+        "fd00::1",
+        "fe80::1",
+        "::ffff:127.0.0.1",
+    ] {
+        assert!(internal(address.parse().unwrap()), "{} is external", address);
+    }
+    for address in ["93.184.215.14", "2606:2800:21f:cb07:6820:80da:af6b:8b2c"] {
+        assert!(!internal(address.parse().unwrap()), "{} is internal", address);
+    }
+}
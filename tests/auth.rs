@@ -0,0 +1,66 @@
+use news_rss::{
+    auth::{Auth, User},
+    feed::VirtualFeed,
+};
+
+fn auth(feeds: &[&str]) -> Auth {
+    Auth {
+        tokens: vec!["secret".to_owned()],
+        users: vec![User {
+            username: "me".to_owned(),
+            password: "hunter2".to_owned(),
+        }],
+        feeds: feeds.iter().map(|feed| (*feed).to_owned()).collect(),
+    }
+}
+
+fn virtual_feed(name: &str, sources: &[&str]) -> VirtualFeed {
+    VirtualFeed {
+        name: name.to_owned(),
+        description: None,
+        sources: sources.iter().map(|source| (*source).to_owned()).collect(),
+        keywords: vec!["budget".to_owned()],
+        patterns: Vec::new(),
+    }
+}
+
+#[test]
+fn protects() {
+    let all = auth(&[]);
+    assert!(all.protects(Some("RTE"), &[]));
+    assert!(all.protects(None, &[]));
+    let some = auth(&["RTE"]);
+    assert!(some.protects(Some("rte"), &[]));
+    assert!(!some.protects(Some("Guardian"), &[]));
+    assert!(some.protects(Some("all"), &[]));
+    assert!(some.protects(None, &[]));
+}
+
+#[test]
+fn virtual_feeds() {
+    let some = auth(&["RTE"]);
+    let virtual_feeds = [
+        virtual_feed("Irish", &["rte", "Irish Times"]),
+        virtual_feed("British", &["BBC", "Guardian"]),
+        virtual_feed("Everything", &[]),
+    ];
+    assert!(some.protects(Some("irish"), &virtual_feeds));
+    assert!(!some.protects(Some("British"), &virtual_feeds));
+    assert!(some.protects(Some("Everything"), &virtual_feeds));
+}
+
+#[test]
+fn authorized() {
+    let auth = auth(&[]);
+    assert!(auth.authorized(Some("Bearer secret"), None));
+    assert!(auth.authorized(None, Some("secret")));
+    // me:hunter2
+    assert!(auth.authorized(Some("Basic bWU6aHVudGVyMg=="), None));
+    assert!(!auth.authorized(None, None));
+    assert!(!auth.authorized(Some("Bearer wrong"), Some("secret")));
+    assert!(!auth.authorized(None, Some("")));
+    // me:wrong
+    assert!(!auth.authorized(Some("Basic bWU6d3Jvbmc="), None));
+    assert!(!auth.authorized(Some("Basic not base64"), None));
+    assert!(!auth.authorized(Some("Digest secret"), None));
+}
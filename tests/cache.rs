@@ -0,0 +1,38 @@
+use news_rss::cache::lifetime;
+use reqwest::header::{HeaderMap, HeaderValue, CACHE_CONTROL, EXPIRES};
+use std::time::Duration;
+
+fn headers(pairs: &[(reqwest::header::HeaderName, &'static str)]) -> HeaderMap {
+    pairs
+        .iter()
+        .map(|(name, value)| (name.clone(), HeaderValue::from_static(value)))
+        .collect()
+}
+
+#[test]
+fn lifetimes() {
+    assert_eq!(lifetime(&HeaderMap::new()), Some(Duration::ZERO));
+    assert_eq!(
+        lifetime(&headers(&[(CACHE_CONTROL, "public, max-age=600")])),
+        Some(Duration::from_secs(600))
+    );
+    assert_eq!(
+        lifetime(&headers(&[(CACHE_CONTROL, "max-age=600, No-Cache")])),
+        Some(Duration::ZERO)
+    );
+    assert_eq!(
+        lifetime(&headers(&[(CACHE_CONTROL, "private, no-store")])),
+        None
+    );
+    assert_eq!(
+        lifetime(&headers(&[(EXPIRES, "Mon, 01 Mar 2021 12:00:00 GMT")])),
+        Some(Duration::ZERO)
+    );
+    assert_eq!(
+        lifetime(&headers(&[
+            (CACHE_CONTROL, "max-age=60"),
+            (EXPIRES, "Fri, 01 Jan 2100 00:00:00 GMT")
+        ])),
+        Some(Duration::from_secs(60))
+    );
+}
@@ -0,0 +1,114 @@
+mod common;
+
+use common::article;
+use news_rss::{
+    cluster::{self, Clustering},
+    feed,
+    render::{Channel, Format},
+    Feed,
+};
+
+fn feeds() -> Vec<Feed> {
+    vec![
+        Feed::new(Channel::new("RTE"), vec![
+            article(
+                "Budget 2025: tax cuts for low earners announced by Donohoe",
+                "https://rte.example/budget",
+                "<p>Minister for Finance Paschal Donohoe told the Dáil the cuts start in January.</p>",
+                10,
+            ),
+            article(
+                "Storm brings flooding to Galway",
+                "https://rte.example/storm",
+                "<p>Met Éireann issued a warning for Galway.</p>",
+                11,
+            ),
+            article(
+                "Tax cuts for low earners in budget, say unions",
+                "https://rte.example/unions",
+                "<p>Unions welcomed the measures.</p>",
+                12,
+            ),
+        ]),
+        Feed::new(Channel::new("Guardian"), vec![article(
+            "Donohoe announces budget tax cuts for low earners",
+            "https://guardian.example/budget",
+            "<p>Ireland's finance minister set out the budget.</p>",
+            11,
+        )]),
+        Feed::new(Channel::new("BBC"), vec![
+            article(
+                "Irish budget: Donohoe unveils tax cuts",
+                "https://bbc.example/budget",
+                "<p>Paschal Donohoe told the Dáil that workers would benefit.</p>",
+                12,
+            ),
+            article(
+                "Ireland beat France in Paris",
+                "https://bbc.example/rugby",
+                "<p>A late try settled it.</p>",
+                13,
+            ),
+        ]),
+    ]
+}
+
+#[test]
+fn clusters() {
+    let feeds = feeds();
+    let articles = feeds
+        .iter()
+        .flat_map(|feed| feed.articles.iter().map(move |article| (feed.name.as_str(), article)))
+        .collect::<Vec<_>>();
+    let clustering = Clustering {
+        collapse: false,
+        similarity: Clustering::DEFAULT_SIMILARITY,
+    };
+    let links = clustering
+        .clusters(&articles)
+        .into_iter()
+        .map(|cluster| {
+            cluster
+                .into_iter()
+                .map(|index| articles[index].1.link.as_str())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    // The BBC's headline shares less, but names the same people and places.
+    assert_eq!(links, [[
+        "https://rte.example/budget",
+        "https://guardian.example/budget",
+        "https://rte.example/unions",
+        "https://bbc.example/budget",
+    ]]);
+}
+
+#[test]
+fn collapsed() {
+    cluster::configure(Clustering {
+        collapse: true,
+        similarity: Clustering::DEFAULT_SIMILARITY,
+    });
+    let combined = feed::combine(&feeds());
+    let headlines = combined
+        .iter()
+        .map(|article| article.headline.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(headlines, [
+        "[BBC] Ireland beat France in Paris",
+        "[RTE] Storm brings flooding to Galway",
+        "[RTE] Budget 2025: tax cuts for low earners announced by Donohoe",
+    ]);
+    let sources = combined[2]
+        .alternates
+        .iter()
+        .map(|alternate| alternate.source.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(sources, ["Guardian", "RTE", "BBC"]);
+
+    let atom = Format::Atom.render(&Channel::new(feed::COMBINED), &combined);
+    assert!(atom.contains(r#"href="https://guardian.example/budget""#));
+    assert!(atom.contains(r#"rel="related""#));
+    let rss = Format::Rss.render(&Channel::new(feed::COMBINED), &combined);
+    assert!(rss.contains("Also reported by"));
+}
@@ -0,0 +1,21 @@
+use chrono::TimeZone;
+use chrono_tz::Europe::Dublin;
+use news_rss::Article;
+
+pub fn article(headline: &str, link: &str, body: &str, hour: u32) -> Article {
+    Article {
+        headline: headline.to_owned(),
+        link: link.parse().unwrap(),
+        body: body.to_owned(),
+        description: None,
+        language: None,
+        image: None,
+        images: Vec::new(),
+        author: None,
+        categories: Vec::new(),
+        date: Dublin.with_ymd_and_hms(2024, 10, 1, hour, 0, 0).unwrap(),
+        updated: None,
+        guid: None,
+        alternates: Vec::new(),
+    }
+}
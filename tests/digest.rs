@@ -0,0 +1,79 @@
+use chrono::{NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Europe::Dublin;
+use news_rss::{
+    digest::{Digest, Schedule},
+    smtp::{Security, Smtp},
+    Article,
+};
+
+fn digest(schedule: Schedule) -> Digest {
+    Digest {
+        smtp: Smtp {
+            host: "smtp.example".to_owned(),
+            port: 587,
+            security: Security::StartTls,
+            username: None,
+            password: None,
+            from: "news@example.com".to_owned(),
+        },
+        to: vec!["reader@example.com".to_owned()],
+        subject: "Digest".to_owned(),
+        schedule,
+        at: NaiveTime::from_hms_opt(7, 30, 0).unwrap(),
+        weekday: Weekday::Mon,
+        feeds: vec!["RTE".to_owned()],
+    }
+}
+
+#[test]
+fn schedule() {
+    // A Wednesday.
+    let now = Utc.with_ymd_and_hms(2021, 3, 3, 8, 0, 0).unwrap();
+    let daily = digest(Schedule::Daily);
+    assert_eq!(
+        daily.next(now),
+        Utc.with_ymd_and_hms(2021, 3, 4, 7, 30, 0).unwrap()
+    );
+    assert_eq!(
+        daily.next(Utc.with_ymd_and_hms(2021, 3, 3, 7, 0, 0).unwrap()),
+        Utc.with_ymd_and_hms(2021, 3, 3, 7, 30, 0).unwrap()
+    );
+    assert_eq!(
+        digest(Schedule::Weekly).next(now),
+        Utc.with_ymd_and_hms(2021, 3, 8, 7, 30, 0).unwrap()
+    );
+    assert!(daily.includes("rte"));
+    assert!(!daily.includes("Guardian"));
+}
+
+#[test]
+fn message() {
+    let article = Article {
+        headline: "Budget <announced>".to_owned(),
+        link: "https://rte.example/budget".parse().unwrap(),
+        body: "<p>The minister announced taxes</p>".to_owned(),
+        description: None,
+        language: None,
+        image: None,
+        images: Vec::new(),
+        author: None,
+        categories: Vec::new(),
+        date: Dublin.with_ymd_and_hms(2021, 3, 1, 12, 0, 0).unwrap(),
+        updated: None,
+        guid: None,
+        alternates: Vec::new(),
+    };
+    let digest = digest(Schedule::Daily);
+    assert!(digest
+        .message(&[("RTE", Vec::new()), ("Guardian", Vec::new())])
+        .is_none());
+    let message = digest
+        .message(&[("RTE", vec![article]), ("Guardian", Vec::new())])
+        .unwrap();
+    assert_eq!(message.to, ["reader@example.com"]);
+    assert!(message.html.contains("<h2>RTE</h2>"));
+    assert!(!message.html.contains("Guardian"));
+    assert!(message.html.contains(
+        r#"<a href="https://rte.example/budget">Budget &lt;announced&gt;</a><br>The minister announced taxes"#
+    ));
+}
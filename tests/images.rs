@@ -0,0 +1,81 @@
+use chrono::TimeZone;
+use chrono_tz::Europe::Dublin;
+use image::{DynamicImage, ImageFormat, RgbImage};
+use news_rss::{
+    images::{self, ImageCache, ThumbnailFormat, Thumbnails},
+    render::{self, Channel},
+    Article, Image,
+};
+use reqwest::Url;
+use std::{env, fs, io::Cursor};
+
+fn image(url: &str) -> Image {
+    Image {
+        url: Url::parse(url).unwrap(),
+        mime_type: None,
+        length: None,
+    }
+}
+
+#[test]
+fn proxied() {
+    let dir = env::temp_dir().join(format!("news-rss-images-{}", std::process::id()));
+    fs::remove_dir_all(&dir).ok();
+    let cache = ImageCache::open(&dir).unwrap().with_thumbnails(Thumbnails {
+        width: 320,
+        format: ThumbnailFormat::Jpeg,
+    });
+    let mut png = Vec::new();
+    DynamicImage::ImageRgb8(RgbImage::new(800, 400))
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .unwrap();
+    let cached = image("https://example.com/lead.png");
+    cache.put(&cached.url, "image/png", &png);
+    images::configure(cache);
+    let images = images::get().unwrap();
+
+    let key = images::key(&cached.url);
+    let loaded = images.load(&key).unwrap();
+    assert_eq!(loaded.mime_type, "image/png");
+    assert_eq!(loaded.body, png);
+    assert!(images.load("../../etc/passwd").is_none());
+    let thumbnail = images.load_thumbnail(&key).unwrap();
+    assert_eq!(thumbnail.mime_type, "image/jpeg");
+    let decoded = image::load_from_memory(&thumbnail.body).unwrap();
+    assert_eq!((decoded.width(), decoded.height()), (320, 160));
+
+    let article = Article {
+        headline: "Budget".to_owned(),
+        link: Url::parse("https://example.com/budget").unwrap(),
+        body: String::new(),
+        description: None,
+        language: None,
+        image: Some(cached),
+        images: vec![image("https://example.com/inline.png")],
+        author: None,
+        categories: Vec::new(),
+        date: Dublin.with_ymd_and_hms(2021, 3, 2, 9, 30, 0).unwrap(),
+        updated: None,
+        guid: None,
+        alternates: Vec::new(),
+    };
+    let channel = Channel {
+        images: Some("https://news.example.com/img".to_owned()),
+        ..Channel::new("Test")
+    };
+    let rss = render::rss(&channel, &[article]);
+    assert!(rss.contains(&format!(r#"url="https://news.example.com/img/{}""#, key)));
+    let thumbnail = rss
+        .split("<media:thumbnail ")
+        .nth(1)
+        .and_then(|thumbnail| thumbnail.split('>').next())
+        .unwrap();
+    assert!(thumbnail.contains(&format!(
+        r#"url="https://news.example.com/img/{}/thumbnail""#,
+        key
+    )));
+    assert!(thumbnail.contains(r#"width="320""#) && thumbnail.contains(r#"height="160""#));
+    // Images that were never cached still link to their origin.
+    assert!(rss.contains(r#"url="https://example.com/inline.png""#));
+    fs::remove_dir_all(&dir).ok();
+}
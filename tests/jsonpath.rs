@@ -0,0 +1,43 @@
+use news_rss::JsonPath;
+use serde_json::json;
+
+#[test]
+fn select() {
+    let value = json!({
+        "data": {
+            "items": [
+                {"title": "One", "tags": ["a", "b"], "author": {"name": "Ann"}},
+                {"title": "Two", "tags": "c", "meta": {"author": {"name": "Bob"}}},
+            ]
+        }
+    });
+    let path = |path: &str| path.parse::<JsonPath>().unwrap();
+    assert_eq!(path("$.data.items[*]").select(&value).len(), 2);
+    assert_eq!(
+        path("data.items[-1].title").string(&value).as_deref(),
+        Some("Two")
+    );
+    assert_eq!(
+        path("$['data'][\"items\"][0].title")
+            .string(&value)
+            .as_deref(),
+        Some("One")
+    );
+    assert_eq!(
+        path("$.data.items[*].tags").strings(&value),
+        ["a", "b", "c"]
+    );
+    assert_eq!(path("$..name").strings(&value), ["Ann", "Bob"]);
+    assert!(path("$.data.missing[0]").select(&value).is_empty());
+}
+
+#[test]
+fn invalid() {
+    for path in ["$.data.", "$[0", "$[x]", "$items"] {
+        assert!(
+            path.parse::<JsonPath>().is_err(),
+            "{:?} should not parse",
+            path
+        );
+    }
+}
@@ -0,0 +1,49 @@
+use news_rss::Plugin;
+
+fn plugin(wat: &str) -> Plugin {
+    let wasm = wat::parse_str(wat).unwrap();
+    Plugin::new("Plugin", "https://news.example/latest".parse().unwrap(), &wasm).unwrap()
+}
+
+#[test]
+fn extract() {
+    let plugin = plugin(include_str!("fixtures/plugin/plugin.wat"));
+    let links = plugin.links("<html></html>").unwrap();
+    assert_eq!(
+        links.iter().map(|link| link.as_str()).collect::<Vec<_>>(),
+        ["https://news.example/news/one", "https://elsewhere.example/two"]
+    );
+    // Input larger than the module's initial memory has to be allocated for.
+    let page = "<p>Text</p>".repeat(10_000);
+    let article = plugin.extract(&page).unwrap();
+    assert_eq!(article.headline, "One");
+    assert_eq!(article.date.unwrap().to_rfc3339(), "2024-10-01T12:00:00+01:00");
+    assert_eq!(article.body, "<p>Body</p>");
+    assert_eq!(article.image.as_deref(), Some("/one.jpg"));
+    assert_eq!(article.categories, ["News"]);
+    assert_eq!(article.author, None);
+}
+
+#[test]
+fn limits() {
+    let mut spinning = plugin(
+        r#"(module
+            (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) (i32.const 0))
+            (func (export "links") (param i32 i32) (result i64) (loop $spin (br $spin)) (unreachable)))"#,
+    );
+    spinning.fuel = 10_000;
+    assert!(spinning.links("").is_err());
+    let greedy = plugin(
+        r#"(module
+            (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) (i32.const 0))
+            (func (export "links") (param i32 i32) (result i64)
+                (if (i32.lt_s (memory.grow (i32.const 65535)) (i32.const 0)) (then (unreachable)))
+                (i64.const 0)))"#,
+    );
+    assert!(greedy.links("").is_err());
+    let missing = plugin(r#"(module (memory (export "memory") 1))"#);
+    assert!(missing.extract("").is_err());
+    assert!(Plugin::new("Plugin", "https://news.example/".parse().unwrap(), b"not wasm").is_err());
+}
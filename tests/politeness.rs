@@ -0,0 +1,22 @@
+use news_rss::{
+    client::USER_AGENT,
+    politeness::{crawl_delay, product_token},
+};
+use std::time::Duration;
+
+#[test]
+fn crawl_delays() {
+    assert_eq!(crawl_delay("2.5"), Some(Duration::from_millis(2500)));
+    assert_eq!(crawl_delay("0"), Some(Duration::ZERO));
+    assert_eq!(crawl_delay("86400"), Some(Duration::from_secs(60)));
+    for invalid in ["-1", "NaN", "inf", "1e400", "soon", ""] {
+        assert_eq!(crawl_delay(invalid), None, "{}", invalid);
+    }
+}
+
+#[test]
+fn product_tokens() {
+    assert_eq!(product_token(USER_AGENT), "news-rss");
+    assert_eq!(product_token("MyReader/2.0 (+https://reader.example)"), "myreader");
+    assert_eq!(product_token("Archiver"), "archiver");
+}
@@ -0,0 +1,172 @@
+mod common;
+
+use chrono::TimeZone;
+use chrono_tz::Europe::Dublin;
+use common::article;
+use news_rss::{
+    render::{accepts_gzip, Channel, Format, Paging},
+    Article,
+};
+
+#[test]
+fn archive_links() {
+    let channel = Channel {
+        paging: Some(Paging {
+            archive: "https://news.example/archive/rte".to_owned(),
+            current: "https://news.example/rte".to_owned(),
+            page: 2,
+            pages: 3,
+        }),
+        ..Channel::new("RTE")
+    };
+    let atom = Format::Atom.render(&channel, &[]);
+    for (rel, page) in [
+        ("self", "2"),
+        ("first", "3"),
+        ("last", "1"),
+        ("previous", "3"),
+        ("next", "1"),
+        ("prev-archive", "1"),
+        ("next-archive", "3"),
+    ] {
+        let link = format!(
+            r#"<link href="https://news.example/archive/rte.atom?page={}" rel="{}""#,
+            page, rel
+        );
+        assert!(atom.contains(&link), "Missing {} in {}", link, atom);
+    }
+    assert!(atom.contains(r#"<link href="https://news.example/rte.atom" rel="current"/>"#));
+    assert!(atom.contains("<fh:archive>"));
+
+    let filling = Channel {
+        paging: channel.paging.clone().map(|paging| Paging { page: 3, ..paging }),
+        ..channel
+    };
+    let atom = Format::Atom.render(&filling, &[]);
+    assert!(atom.contains(
+        r#"<link href="https://news.example/archive/rte.atom?page=2" rel="prev-archive"/>"#
+    ));
+    assert!(!atom.contains("next-archive"));
+    assert!(!atom.contains("fh:archive"));
+
+    let live = Channel {
+        archive: Some("https://news.example/archive/rte".to_owned()),
+        ..Channel::new("RTE")
+    };
+    let atom = Format::Atom.render(&live, &[]);
+    assert!(atom.contains(
+        r#"<link href="https://news.example/archive/rte.atom" rel="prev-archive"/>"#
+    ));
+    assert!(!atom.contains("fh:archive"));
+}
+
+#[test]
+fn atom_id() {
+    let section = Channel {
+        title: "RTE - Sport & Leisure".to_owned(),
+        slug: "rte/sport & leisure".to_owned(),
+        ..Channel::new("RTE")
+    };
+    let atom = Format::Atom.render(&section, &[]);
+    assert!(
+        atom.contains("<id>urn:news-rss:rte-sport---leisure</id>"),
+        "{}",
+        atom
+    );
+    let renamed = Channel {
+        title: "RTÉ News".to_owned(),
+        ..Channel::new("RTE")
+    };
+    assert!(Format::Atom
+        .render(&renamed, &[])
+        .contains("<id>urn:news-rss:rte</id>"));
+}
+
+#[test]
+fn websub_hub() {
+    let channel = Channel {
+        self_link: Some("https://news.example/rte".to_owned()),
+        hub: Some("https://hub.example/".to_owned()),
+        ..Channel::new("RTE")
+    };
+    assert!(Format::Atom
+        .render(&channel, &[])
+        .contains(r#"<link href="https://hub.example/" rel="hub"/>"#));
+    let rss = Format::Rss.render(&channel, &[]);
+    assert!(rss.contains(r#"href="https://hub.example/""#) && rss.contains(r#"rel="hub""#));
+    assert!(Format::Json
+        .render(&channel, &[])
+        .contains(r#""hubs":[{"type":"WebSub","url":"https://hub.example/"}]"#));
+
+    let unlinked = Channel {
+        self_link: None,
+        ..channel
+    };
+    assert!(!Format::Atom.render(&unlinked, &[]).contains("hub"));
+}
+
+#[test]
+fn negotiate() {
+    assert_eq!(Format::negotiate(""), Format::Rss);
+    assert_eq!(Format::negotiate("text/html, */*"), Format::Rss);
+    assert_eq!(Format::negotiate("application/atom+xml"), Format::Atom);
+    assert_eq!(
+        Format::negotiate("application/rss+xml;q=0.5, application/feed+json;q=0.9"),
+        Format::Json
+    );
+    assert_eq!(
+        Format::negotiate("application/atom+xml, application/rss+xml"),
+        Format::Atom
+    );
+    assert_eq!(
+        Format::negotiate("application/atom+xml;q=0, text/xml;q=0.1"),
+        Format::Rss
+    );
+}
+
+#[test]
+fn stylesheet() {
+    let channel = Channel::new("RTE");
+    for format in [Format::Rss, Format::Atom] {
+        let xml = format.render(&channel, &[]);
+        let instruction = r#"<?xml-stylesheet type="text/xsl" href="/feed.xsl"?>"#;
+        assert!(xml.contains(instruction), "Missing stylesheet in {}", xml);
+        assert!(xml.find("<?xml ") < xml.find(instruction));
+    }
+    assert!(Format::Rss
+        .render(&channel, &[])
+        .parse::<rss::Channel>()
+        .is_ok());
+    assert!(!Format::Json
+        .render(&channel, &[])
+        .contains("xml-stylesheet"));
+}
+
+#[test]
+fn last_build_date() {
+    let channel = Channel::new("RTE");
+    let article = Article {
+        updated: Some(Dublin.with_ymd_and_hms(2024, 10, 2, 9, 30, 0).unwrap()),
+        ..article("Budget", "https://rte.example/budget", "<p>Taxes</p>", 12)
+    };
+    let rss = Format::Rss
+        .render(&channel, &[article])
+        .parse::<rss::Channel>()
+        .unwrap();
+    assert_eq!(
+        rss.last_build_date(),
+        Some("Wed, 2 Oct 2024 09:30:00 +0100")
+    );
+}
+
+#[test]
+fn gzip_negotiation() {
+    assert!(accepts_gzip("gzip"));
+    assert!(accepts_gzip("br;q=1.0, gzip;q=0.8, *;q=0.1"));
+    assert!(accepts_gzip("*"));
+    assert!(accepts_gzip("x-gzip"));
+    assert!(!accepts_gzip("identity"));
+    assert!(!accepts_gzip("gzip;q=0"));
+    assert!(!accepts_gzip("gzip; q=0.000, *"));
+    assert!(!accepts_gzip(""));
+}
@@ -0,0 +1,34 @@
+use news_rss::RateLimited;
+use reqwest::{
+    header::{HeaderMap, HeaderValue, RETRY_AFTER},
+    StatusCode,
+};
+use std::time::Duration;
+
+fn retry_after(status: StatusCode, value: Option<&'static str>) -> Option<Duration> {
+    let mut headers = HeaderMap::new();
+    if let Some(value) = value {
+        headers.insert(RETRY_AFTER, HeaderValue::from_static(value));
+    }
+    RateLimited::from_response(status, &headers).map(|limited| limited.retry_after)
+}
+
+#[test]
+fn retry_afters() {
+    assert_eq!(
+        retry_after(StatusCode::TOO_MANY_REQUESTS, Some("120")),
+        Some(Duration::from_secs(120))
+    );
+    assert!(
+        retry_after(StatusCode::SERVICE_UNAVAILABLE, Some("Fri, 01 Jan 2100 00:00:00 GMT"))
+            .is_some_and(|wait| wait > Duration::from_secs(365 * 24 * 60 * 60))
+    );
+    // Dates that have passed mean trying again straight away.
+    assert_eq!(
+        retry_after(StatusCode::TOO_MANY_REQUESTS, Some("Mon, 01 Mar 2021 12:00:00 GMT")),
+        Some(Duration::ZERO)
+    );
+    assert_eq!(retry_after(StatusCode::TOO_MANY_REQUESTS, None), None);
+    assert_eq!(retry_after(StatusCode::TOO_MANY_REQUESTS, Some("soon")), None);
+    assert_eq!(retry_after(StatusCode::INTERNAL_SERVER_ERROR, Some("120")), None);
+}
@@ -0,0 +1,59 @@
+use chrono::{TimeZone, Utc};
+use news_rss::{Adaptive, Stagger};
+use std::time::Duration;
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+
+#[test]
+fn slots() {
+    let stagger = Stagger {
+        offset: Some(Duration::from_secs(10 * 60)),
+        jitter: 0.0,
+    };
+    let at = |hour, minute| Utc.with_ymd_and_hms(2024, 11, 12, hour, minute, 0).unwrap();
+    assert_eq!(stagger.delay("RTE", HOUR, at(9, 30)), Duration::from_secs(40 * 60));
+    // A slot less than half an interval away is skipped, so scrapes don't come close together.
+    assert_eq!(stagger.delay("RTE", HOUR, at(9, 50)), Duration::from_secs(80 * 60));
+    assert_eq!(stagger.delay("RTE", HOUR, at(9, 10)), HOUR);
+    // Offsets wrap around the interval.
+    let wrapped = Stagger {
+        offset: Some(Duration::from_secs(70 * 60)),
+        ..stagger
+    };
+    assert_eq!(wrapped.delay("RTE", HOUR, at(9, 30)), Duration::from_secs(40 * 60));
+}
+
+#[test]
+fn spread() {
+    let stagger = Stagger {
+        offset: None,
+        jitter: 0.0,
+    };
+    let now = Utc.with_ymd_and_hms(2024, 11, 12, 9, 0, 0).unwrap();
+    let delay = |name| stagger.delay(name, HOUR, now);
+    assert_eq!(delay("RTE"), delay("RTE"));
+    assert_ne!(delay("RTE"), delay("BBC"));
+    let jittered = Stagger {
+        jitter: 0.5,
+        ..stagger
+    }
+    .delay("RTE", HOUR, now);
+    assert!(jittered >= delay("RTE") && jittered < delay("RTE") + HOUR / 2);
+}
+
+#[test]
+fn adaptive() {
+    let adaptive = Adaptive {
+        min_interval: Duration::from_secs(10 * 60),
+        max_interval: 4 * HOUR,
+    };
+    let at = |hour, minute| Utc.with_ymd_and_hms(2024, 11, 12, hour, minute, 0).unwrap();
+    let busy = (0..6).map(|i| at(9, i * 10));
+    assert_eq!(adaptive.interval(busy.clone(), at(10, 0)), Duration::from_secs(10 * 60));
+    // Posts every 40 minutes get checked every 20, until the source goes quiet.
+    let steady = (0..6).map(|i| at(9, 0) + chrono::Duration::minutes(i * 40));
+    assert_eq!(adaptive.interval(steady.clone(), at(12, 30)), Duration::from_secs(20 * 60));
+    assert_eq!(adaptive.interval(steady, at(15, 20)), Duration::from_secs(90 * 60));
+    assert_eq!(adaptive.interval(busy, at(23, 0)), 4 * HOUR);
+    assert_eq!(adaptive.interval(None, at(10, 0)), 4 * HOUR);
+}
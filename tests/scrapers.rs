@@ -0,0 +1,572 @@
+use chrono::{TimeZone, Utc};
+use chrono_tz::Tz;
+use news_rss::{
+    render::{Channel, Format},
+    rewrite::Field,
+    script::Script,
+    Article, DateFallback, DateParser, Extractor, Feed, GuidStrategy, Pagination, Retention, Scraper,
+    ScraperBuilder, BBC, GUARDIAN, RTE,
+};
+use std::{borrow::Cow, path::Path, time::Duration};
+
+async fn scrape(scraper: &Scraper, fixture: &str) -> Vec<Article> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(fixture);
+    let scraped = scraper.scrape_fixtures(dir).await.unwrap();
+    assert_eq!(scraped.failed, 0);
+    scraped.articles
+}
+
+#[tokio::test]
+async fn rte() {
+    let articles = scrape(&RTE, "rte").await;
+    let headlines = articles
+        .iter()
+        .map(|article| article.headline.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        headlines,
+        ["Budget measures announced", "Leaders meet at summit"]
+    );
+    assert_eq!(
+        articles[0].link.as_str(),
+        "https://www.rte.ie/news/ireland/2024/0301/1234-budget/"
+    );
+    assert_eq!(
+        articles[0].date,
+        Utc.with_ymd_and_hms(2024, 3, 1, 14, 5, 0).unwrap()
+    );
+    assert!(articles[0].body.contains("series of"));
+    assert!(articles[0]
+        .body
+        .contains(r#"href="https://www.rte.ie/news/business/""#));
+}
+
+#[tokio::test]
+async fn guardian() {
+    let articles = scrape(&GUARDIAN, "guardian").await;
+    assert_eq!(articles.len(), 1);
+    let article = &articles[0];
+    assert_eq!(article.headline, "Election result declared");
+    assert_eq!(
+        article.date,
+        Utc.with_ymd_and_hms(2024, 3, 1, 14, 5, 0).unwrap()
+    );
+    assert_eq!(article.author.as_deref(), Some("Jane Doe"));
+    assert_eq!(article.categories, ["World news"]);
+    assert_eq!(
+        article.image.as_ref().map(|image| image.url.as_str()),
+        Some("https://i.guim.co.uk/img/media/election.jpg")
+    );
+    assert!(article.body.contains("The count has concluded"));
+}
+
+#[tokio::test]
+async fn bbc() {
+    let articles = scrape(&BBC, "bbc").await;
+    assert_eq!(articles.len(), 1);
+    let article = &articles[0];
+    assert_eq!(article.headline, "Storm warning issued");
+    assert_eq!(
+        article.link.as_str(),
+        "https://www.bbc.co.uk/news/articles/c0000001"
+    );
+    assert_eq!(
+        article.date,
+        Utc.with_ymd_and_hms(2024, 3, 1, 14, 5, 0).unwrap()
+    );
+    assert_eq!(
+        article.image.as_ref().map(|image| image.url.as_str()),
+        Some("https://ichef.bbci.co.uk/news/storm.jpg")
+    );
+    assert!(article.body.contains("amber warning"));
+}
+
+fn local() -> ScraperBuilder {
+    Scraper::builder()
+        .name("Paginated")
+        .base_url("https://example.com/")
+        .news_url("/news/")
+        .article_selector("article")
+        .headline_selector("h2")
+        .link_selector("a")
+        .date_selector("time")
+        .date_attribute("datetime")
+        .parse_date(DateParser::Format {
+            formats: Cow::Borrowed(&[Cow::Borrowed("%Y-%m-%dT%H:%M:%S")]),
+            fallback: DateFallback::Fail,
+        })
+        .body_selector("main")
+}
+
+fn paginated(pagination: Pagination) -> Scraper {
+    local().pagination(pagination).build().unwrap()
+}
+
+#[tokio::test]
+async fn next_page_selector() {
+    let scraper = paginated(Pagination::Next {
+        selector: r#"a[rel="next"]"#.into(),
+        max_pages: 10,
+    });
+    let articles = scrape(&scraper, "paginated").await;
+    let headlines = articles
+        .iter()
+        .map(|article| article.headline.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(headlines, ["Story a1", "Story a2", "Story a3"]);
+}
+
+#[tokio::test]
+async fn page_url_template() {
+    let scraper = paginated(Pagination::Template {
+        template: "/news/page/{page}".into(),
+        max_pages: 2,
+    });
+    let articles = scrape(&scraper, "paginated").await;
+    let headlines = articles
+        .iter()
+        .map(|article| article.headline.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(headlines, ["Story a1", "Story a2"]);
+}
+
+#[tokio::test]
+async fn article_next_page_selector() {
+    let scraper = local()
+        .name("Multipage")
+        .article_next_page_selector("a.next")
+        .build()
+        .unwrap();
+    let articles = scrape(&scraper, "multipage").await;
+    assert_eq!(articles.len(), 1);
+    assert_eq!(
+        articles[0].body,
+        "<main><p>Part 1.</p></main><main><p>Part 2.</p></main><main><p>Part 3.</p></main>"
+    );
+}
+
+#[tokio::test]
+async fn prefer_amp() {
+    let scraper = local().name("AMP").prefer_amp(true).build().unwrap();
+    let articles = scrape(&scraper, "amp").await;
+    assert_eq!(articles.len(), 1);
+    assert_eq!(articles[0].link.as_str(), "https://example.com/news/story");
+    assert_eq!(
+        articles[0].body,
+        r#"<main><p>Static <a href="https://example.com/news/amp/related">AMP</a> content.</p></main>"#
+    );
+}
+
+#[tokio::test]
+async fn canonical_links() {
+    let scraper = local().name("Canonical").build().unwrap();
+    let links = scrape(&scraper, "canonical")
+        .await
+        .into_iter()
+        .map(|article| article.link.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        links,
+        [
+            "https://example.com/news/one?id=3",
+            "https://example.com/stories/two",
+            "https://example.com/news/three",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn guid_strategies() {
+    let guids = |articles: Vec<Article>| {
+        articles
+            .iter()
+            .map(|article| article.id().to_owned())
+            .collect::<Vec<_>>()
+    };
+    let link = local().name("GUID").build().unwrap();
+    assert_eq!(
+        guids(scrape(&link, "guid").await),
+        [
+            "https://example.com/news/2024/one",
+            "https://example.com/news/2024/two"
+        ]
+    );
+    let selector = local()
+        .name("GUID")
+        .guid(GuidStrategy::Selector {
+            selector: r#"meta[name="article:id"]"#.into(),
+            attribute: Some("content".into()),
+        })
+        .build()
+        .unwrap();
+    assert_eq!(
+        guids(scrape(&selector, "guid").await),
+        [
+            "urn:news-rss:guid:1234",
+            "https://example.com/news/2024/two"
+        ]
+    );
+    let hash = local()
+        .name("GUID")
+        .guid(GuidStrategy::Hash)
+        .build()
+        .unwrap();
+    let hashed = guids(scrape(&hash, "guid").await);
+    assert_eq!(hashed, guids(scrape(&hash, "guid").await));
+    assert_ne!(hashed[0], hashed[1]);
+    assert!(hashed
+        .iter()
+        .all(|guid| guid.starts_with("urn:news-rss:guid:")));
+}
+
+#[tokio::test]
+async fn undated_hash_guids() {
+    let scraper = local()
+        .name("Undated")
+        .parse_date(DateParser::Format {
+            formats: Cow::Borrowed(&[]),
+            fallback: DateFallback::Now,
+        })
+        .guid(GuidStrategy::Hash)
+        .build()
+        .unwrap();
+    let first = scrape(&scraper, "undated").await;
+    // The scrape time stands in for the date, so it has to change between the scrapes.
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let second = scrape(&scraper, "undated").await;
+    assert_ne!(first[0].date, second[0].date);
+    assert_eq!(first[0].guid, second[0].guid);
+    assert!(first[0].guid.is_some());
+}
+
+#[tokio::test]
+async fn updated_articles() {
+    let scraper = local().name("Updates").build().unwrap();
+    let articles = scrape(&scraper, "guid").await;
+    let mut feed = Feed::new(Channel::new("Updates"), articles.clone());
+    feed.update(articles.clone(), Retention::DEFAULT);
+    assert!(feed
+        .articles
+        .iter()
+        .all(|article| article.updated.is_none()));
+    let mut changed = articles;
+    changed[0].body.push_str("<p>Correction</p>");
+    let updated = |feed: &Feed, headline: &str| {
+        feed.articles
+            .iter()
+            .find(|article| article.headline == headline)
+            .and_then(|article| article.updated)
+    };
+    feed.update(changed.clone(), Retention::DEFAULT);
+    let first = updated(&feed, "One").expect("Expected an updated timestamp");
+    assert!(updated(&feed, "Two").is_none());
+    feed.update(changed, Retention::DEFAULT);
+    assert_eq!(updated(&feed, "One"), Some(first));
+}
+
+#[tokio::test]
+async fn sorted_and_capped() {
+    let scraper = local().name("Sorted").build().unwrap();
+    let feed = Feed::new(Channel::new("Sorted"), scrape(&scraper, "guid").await);
+    let headlines = feed
+        .articles
+        .iter()
+        .map(|article| article.headline.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(headlines, ["Two", "One"]);
+    let channel = Channel {
+        max_items: Some(1),
+        ..Channel::new("Sorted")
+    };
+    let rss = Format::Rss.render(&channel, &feed.articles);
+    assert_eq!(rss.matches("<item>").count(), 1);
+    assert!(rss.contains("<title>Two</title>"));
+}
+
+#[tokio::test]
+async fn summary_only() {
+    let scraper = local()
+        .name("Summaries")
+        .image_selector("img")
+        .summary_only(true)
+        .build()
+        .unwrap();
+    // The fixture has no article pages, so fetching one would fail the scrape.
+    let articles = scrape(&scraper, "summary").await;
+    assert_eq!(articles.len(), 2);
+    assert_eq!(articles[0].headline, "One");
+    assert_eq!(
+        articles[0].date,
+        Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap()
+    );
+    assert!(articles[0]
+        .body
+        .contains(r#"href="https://example.com/news/2024/one#more""#));
+    assert_eq!(
+        articles[0].image.as_ref().map(|image| image.url.as_str()),
+        Some("https://example.com/images/one.jpg")
+    );
+    assert!(articles[1].body.is_empty());
+}
+
+#[tokio::test]
+async fn lazy_lead_images() {
+    let scraper = local()
+        .name("Lazy")
+        .image_selector("figure img")
+        .build()
+        .unwrap();
+    let articles = scrape(&scraper, "lazy").await;
+    assert_eq!(
+        articles[0].image.as_ref().map(|image| image.url.as_str()),
+        Some("https://example.com/images/lazy-large.jpg")
+    );
+    assert!(articles[1].image.is_none());
+}
+
+#[tokio::test]
+async fn descriptions() {
+    let scraper = local().name("Descriptions").build().unwrap();
+    let articles = scrape(&scraper, "descriptions").await;
+    assert_eq!(articles[0].description.as_deref(), Some("The budget, in brief."));
+    assert_eq!(articles[1].description, None);
+    assert!(articles[1].summary().starts_with("Rates rose"));
+
+    let scraper = local()
+        .name("Descriptions")
+        .summary_sentences(2)
+        .build()
+        .unwrap();
+    let articles = scrape(&scraper, "descriptions").await;
+    assert_eq!(
+        articles[1].description.as_deref(),
+        Some("Rates rose by 0.5 points, e.g. for mortgages. Lenders reacted quickly!")
+    );
+    assert_eq!(
+        articles[0].description.as_deref(),
+        Some("Ministers met on Tuesday. The budget passed.")
+    );
+    let rss = Format::Rss
+        .render(&Channel::new("Descriptions"), &articles)
+        .parse::<rss::Channel>()
+        .unwrap();
+    assert_eq!(
+        rss.items()[1].description(),
+        Some("Rates rose by 0.5 points, e.g. for mortgages. Lenders reacted quickly!")
+    );
+}
+
+#[tokio::test]
+async fn readability() {
+    let scraper = local()
+        .name("Readability")
+        .extractor(Extractor::Readability)
+        .build()
+        .unwrap();
+    // The body selector matches nothing on this page, leaving it to readability.
+    let articles = scrape(&scraper, "readability").await;
+    let body = &articles[0].body;
+    assert!(body.contains("The minister announced the budget"));
+    assert!(body.contains("did too little for renters"));
+    for junk in ["Home", "Share this", "track(", "Another story", "Great article"] {
+        assert!(!body.contains(junk), "Found {:?} in {}", junk, body);
+    }
+}
+
+#[tokio::test]
+async fn remove_selectors() {
+    let scraper = local()
+        .name("Removal")
+        .collect_images(true)
+        .remove_selector(".related-articles, .newsletter-signup")
+        .remove_selector("figure.ad")
+        .build()
+        .unwrap();
+    let articles = scrape(&scraper, "removal").await;
+    let body = &articles[0].body;
+    assert!(body.contains("The budget passed"));
+    assert!(body.contains("Renters were disappointed"));
+    for junk in ["Another story", "newsletter", "banner.png"] {
+        assert!(!body.contains(junk), "Found {:?} in {}", junk, body);
+    }
+    assert!(articles[0].image.is_none());
+}
+
+#[tokio::test]
+async fn icon() {
+    let scraper = local().name("Icon").build().unwrap();
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/icon");
+    let scraped = scraper.scrape_fixtures(dir).await.unwrap();
+    let icon = scraped.icon.unwrap();
+    assert_eq!(icon.url.as_str(), "https://example.com/static/touch.png");
+    let mut feed = Feed::new(Channel::new("Icon"), scraped.articles);
+    assert!(feed.set_icon(Some(icon.clone())));
+    assert!(!feed.set_icon(Some(icon)));
+    let rss = Format::Rss
+        .render(&feed.channel, &feed.articles)
+        .parse::<rss::Channel>()
+        .unwrap();
+    assert_eq!(
+        rss.image().map(|image| image.url()),
+        Some("https://example.com/static/touch.png")
+    );
+    let json = Format::Json.render(&feed.channel, &feed.articles);
+    assert!(json.contains(r#""favicon":"https://example.com/static/touch.png""#));
+}
+
+#[tokio::test]
+async fn languages() {
+    let scraper = local().name("Languages").build().unwrap();
+    let articles = scrape(&scraper, "languages").await;
+    let languages = articles
+        .iter()
+        .map(|article| (article.headline.as_str(), article.language.as_deref()))
+        .collect::<Vec<_>>();
+    assert_eq!(languages, [
+        ("Budget published", Some("en")),
+        ("Buiséad foilsithe", Some("ga")),
+        ("Housing plan", Some("en")),
+    ]);
+
+    let rss = Format::Rss
+        .render(&Channel::new("Languages"), &articles)
+        .parse::<rss::Channel>()
+        .unwrap();
+    assert_eq!(rss.language(), Some("en"));
+    let item = rss
+        .items()
+        .iter()
+        .find(|item| item.title() == Some("Buiséad foilsithe"))
+        .unwrap();
+    assert_eq!(item.dublin_core_ext().unwrap().languages(), ["ga"]);
+    let atom = Format::Atom.render(&Channel::new("Languages"), &articles);
+    assert!(atom.contains(r#"xml:lang="ga""#));
+
+    let scraper = local()
+        .name("Languages")
+        .detect_language(false)
+        .build()
+        .unwrap();
+    let articles = scrape(&scraper, "languages").await;
+    let languages = articles
+        .iter()
+        .map(|article| article.language.as_deref())
+        .collect::<Vec<_>>();
+    assert_eq!(languages, [Some("en"), Some("ga"), None]);
+}
+
+#[tokio::test]
+async fn rewrites() {
+    let scraper = local()
+        .name("Rewrites")
+        .author_selector(".byline")
+        .categories_selector(".tag")
+        .rewrite(Field::Headline, r"^LIVE:\s*", "")
+        .rewrite(Field::Headline, r"\s+-\s+RTÉ$", "")
+        .rewrite(Field::Headline, r"\s+", " ")
+        .rewrite(Field::Body, r#"<p class="promo">.*?</p>"#, "")
+        .rewrite(Field::Author, r"^By\b", "")
+        .rewrite(Field::Categories, r"^News - (\w+)$", "$1")
+        .build()
+        .unwrap();
+    let articles = scrape(&scraper, "rewrites").await;
+    let fields = articles
+        .iter()
+        .map(|article| {
+            (
+                article.headline.as_str(),
+                article.author.as_deref(),
+                article.categories.as_slice(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(fields, [
+        ("Budget 2025 as it happens", Some("Political Staff"), &["Politics".to_owned()][..]),
+        ("Housing plan", None, &["Housing".to_owned()][..]),
+    ]);
+    assert_eq!(articles[0].body, "<main><p>Tax cuts were announced.</p></main>");
+
+    let invalid = local().rewrite(Field::Headline, "(", "").build();
+    assert!(invalid.is_err());
+}
+
+#[tokio::test]
+async fn categories() {
+    for summary_only in [false, true] {
+        let scraper = local()
+            .name("Categories")
+            .categories_selector(".tag")
+            .summary_only(summary_only)
+            .build()
+            .unwrap();
+        let articles = scrape(&scraper, "categories").await;
+        assert_eq!(articles[0].categories, ["Politics", "Budget"]);
+    }
+}
+
+#[tokio::test]
+async fn scripts() {
+    let script = Script::new(
+        r#"
+        fn transform_headline(headline) {
+            headline.replace("LIVE:", "");
+            headline.replace(" - RTÉ", "");
+            headline.replace("\n", " ");
+            while headline.contains("  ") {
+                headline.replace("  ", " ");
+            }
+            headline.trim();
+            headline
+        }
+
+        fn transform_body(body) {
+            body.replace(`<p class="promo">Follow RTÉ News on WhatsApp</p>`, "");
+            body
+        }
+
+        fn parse_date(date) {
+            date + "+01:00"
+        }
+        "#,
+    )
+    .unwrap();
+    let scraper = local().name("Scripts").script(script).build().unwrap();
+    let articles = scrape(&scraper, "rewrites").await;
+    let headlines = articles
+        .iter()
+        .map(|article| article.headline.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(headlines, ["Budget 2025 as it happens", "Housing plan"]);
+    assert_eq!(articles[0].body, "<main><p>Tax cuts were announced.</p></main>");
+    assert_eq!(
+        articles[0].date,
+        Utc.with_ymd_and_hms(2024, 10, 1, 11, 0, 0).unwrap()
+    );
+
+    assert!(Script::new("fn transform_headline(headline) {").is_err());
+    assert!(Script::new("fn helper(text) { text }").is_err());
+    assert!(Script::new("fn parse_date(date, timezone) { date }").is_err());
+    let endless = Script::new("fn transform_headline(headline) { loop {} }").unwrap();
+    assert!(endless.headline("Headline".to_owned()).is_err());
+}
+
+#[tokio::test]
+async fn timezones() {
+    let scraper = local()
+        .name("Timezones")
+        .timezone(Tz::Europe__Dublin)
+        .build()
+        .unwrap();
+    let articles = scrape(&scraper, "timezones").await;
+    let dates = articles
+        .iter()
+        .map(|article| (article.headline.as_str(), article.date.to_rfc3339()))
+        .collect::<Vec<_>>();
+    // Dates without an offset are Irish time; the other falls through to RFC 3339.
+    assert_eq!(dates, [
+        ("Local time", "2024-07-01T10:00:00+01:00".to_owned()),
+        ("With offset", "2024-07-01T09:00:00+01:00".to_owned()),
+    ]);
+}
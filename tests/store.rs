@@ -0,0 +1,256 @@
+mod common;
+
+use chrono::{TimeZone, Utc};
+use common::article;
+use news_rss::{
+    feed::content_hash,
+    store::{LastScrape, Store},
+    Article, Image, Query,
+};
+
+fn query(query: &str) -> Query {
+    query.parse().unwrap()
+}
+
+#[test]
+fn queries() {
+    for invalid in [
+        "", "  ", "\"(*", "rain AND", "OR rain", "(rain", "rain)", "()",
+    ] {
+        assert!(invalid.parse::<Query>().is_err(), "Parsed {:?}", invalid);
+    }
+    assert_eq!(
+        query("housing AND (dublin OR cork*) \"rent caps\"").compiled(),
+        r#""housing" AND ( "dublin" OR "cork"* ) "rent caps""#
+    );
+    assert_eq!(query("and or").compiled(), r#""and" "or""#);
+}
+
+#[test]
+fn search() {
+    let store = Store::open(":memory:").unwrap();
+    store
+        .save(
+            "RTE",
+            &[
+                article(
+                    "Budget announced",
+                    "https://rte.example/budget",
+                    "<p>The minister <b>announced</b> taxes</p>",
+                    12,
+                ),
+                article(
+                    "Weather warning",
+                    "https://rte.example/weather",
+                    "<p>Rain</p>",
+                    12,
+                ),
+            ],
+        )
+        .unwrap();
+    store
+        .save(
+            "Guardian",
+            &[article(
+                "Storm",
+                "https://guardian.example/storm",
+                "<p>Heavy rain and wind</p>",
+                12,
+            )],
+        )
+        .unwrap();
+
+    let headlines = |articles: Vec<Article>| {
+        let mut headlines = articles
+            .into_iter()
+            .map(|article| article.headline)
+            .collect::<Vec<_>>();
+        headlines.sort();
+        headlines
+    };
+    assert_eq!(
+        headlines(store.search(&query("rain"), None, 10).unwrap()),
+        ["Storm", "Weather warning"]
+    );
+    assert_eq!(
+        headlines(store.search(&query("rain"), Some("RTE"), 10).unwrap()),
+        ["Weather warning"]
+    );
+    assert_eq!(
+        headlines(store.search(&query("announcing taxes"), None, 10).unwrap()),
+        ["Budget announced"]
+    );
+    assert_eq!(
+        headlines(
+            store
+                .search(&query("(wind OR taxes) NOT rain"), None, 10)
+                .unwrap()
+        ),
+        ["Budget announced"]
+    );
+    assert_eq!(
+        headlines(
+            store
+                .search(&query("\"minister announced\" annou*"), None, 10)
+                .unwrap()
+        ),
+        ["Budget announced"]
+    );
+
+    store
+        .save(
+            "RTE",
+            &[article(
+                "Sunshine",
+                "https://rte.example/weather",
+                "<p>Clear skies</p>",
+                12,
+            )],
+        )
+        .unwrap();
+    assert_eq!(
+        headlines(store.search(&query("rain"), None, 10).unwrap()),
+        ["Storm"]
+    );
+}
+
+#[test]
+fn posted() {
+    let store = Store::open(":memory:").unwrap();
+    let link = "https://rte.example/budget".parse().unwrap();
+    assert!(!store.posted("telegram:@news", &link).unwrap());
+    store.mark_posted("telegram:@news", &link).unwrap();
+    store.mark_posted("telegram:@news", &link).unwrap();
+    assert!(store.posted("telegram:@news", &link).unwrap());
+    assert!(!store.posted("telegram:@other", &link).unwrap());
+}
+
+#[test]
+fn unchanged() {
+    let store = Store::open(":memory:").unwrap();
+    let budget = article("Budget", "https://rte.example/budget", "<p>Taxes</p>", 12);
+    let weather = article("Weather", "https://rte.example/weather", "<p>Sun</p>", 12);
+    store.save("RTE", &[budget.clone(), weather.clone()]).unwrap();
+    store.save("RTE", &[budget.clone(), weather.clone()]).unwrap();
+    assert_eq!(store.load("RTE").unwrap().len(), 2);
+    assert_eq!(store.search(&query("taxes"), None, 10).unwrap().len(), 1);
+
+    let corrected = Article {
+        body: "<p>Rain</p>".to_owned(),
+        ..weather
+    };
+    store.save("RTE", &[budget, corrected]).unwrap();
+    let loaded = store.load("RTE").unwrap();
+    assert_eq!(loaded.len(), 2);
+    assert_eq!(loaded[1].body, "<p>Rain</p>");
+    assert!(store.search(&query("sun"), None, 10).unwrap().is_empty());
+    assert_eq!(store.search(&query("rain"), None, 10).unwrap().len(), 1);
+}
+
+#[test]
+fn image_probes() {
+    let image = |mime_type: Option<&str>, length| Image {
+        url: "https://rte.example/budget.jpg".parse().unwrap(),
+        mime_type: mime_type.map(str::to_owned),
+        length,
+    };
+    let probed = Article {
+        image: Some(image(Some("image/jpeg"), Some(1024))),
+        images: vec![image(Some("image/jpeg"), Some(1024))],
+        ..article("Budget", "https://rte.example/budget", "<p>Taxes</p>", 12)
+    };
+    let unprobed = Article {
+        image: Some(image(None, None)),
+        images: vec![image(None, None)],
+        ..probed.clone()
+    };
+    assert_eq!(content_hash(&probed), content_hash(&unprobed));
+    let moved = Article {
+        image: Some(Image {
+            url: "https://rte.example/budget.png".parse().unwrap(),
+            ..image(None, None)
+        }),
+        ..unprobed
+    };
+    assert_ne!(content_hash(&probed), content_hash(&moved));
+}
+
+#[test]
+fn aliases() {
+    let store = Store::open(":memory:").unwrap();
+    let listed = "https://rte.example/news/budget?section=home".parse().unwrap();
+    let canonical = "https://rte.example/budget".parse().unwrap();
+    assert!(store.get("RTE", &listed).unwrap().is_none());
+    store.alias("RTE", &listed, &canonical).unwrap();
+    store
+        .save("RTE", &[article("Budget", canonical.as_str(), "<p>Taxes</p>", 12)])
+        .unwrap();
+    let stored = store.get("RTE", &listed).unwrap().unwrap();
+    assert_eq!(stored.link, canonical);
+    assert!(store.get("RTE", &canonical).unwrap().is_some());
+    assert!(store.get("BBC", &listed).unwrap().is_none());
+}
+
+#[test]
+fn history() {
+    let store = Store::open(":memory:").unwrap();
+    let headlines = |articles: Vec<Article>| {
+        articles
+            .into_iter()
+            .map(|article| article.headline)
+            .collect::<Vec<_>>()
+    };
+    // 10:30 UTC is after 11:00 in Dublin, though it sorts before it as text.
+    let late = Article {
+        date: chrono_tz::UTC.with_ymd_and_hms(2024, 10, 1, 10, 30, 0).unwrap(),
+        ..article("Late", "https://rte.example/late", "<p>Late</p>", 12)
+    };
+    store
+        .save("RTE", &[
+            article("Early", "https://rte.example/early", "<p>Early</p>", 9),
+            late,
+            article("Middle", "https://rte.example/middle", "<p>Middle</p>", 11),
+        ])
+        .unwrap();
+    assert_eq!(store.count("RTE").unwrap(), 3);
+    assert_eq!(store.count("BBC").unwrap(), 0);
+    assert_eq!(headlines(store.history("RTE", 0, 2).unwrap()), ["Early", "Middle"]);
+    assert_eq!(headlines(store.history("RTE", 2, 2).unwrap()), ["Late"]);
+
+    // A full page is archived, so newer articles mustn't move anything on it.
+    store
+        .save("RTE", &[
+            article("Later", "https://rte.example/later", "<p>Later</p>", 13),
+            article("Latest", "https://rte.example/latest", "<p>Latest</p>", 14),
+        ])
+        .unwrap();
+    assert_eq!(store.count("RTE").unwrap(), 5);
+    assert_eq!(headlines(store.history("RTE", 0, 2).unwrap()), ["Early", "Middle"]);
+    assert_eq!(headlines(store.history("RTE", 2, 2).unwrap()), ["Late", "Later"]);
+    assert_eq!(headlines(store.history("RTE", 4, 2).unwrap()), ["Latest"]);
+}
+
+#[test]
+fn last_scrape() {
+    let store = Store::open(":memory:").unwrap();
+    assert_eq!(store.last_scrape("RTE").unwrap(), None);
+    let at = |hour| Utc.with_ymd_and_hms(2024, 10, 1, hour, 0, 0).unwrap();
+    let succeeded = LastScrape {
+        at: at(9),
+        succeeded: Some(at(9)),
+        failures: 0,
+        error: None,
+    };
+    store.record_scrape("RTE", &succeeded).unwrap();
+    assert_eq!(store.last_scrape("RTE").unwrap(), Some(succeeded));
+    // Later scrapes replace the record, keeping when the feed last worked.
+    let failed = LastScrape {
+        at: at(10),
+        succeeded: Some(at(9)),
+        failures: 1,
+        error: Some((at(10), "Timed out".to_owned())),
+    };
+    store.record_scrape("RTE", &failed).unwrap();
+    assert_eq!(store.last_scrape("RTE").unwrap(), Some(failed));
+    assert_eq!(store.last_scrape("BBC").unwrap(), None);
+}
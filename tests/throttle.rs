@@ -0,0 +1,51 @@
+use news_rss::throttle::{Limiter, Throttle};
+use std::{
+    net::IpAddr,
+    time::{Duration, Instant},
+};
+
+fn throttle() -> Throttle {
+    Throttle {
+        requests_per_minute: 2,
+        trusted_proxies: vec!["10.0.0.0/8".parse().unwrap()],
+    }
+}
+
+fn ip(address: &str) -> IpAddr {
+    address.parse().unwrap()
+}
+
+#[test]
+fn client() {
+    let throttle = throttle();
+    let proxy = Some(ip("10.0.0.1"));
+    assert_eq!(
+        throttle.client(Some(ip("203.0.113.9")), Some("198.51.100.1")),
+        Some(ip("203.0.113.9"))
+    );
+    assert_eq!(
+        throttle.client(proxy, Some("198.51.100.1, 203.0.113.9, 10.0.0.2")),
+        Some(ip("203.0.113.9"))
+    );
+    assert_eq!(
+        throttle.client(proxy, Some("10.0.0.2")),
+        Some(ip("10.0.0.2"))
+    );
+    assert_eq!(throttle.client(proxy, None), proxy);
+    assert_eq!(
+        throttle.client(None, Some("203.0.113.9")),
+        Some(ip("203.0.113.9"))
+    );
+}
+
+#[test]
+fn check() {
+    let limiter = Limiter::new(throttle());
+    let now = Instant::now();
+    let client = ip("203.0.113.9");
+    assert!(limiter.check(client, now).is_ok());
+    assert!(limiter.check(client, now).is_ok());
+    assert_eq!(limiter.check(client, now), Err(Duration::from_secs(30)));
+    assert!(limiter.check(ip("203.0.113.10"), now).is_ok());
+    assert!(limiter.check(client, now + Duration::from_secs(30)).is_ok());
+}
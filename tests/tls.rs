@@ -0,0 +1,28 @@
+use news_rss::tls::{Certificates, Tls};
+
+fn fixture(name: &str) -> std::path::PathBuf {
+    [env!("CARGO_MANIFEST_DIR"), "tests/fixtures/tls", name]
+        .iter()
+        .collect()
+}
+
+#[test]
+fn load() {
+    let certificates = Certificates::new(Tls {
+        certificate: fixture("cert.pem"),
+        private_key: fixture("key.pem"),
+    })
+    .unwrap();
+    assert!(!certificates.refresh().unwrap());
+}
+
+#[test]
+fn swapped() {
+    let error = Certificates::new(Tls {
+        certificate: fixture("key.pem"),
+        private_key: fixture("cert.pem"),
+    })
+    .err()
+    .unwrap();
+    assert!(error.to_string().starts_with("No certificates in"));
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use common::article;
+use news_rss::{
+    feed::{self, VirtualFeed},
+    render::Channel,
+    Article, Feed,
+};
+use regex::Regex;
+
+fn feeds() -> Vec<Feed> {
+    vec![
+        Feed::new(Channel::new("RTE"), vec![
+            article(
+                "Ireland to miss emissions targets",
+                "https://rte.example/emissions",
+                "<p>The EPA projections were published today.</p>",
+                9,
+            ),
+            article(
+                "Storm brings flooding to Galway",
+                "https://rte.example/storm",
+                r#"<p>Met Éireann issued a warning. <a href="https://rte.example/climate">More</a></p>"#,
+                10,
+            ),
+        ]),
+        Feed::new(Channel::new("BBC"), vec![article(
+            "Leaders gather in Baku",
+            "https://bbc.example/baku",
+            "<p>The COP29 summit opens with calls to raise climate finance.</p>",
+            11,
+        )]),
+        Feed::new(Channel::new(feed::COMBINED), vec![article(
+            "[BBC] Leaders gather in Baku",
+            "https://bbc.example/baku",
+            "<p>The COP29 summit opens with calls to raise climate finance.</p>",
+            11,
+        )]),
+    ]
+}
+
+fn climate(sources: &[&str]) -> VirtualFeed {
+    VirtualFeed {
+        name: "Climate".to_owned(),
+        description: None,
+        sources: sources.iter().map(|source| (*source).to_owned()).collect(),
+        keywords: vec!["Emissions".to_owned()],
+        patterns: vec![Regex::new(r"(?i)\bCOP ?\d+\b").unwrap()],
+    }
+}
+
+fn headlines(articles: &[Article]) -> Vec<&str> {
+    articles
+        .iter()
+        .map(|article| article.headline.as_str())
+        .collect()
+}
+
+#[test]
+fn select() {
+    // Links in the body aren't part of its text, and the combined feed's copies are left out.
+    let articles = climate(&[]).select(&feeds());
+    assert_eq!(headlines(&articles), [
+        "[BBC] Leaders gather in Baku",
+        "[RTE] Ireland to miss emissions targets",
+    ]);
+}
+
+#[test]
+fn sources() {
+    let articles = climate(&["rte"]).select(&feeds());
+    assert_eq!(headlines(&articles), ["[RTE] Ireland to miss emissions targets"]);
+}